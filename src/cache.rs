@@ -0,0 +1,115 @@
+use crate::matcher::valhalla::decode_trip;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::path::PathBuf;
+use valhalla_client::route::Trip;
+
+/// 64-bit FNV-1a, which unlike the standard library hasher is stable across releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Valhalla `trace_route` responses stored as one JSON file per request in a directory,
+/// keyed by a hash of the request manifest
+pub struct MatchCache {
+    dir: PathBuf,
+}
+
+impl MatchCache {
+    pub fn new(dir: &str) -> Result<MatchCache> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Failed to create cache directory {}: {}", dir, e))?;
+        Ok(MatchCache {
+            dir: PathBuf::from(dir),
+        })
+    }
+
+    pub fn key(manifest: &impl Serialize) -> Result<String> {
+        let json = serde_json::to_vec(manifest)?;
+        Ok(format!("{:016x}", fnv1a(&json)))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Returns the trip of the cached response, treating unreadable entries as misses
+    pub fn get(&self, key: &str) -> Option<Trip> {
+        let body = std::fs::read(self.path(key)).ok()?;
+        decode_trip(&body).ok()
+    }
+
+    /// Stores a response body as received. Writes through a temporary file so that an
+    /// interrupted run never leaves a truncated entry behind.
+    pub fn put(&self, key: &str, body: &[u8]) -> Result<()> {
+        let path = self.path(key);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, body)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    /// `trace_route` response with fields `Trip` does not model
+    fn response() -> serde_json::Value {
+        let summary = json!({
+            "length": 0.5, "time": 60, "has_toll": false, "has_highway": false,
+            "has_ferry": false, "min_lat": 42.1, "min_lon": -83.2, "max_lat": 42.2,
+            "max_lon": -83.1,
+        });
+        json!({
+            "trip": {
+                "status": 0,
+                "status_message": "OK",
+                "units": "kilometers",
+                "language": "en-US",
+                "locations": [{"lat": 42.1, "lon": -83.1}, {"lat": 42.2, "lon": -83.2}],
+                "legs": [{
+                    "maneuvers": [{
+                        "type": 0, "begin_shape_index": 0, "end_shape_index": 1,
+                        "street_names": ["Test St"], "instruction": "Go straight",
+                        "time": 60, "length": 0.5, "travel_mode": "drive",
+                        "travel_type": "car",
+                    }],
+                    "shape": "_qqhoA~t_o}C_ibE~hbE",
+                    "summary": summary,
+                }],
+                "summary": summary,
+                "admins": [{"country_code": "US", "state_code": "MI"}],
+            },
+            "id": "trajectory-1",
+        })
+    }
+
+    #[test]
+    fn test_match_cache_round_trip() {
+        let dir = tempdir().unwrap();
+        let cache = MatchCache::new(dir.path().join("cache").to_str().unwrap()).unwrap();
+
+        let key = MatchCache::key(&json!({"shape": [[42.0, -83.7]]})).unwrap();
+        assert_eq!(key, MatchCache::key(&json!({"shape": [[42.0, -83.7]]})).unwrap());
+        assert_ne!(key, MatchCache::key(&json!({"shape": [[42.0, -83.6]]})).unwrap());
+        assert_eq!(key.len(), 16);
+
+        // The body is stored as received, unmodelled fields included
+        let body = serde_json::to_vec(&response()).unwrap();
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &body).unwrap();
+        assert_eq!(std::fs::read(cache.path(&key)).unwrap(), body);
+        let trip = cache.get(&key).unwrap();
+        assert_eq!(trip.legs[0].shape.len(), 2);
+        assert!((trip.legs[0].shape[1].lat - 42.2).abs() < 1e-9);
+
+        // Entries that are not a response count as misses
+        cache.put(&key, b"{\"shape\": []}").unwrap();
+        assert!(cache.get(&key).is_none());
+    }
+}
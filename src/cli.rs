@@ -1,5 +1,10 @@
-use crate::models::config::Config;
-use clap::{Args, Parser, Subcommand};
+pub use crate::models::aggregate::H3Metric;
+use crate::models::config::{Config, ValhallaConfig};
+pub use crate::models::config::{ConfigScope, ValhallaCosting};
+pub use crate::models::route::{RouteAlgorithm, RouteWeight};
+use crate::tools::{DEFAULT_BASE_DATE, DEFAULT_TIME_ZONE, TimeBase};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Clone, Debug)]
 #[command(name = "MyApp")]
@@ -13,21 +18,56 @@ pub struct Cli {
     #[arg(long, default_value_t = String::from("./data/eved/evedb.db"), help = "Sets the database path")]
     pub db_path: String,
 
+    #[arg(long, default_value_t = String::from(DEFAULT_BASE_DATE), help = "Sets the date of day number 1 (YYYY-MM-DD)")]
+    pub base_date: String,
+
+    #[arg(long, default_value_t = String::from(DEFAULT_TIME_ZONE), help = "Sets the time zone of the source data")]
+    pub time_zone: String,
+
     #[arg(long, help = "Verbose mode on")]
     pub verbose: bool,
 
+    #[arg(long, help = "Applies this profile of the config files")]
+    pub profile: Option<String>,
+
+    #[arg(skip)]
+    pub valhalla: ValhallaConfig,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
 impl Cli {
-    pub fn load_config(&mut self, cfg: &Config) {
-        self.db_path = cfg.db_path.clone();
-        self.repo_path = cfg.repo_path.clone();
+    /// Takes the settings from the config, except those given on the command line
+    pub fn load_config(&mut self, cfg: &Config, matches: &ArgMatches) {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        if unset("db_path") {
+            self.db_path = cfg.db_path.clone();
+        }
+        if unset("repo_path") {
+            self.repo_path = cfg.repo_path.clone();
+        }
+        if unset("base_date") {
+            self.base_date = cfg.base_date.clone();
+        }
+        if unset("time_zone") {
+            self.time_zone = cfg.time_zone.clone();
+        }
+        self.valhalla = cfg.valhalla.clone();
     }
 
     pub fn get_config(&self) -> Config {
-        Config::new(self.repo_path.clone(), self.db_path.clone())
+        Config {
+            repo_path: self.repo_path.clone(),
+            db_path: self.db_path.clone(),
+            base_date: self.base_date.clone(),
+            time_zone: self.time_zone.clone(),
+            valhalla: self.valhalla.clone(),
+        }
+    }
+
+    pub fn time_base(&self) -> anyhow::Result<TimeBase> {
+        TimeBase::new(&self.base_date, &self.time_zone)
     }
 }
 
@@ -38,7 +78,35 @@ pub enum Commands {
     Build(BuildCommandArgs),
 
     #[command(about = "Map-matches the trajectories")]
-    Match,
+    Match(MatchCommandArgs),
+
+    /// runs an optional build stage
+    #[command(about = "Runs an optional build stage")]
+    Stage(StageCommandArgs),
+
+    /// builds aggregate tables
+    #[command(about = "Builds aggregate tables")]
+    Aggregate(AggregateCommandArgs),
+
+    /// exports database contents to files
+    #[command(about = "Exports database contents to files")]
+    Export(ExportCommandArgs),
+
+    /// diagnoses and repairs the matched road network
+    #[command(about = "Diagnoses and repairs the matched road network")]
+    Network(NetworkCommandArgs),
+
+    /// flags trajectories with poor map-match quality
+    #[command(about = "Flags trajectories with poor map-match quality")]
+    Validate(ValidateCommandArgs),
+
+    /// finds the shortest path between two points over the matched road network
+    #[command(about = "Finds the shortest path over the matched road network")]
+    Route(RouteCommandArgs),
+
+    /// reads and writes the config files
+    #[command(about = "Reads and writes the config files")]
+    Config(ConfigCommandArgs),
 
     /// cleans the repositories folder
     #[command(about = "Cleans the repositories folder")]
@@ -60,4 +128,359 @@ pub struct BuildCommandArgs {
 
     #[arg(long, help = "Do not clean the repositories folder after building")]
     pub no_clean: bool,
+
+    #[arg(long, help = "Splits trips on time gaps longer than this (s)")]
+    pub split_gap: Option<f64>,
+
+    #[arg(long, help = "Splits trips on position jumps longer than this (m)")]
+    pub split_jump: Option<f64>,
+
+    #[arg(long, help = "Also stores the local time of each signal")]
+    pub local_time: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct MatchCommandArgs {
+    #[arg(long, default_value_t = 200.0, help = "Removes GPS points implying a higher speed (km/h)")]
+    pub max_speed: f64,
+
+    #[arg(long, default_value_t = 6, help = "Decimal places used to collapse duplicate GPS points")]
+    pub duplicate_precision: u32,
+
+    #[arg(long, default_value_t = 0.0, help = "Minimum distance between GPS points (m)")]
+    pub min_distance: f64,
+
+    #[arg(long, help = "Do not filter the GPS points before map-matching")]
+    pub no_filter: bool,
+
+    #[arg(long, help = "Caches the Valhalla responses in this directory")]
+    pub cache_dir: Option<String>,
+
+    #[arg(long, requires = "cache_dir", help = "Matches from the cache only, without Valhalla")]
+    pub offline: bool,
+
+    #[arg(long, value_enum, default_value_t = Matcher::Valhalla, help = "Map-matcher to use")]
+    pub matcher: Matcher,
+
+    #[arg(long, default_value_t = 1, help = "Map-matching requests in flight at once")]
+    pub concurrency: usize,
+
+    #[arg(long, help = "Valhalla URL, or several separated by commas")]
+    pub valhalla_url: Option<String>,
+
+    #[arg(long, help = "Timeout of each Valhalla request (s)")]
+    pub valhalla_timeout: Option<u64>,
+
+    #[arg(long, value_enum, help = "Valhalla costing model")]
+    pub costing: Option<ValhallaCosting>,
+
+    #[arg(long, help = "Valhalla search radius around each GPS point (m)")]
+    pub search_radius: Option<f64>,
+
+    #[arg(long, help = "Valhalla GPS accuracy (m)")]
+    pub gps_accuracy: Option<f64>,
+
+    #[arg(long, default_value_t = 5.0, help = "Standard deviation of the GPS noise for the HMM matcher (m)")]
+    pub hmm_sigma: f64,
+
+    #[arg(long, default_value_t = 10.0, help = "Scale of the route detour tolerated by the HMM matcher (m)")]
+    pub hmm_beta: f64,
+
+    #[arg(long, value_enum, default_value_t = HmmNetwork::Matched, help = "Road network for the HMM matcher")]
+    pub hmm_network: HmmNetwork,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Matcher {
+    /// Valhalla's trace_route, optionally through the match cache
+    Valhalla,
+    /// built-in HMM matcher on a local road network
+    Hmm,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HmmNetwork {
+    /// the node and edge tables of an earlier match
+    Matched,
+    /// the OSM network imported with `network import-osm`
+    Osm,
+}
+
+/// The `match` arguments with every flag at its clap default
+impl Default for MatchCommandArgs {
+    fn default() -> Self {
+        match Cli::try_parse_from(["evedb", "match"]) {
+            Ok(Cli {
+                command: Some(Commands::Match(args)),
+                ..
+            }) => args,
+            _ => unreachable!("`match` has no required arguments"),
+        }
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct StageCommandArgs {
+    #[command(subcommand)]
+    pub stage: Stages,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Stages {
+    /// builds the H3 origin-destination table
+    #[command(about = "Builds the H3 origin-destination table")]
+    Od(OdCommandArgs),
+
+    /// detects stop episodes within the trajectories
+    #[command(about = "Detects stop episodes within the trajectories")]
+    Stops(StopsCommandArgs),
+
+    /// derives distance, acceleration and jerk for every signal
+    #[command(about = "Derives distance, acceleration and jerk for every signal")]
+    Kinematics,
+
+    /// detects PHEV and EV charging and regenerative braking
+    #[command(about = "Detects PHEV and EV charging and regenerative braking")]
+    Charge(ChargeCommandArgs),
+
+    /// collapses intersection and bus stop flags into events
+    #[command(about = "Collapses intersection and bus stop flags into events")]
+    Events(EventsCommandArgs),
+
+    /// derives turn movements from consecutive matched edges
+    #[command(about = "Derives turn movements from consecutive matched edges")]
+    Turns(TurnsCommandArgs),
+
+    /// measures how well the map-matched shapes fit the GPS points
+    #[command(about = "Measures how well the map-matched shapes fit the GPS points")]
+    MatchQuality,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct OdCommandArgs {
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(0..=12), help = "H3 resolution of the origin and destination cells")]
+    pub resolution: u8,
+
+    #[arg(long, help = "Exports the origin-destination table to this file")]
+    pub export: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv, help = "Export file format")]
+    pub format: ExportFormat,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct StopsCommandArgs {
+    #[arg(long, default_value_t = 1.0, help = "Speed at or below which the vehicle is stopped (km/h)")]
+    pub speed_threshold: f64,
+
+    #[arg(long, default_value_t = 5.0, help = "Minimum stop duration (s)")]
+    pub min_duration: f64,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ChargeCommandArgs {
+    #[arg(long, default_value_t = 1.0, help = "Minimum SOC increase between trips to count as a charge (%)")]
+    pub min_soc_gain: f64,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct EventsCommandArgs {
+    #[arg(long, default_value_t = 1.0, help = "Speed at or below which the vehicle stopped (km/h)")]
+    pub stop_speed: f64,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct TurnsCommandArgs {
+    #[arg(long, default_value_t = 30.0, help = "Largest heading change of a straight movement (degrees)")]
+    pub straight_angle: f64,
+
+    #[arg(long, default_value_t = 150.0, help = "Smallest heading change of a U-turn (degrees)")]
+    pub u_turn_angle: f64,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct AggregateCommandArgs {
+    #[command(subcommand)]
+    pub aggregate: Aggregates,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Aggregates {
+    /// aggregates the signals into H3 hexagons
+    #[command(about = "Aggregates the signals into H3 hexagons")]
+    H3(H3AggregateArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct H3AggregateArgs {
+    #[arg(long, default_value_t = 9, value_parser = clap::value_parser!(u8).range(0..=12), help = "H3 resolution of the aggregate cells")]
+    pub resolution: u8,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = H3Metric::all(), help = "Comma-separated list of metrics")]
+    pub metrics: Vec<H3Metric>,
+
+    #[arg(long, help = "Exports the hexagons to this GeoJSON file")]
+    pub geojson: Option<String>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ExportCommandArgs {
+    #[command(subcommand)]
+    pub export: Exports,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Exports {
+    /// exports the matched road network as a graph
+    #[command(about = "Exports the matched road network as a graph")]
+    Graph(GraphExportArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct GraphExportArgs {
+    #[arg(long, value_enum, default_value_t = GraphFormat::Graphml, help = "Graph file format")]
+    pub format: GraphFormat,
+
+    #[arg(long, help = "Output file path")]
+    pub output: String,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Graphml,
+    Edgelist,
+    Csr,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct NetworkCommandArgs {
+    #[command(subcommand)]
+    pub network: Networks,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Networks {
+    /// reports connectivity and topology problems
+    #[command(about = "Reports connectivity and topology problems")]
+    Check(NetworkCheckArgs),
+
+    /// imports the drivable ways of an OSM extract around the signals
+    #[command(about = "Imports the drivable ways of an OSM extract around the signals")]
+    ImportOsm(OsmImportArgs),
+
+    /// links the matched edges to OSM ways and reports coverage per road class
+    #[command(about = "Links the matched edges to OSM ways and reports coverage per road class")]
+    Coverage,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct NetworkCheckArgs {
+    #[arg(long, default_value_t = 2.0, help = "Distance below which two nodes are near duplicates (m)")]
+    pub near_distance: f64,
+
+    #[arg(long, help = "Merges near-duplicate nodes and collapses duplicate edges and self-loops")]
+    pub repair: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct OsmImportArgs {
+    #[arg(long, default_value_t = String::from("./valhalla/files/michigan-latest.osm.pbf"), help = "OSM PBF extract to import")]
+    pub pbf: String,
+
+    #[arg(long, default_value_t = 1000.0, help = "Margin around the signal bounding box (m)")]
+    pub margin: f64,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ConfigCommandArgs {
+    #[command(subcommand)]
+    pub config: Configs,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Configs {
+    /// prints the effective value of a key
+    #[command(about = "Prints the effective value of a key")]
+    Get(ConfigGetArgs),
+
+    /// writes a key to a config file
+    #[command(about = "Writes a key to a config file")]
+    Set(ConfigSetArgs),
+
+    /// lists every key with its effective value and where it comes from
+    #[command(about = "Lists every key with its effective value and where it comes from")]
+    List,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ConfigGetArgs {
+    #[arg(help = "Dotted key name, such as valhalla.url")]
+    pub key: String,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ConfigSetArgs {
+    #[arg(help = "Dotted key name, such as valhalla.url")]
+    pub key: String,
+
+    #[arg(help = "New value")]
+    pub value: String,
+
+    #[arg(long, value_enum, default_value_t = ConfigScope::Project, help = "Config file to write")]
+    pub scope: ConfigScope,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ValidateCommandArgs {
+    #[arg(long, default_value_t = 25.0, help = "Largest mean distance from the GPS points to the matched shape (m)")]
+    pub max_mean_distance: f64,
+
+    #[arg(long, default_value_t = 0.05, help = "Largest share of GPS points beyond the search radius")]
+    pub max_beyond_share: f64,
+
+    #[arg(long, default_value_t = 0.8, help = "Smallest ratio of matched to GPS length")]
+    pub min_length_ratio: f64,
+
+    #[arg(long, default_value_t = 1.25, help = "Largest ratio of matched to GPS length")]
+    pub max_length_ratio: f64,
 }
+
+#[derive(Args, Clone, Debug)]
+pub struct RouteCommandArgs {
+    #[arg(long, value_parser = parse_lat_lon, help = "Route origin as lat,lon")]
+    pub from: (f64, f64),
+
+    #[arg(long, value_parser = parse_lat_lon, help = "Route destination as lat,lon")]
+    pub to: (f64, f64),
+
+    #[arg(long, value_enum, default_value_t = RouteWeight::Length, help = "Edge weight to minimize")]
+    pub weight: RouteWeight,
+
+    #[arg(long, value_enum, default_value_t = RouteAlgorithm::Astar, help = "Shortest-path algorithm")]
+    pub algorithm: RouteAlgorithm,
+
+    #[arg(long, default_value_t = 10, help = "H3 ring size used to snap the endpoints to nodes")]
+    pub snap_ring: u32,
+
+    #[arg(long, help = "Exports the route to this GeoJSON file")]
+    pub geojson: Option<String>,
+}
+
+fn parse_lat_lon(text: &str) -> Result<(f64, f64), String> {
+    let (lat, lon) = text
+        .split_once(',')
+        .ok_or_else(|| format!("expected lat,lon but got '{}'", text))?;
+    let lat: f64 = lat.trim().parse().map_err(|e| format!("invalid latitude: {}", e))?;
+    let lon: f64 = lon.trim().parse().map_err(|e| format!("invalid longitude: {}", e))?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("coordinates out of range: {}", text));
+    }
+    Ok((lat, lon))
+}
+
@@ -0,0 +1,8 @@
+use crate::cli::{AggregateCommandArgs, Aggregates, Cli};
+use crate::commands::builders::aggregate::build_h3_aggregates;
+
+pub fn run_aggregate(cli: &Cli, args: &AggregateCommandArgs) -> bool {
+    match &args.aggregate {
+        Aggregates::H3(h3_args) => build_h3_aggregates(cli, h3_args),
+    }
+}
@@ -10,8 +10,8 @@ pub async fn build_database(cli: &Cli, args: &BuildCommandArgs) {
         clone_data(cli);
     }
 
-    if build_vehicles(cli) && build_signals(cli) {
-        build_trajectories(cli);
+    if build_vehicles(cli) && build_signals(cli, args) {
+        build_trajectories(cli, args);
     }
 
     if !args.no_clean {
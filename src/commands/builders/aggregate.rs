@@ -0,0 +1,156 @@
+use crate::cli::{Cli, H3AggregateArgs};
+use crate::db::ddl::aggregate::h3_table_name;
+use crate::db::evedb::EveDb;
+use crate::export::h3::export_h3_geojson;
+use crate::models::aggregate::{H3Aggregate, H3Metric, H3SignalStats};
+use crate::tools::h3_to_parent;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+struct H3Accumulator {
+    point_count: i64,
+    speed: (f64, i64),
+    energy: (f64, i64),
+    fuel: (f64, i64),
+    vehicles: HashSet<i64>,
+}
+
+fn mean((sum, count): (f64, i64)) -> Option<f64> {
+    if count > 0 {
+        Some(sum / count as f64)
+    } else {
+        None
+    }
+}
+
+fn aggregate_h3(stats: &[H3SignalStats], resolution: u8) -> Vec<H3Aggregate> {
+    let mut groups: HashMap<u64, H3Accumulator> = HashMap::new();
+
+    for stat in stats {
+        let Some(h3) = h3_to_parent(stat.h3_12, resolution) else {
+            continue;
+        };
+        let acc = groups.entry(h3).or_default();
+        acc.point_count += stat.point_count;
+        acc.speed.0 += stat.speed_sum;
+        acc.speed.1 += stat.speed_count;
+        acc.energy.0 += stat.energy_sum;
+        acc.energy.1 += stat.energy_count;
+        acc.fuel.0 += stat.fuel_sum;
+        acc.fuel.1 += stat.fuel_count;
+        acc.vehicles.insert(stat.vehicle_id);
+    }
+
+    let mut cells: Vec<H3Aggregate> = groups
+        .into_iter()
+        .map(|(h3, acc)| H3Aggregate {
+            h3,
+            point_count: acc.point_count,
+            mean_speed: mean(acc.speed),
+            mean_energy_consumption: mean(acc.energy),
+            mean_fuel_rate: mean(acc.fuel),
+            vehicle_count: acc.vehicles.len() as i64,
+        })
+        .collect();
+    cells.sort_by_key(|c| c.h3);
+    cells
+}
+
+/// The metrics in the order first given, so that a repeated metric gets one column
+fn unique_metrics(metrics: &[H3Metric]) -> Vec<H3Metric> {
+    let mut unique = Vec::with_capacity(metrics.len());
+    for &metric in metrics {
+        if !unique.contains(&metric) {
+            unique.push(metric);
+        }
+    }
+    unique
+}
+
+pub fn build_h3_aggregates(cli: &Cli, args: &H3AggregateArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+    let table = h3_table_name(args.resolution);
+    let metrics = unique_metrics(&args.metrics);
+
+    if cli.verbose {
+        println!("Creating the {} table", table)
+    }
+
+    if let Err(e) = db.create_h3_aggregate_table(args.resolution, &metrics) {
+        eprintln!("Failed to create {} table: {}", table, e);
+        return false;
+    }
+
+    let stats = match db.get_h3_signal_stats() {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("Failed to read signal statistics: {}", e);
+            return false;
+        }
+    };
+
+    let cells = aggregate_h3(&stats, args.resolution);
+    if cli.verbose {
+        println!("Inserting {} cells into {}", cells.len(), table)
+    }
+
+    if let Err(e) = db.insert_h3_aggregates(args.resolution, &metrics, &cells) {
+        eprintln!("Failed to insert {} cells: {}", table, e);
+        return false;
+    }
+
+    if let Some(path) = &args.geojson {
+        if cli.verbose {
+            println!("Exporting the hexagons to {}", path)
+        }
+        if let Err(e) = export_h3_geojson(path, &metrics, &cells) {
+            eprintln!("Failed to export hexagons: {}", e);
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::lat_lng_to_h3_12;
+
+    fn stats(lat: f64, lng: f64, vehicle_id: i64) -> H3SignalStats {
+        H3SignalStats {
+            h3_12: lat_lng_to_h3_12(lat, lng),
+            vehicle_id,
+            point_count: 2,
+            speed_sum: 100.0,
+            speed_count: 2,
+            energy_sum: 0.0,
+            energy_count: 0,
+            fuel_sum: 3.0,
+            fuel_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_h3() {
+        // All points fall in the same resolution 7 cell
+        let stats = vec![
+            stats(42.2808, -83.7430, 1),
+            stats(42.2809, -83.7431, 2),
+            stats(42.2810, -83.7432, 2),
+        ];
+        let cells = aggregate_h3(&stats, 7);
+
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].point_count, 6);
+        assert_eq!(cells[0].mean_speed, Some(50.0));
+        assert_eq!(cells[0].mean_energy_consumption, None);
+        assert_eq!(cells[0].mean_fuel_rate, Some(3.0));
+        assert_eq!(cells[0].vehicle_count, 2);
+    }
+
+    #[test]
+    fn test_unique_metrics() {
+        let metrics = [H3Metric::Count, H3Metric::Fuel, H3Metric::Count];
+        assert_eq!(unique_metrics(&metrics), metrics[..2]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::cli::{ChargeCommandArgs, Cli};
+use crate::commands::builders::energy::{battery_power_w, integrate};
+use crate::db::evedb::EveDb;
+use crate::models::charge::{ChargeEvent, TripSoc};
+use crate::models::signal::EnergySignal;
+use crate::models::trajectory::TrajectoryRegen;
+use chrono::{DateTime, FixedOffset};
+use indicatif::ProgressIterator;
+use std::collections::BTreeMap;
+
+struct DatedTrip<'a> {
+    trip: &'a TripSoc,
+    dt_ini: DateTime<FixedOffset>,
+    dt_end: DateTime<FixedOffset>,
+    soc_ini: f64,
+    soc_end: f64,
+}
+
+fn to_dated_trip(trip: &TripSoc) -> Option<DatedTrip<'_>> {
+    Some(DatedTrip {
+        trip,
+        dt_ini: DateTime::parse_from_rfc3339(trip.dt_ini.as_deref()?).ok()?,
+        dt_end: DateTime::parse_from_rfc3339(trip.dt_end.as_deref()?).ok()?,
+        soc_ini: trip.soc_ini?,
+        soc_end: trip.soc_end?,
+    })
+}
+
+/// Finds the charges between consecutive trips of each vehicle, where the SOC at the
+/// start of a trip exceeds the SOC at the end of the previous one by at least
+/// `min_soc_gain`. Trips without time or SOC bounds are ignored.
+fn detect_charges(trips: &[TripSoc], min_soc_gain: f64) -> Vec<ChargeEvent> {
+    let mut by_vehicle: BTreeMap<i64, Vec<DatedTrip>> = BTreeMap::new();
+    for trip in trips.iter().filter_map(to_dated_trip) {
+        by_vehicle.entry(trip.trip.vehicle_id).or_default().push(trip);
+    }
+
+    let mut charges: Vec<ChargeEvent> = Vec::new();
+    for (vehicle_id, mut vehicle_trips) in by_vehicle {
+        vehicle_trips.sort_by_key(|t| t.dt_ini);
+
+        for pair in vehicle_trips.windows(2) {
+            let (before, after) = (&pair[0], &pair[1]);
+            if after.soc_ini - before.soc_end < min_soc_gain {
+                continue;
+            }
+            charges.push(
+                ChargeEvent::builder()
+                    .vehicle_id(vehicle_id)
+                    .day(after.dt_ini.date_naive().to_string())
+                    .traj_id_before(before.trip.traj_id)
+                    .traj_id_after(after.trip.traj_id)
+                    .dt_ini(before.dt_end.to_rfc3339())
+                    .dt_end(after.dt_ini.to_rfc3339())
+                    .gap_s((after.dt_ini - before.dt_end).num_milliseconds() as f64 / 1000.0)
+                    .soc_ini(before.soc_end)
+                    .soc_end(after.soc_ini)
+                    .build(),
+            );
+        }
+    }
+    charges
+}
+
+/// Integrates the battery power flowing against the trip's net direction, which is the
+/// energy recovered by regenerative braking. This holds whatever sign convention the
+/// source data uses for the battery current.
+fn regen_energy_kwh(signals: &[EnergySignal]) -> Option<f64> {
+    let direction = integrate(signals, battery_power_w)?.signum();
+    integrate(signals, |s| {
+        battery_power_w(s).map(|p| if p * direction < 0.0 { p.abs() } else { 0.0 })
+    })
+    .map(|ws| ws / 3_600_000.0)
+}
+
+pub fn build_charges(cli: &Cli, args: &ChargeCommandArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the charge_event table")
+    }
+
+    if let Err(e) = db.create_charge_table() {
+        eprintln!("Failed to create charge_event table: {}", e);
+        return false;
+    }
+
+    let trips = match db.get_trip_socs() {
+        Ok(trips) => trips,
+        Err(e) => {
+            eprintln!("Failed to get PHEV and EV trips: {}", e);
+            return false;
+        }
+    };
+
+    let charges = detect_charges(&trips, args.min_soc_gain);
+    if cli.verbose {
+        println!("Inserting {} charge events", charges.len())
+    }
+
+    if let Err(e) = db.insert_charge_events(&charges) {
+        eprintln!("Failed to insert charge events: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.create_charge_indexes() {
+        eprintln!("Failed to create charge_event indexes: {}", e);
+        return false;
+    }
+
+    if cli.verbose {
+        println!("Computing regenerative braking energy")
+    }
+
+    let regens: Vec<TrajectoryRegen> = trips
+        .iter()
+        .progress()
+        .map(|trip| TrajectoryRegen {
+            trajectory_id: trip.traj_id,
+            regen_kwh: regen_energy_kwh(&db.get_energy_signals(trip.traj_id).unwrap_or(vec![])),
+        })
+        .collect();
+
+    if let Err(e) = db.update_trajectory_regen(&regens) {
+        eprintln!("Failed to update trajectory regen: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trip(traj_id: i64, vehicle_id: i64, hour: u32, soc_ini: f64, soc_end: f64) -> TripSoc {
+        TripSoc {
+            traj_id,
+            vehicle_id,
+            dt_ini: Some(format!("2017-11-01T{:02}:00:00-04:00", hour)),
+            dt_end: Some(format!("2017-11-01T{:02}:30:00-04:00", hour)),
+            soc_ini: Some(soc_ini),
+            soc_end: Some(soc_end),
+        }
+    }
+
+    #[test]
+    fn test_detect_charges() {
+        let trips = vec![
+            trip(3, 10, 18, 90.0, 80.0),
+            trip(1, 10, 8, 80.0, 60.0),
+            trip(2, 10, 12, 60.5, 40.0),
+            trip(4, 20, 9, 50.0, 45.0),
+            TripSoc {
+                soc_ini: None,
+                ..trip(5, 20, 10, 0.0, 0.0)
+            },
+            trip(6, 20, 11, 45.0, 40.0),
+        ];
+        let charges = detect_charges(&trips, 1.0);
+
+        assert_eq!(charges.len(), 1);
+        let charge = &charges[0];
+        assert_eq!(charge.vehicle_id, 10);
+        assert_eq!(charge.day, "2017-11-01");
+        assert_eq!((charge.traj_id_before, charge.traj_id_after), (2, 3));
+        assert_eq!(charge.dt_ini, "2017-11-01T12:30:00-04:00");
+        assert_eq!(charge.gap_s, 5.5 * 3600.0);
+        assert_eq!((charge.soc_ini, charge.soc_end), (40.0, 90.0));
+    }
+
+    #[test]
+    fn test_regen_energy_kwh() {
+        let signal = |time_stamp: i64, current: f64| EnergySignal {
+            time_stamp,
+            hv_bat_current: Some(current),
+            hv_bat_volt: Some(400.0),
+            ..Default::default()
+        };
+        // Net discharge with 1 hour at -10 A of regen between the discharge segments
+        let signals = vec![
+            signal(0, 100.0),
+            signal(3_600_000, 100.0),
+            signal(3_600_000, -10.0),
+            signal(7_200_000, -10.0),
+            signal(7_200_000, 100.0),
+        ];
+        assert!((regen_energy_kwh(&signals).unwrap() - 4.0).abs() < 1e-9);
+
+        // The same trip with the opposite sign convention
+        let flipped: Vec<EnergySignal> = signals
+            .iter()
+            .map(|s| signal(s.time_stamp, -s.hv_bat_current.unwrap()))
+            .collect();
+        assert!((regen_energy_kwh(&flipped).unwrap() - 4.0).abs() < 1e-9);
+        assert_eq!(regen_energy_kwh(&[]), None);
+    }
+}
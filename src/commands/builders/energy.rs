@@ -0,0 +1,145 @@
+use crate::models::signal::EnergySignal;
+use crate::models::trajectory::TrajectoryEnergy;
+
+/// Integrates a reading over time with the trapezoidal rule, in reading units times
+/// seconds. Pairs of consecutive signals with a missing reading are skipped, and the
+/// result is `None` when no pair has both readings.
+pub fn integrate(
+    signals: &[EnergySignal],
+    value: impl Fn(&EnergySignal) -> Option<f64>,
+) -> Option<f64> {
+    signals
+        .windows(2)
+        .filter_map(|pair| {
+            let dt_s = (pair[1].time_stamp - pair[0].time_stamp) as f64 / 1000.0;
+            Some(0.5 * (value(&pair[0])? + value(&pair[1])?) * dt_s)
+        })
+        .reduce(|a, b| a + b)
+}
+
+/// Battery power (W) as reported by the source data, `hv_bat_current * hv_bat_volt`.
+pub fn battery_power_w(signal: &EnergySignal) -> Option<f64> {
+    Some(signal.hv_bat_current? * signal.hv_bat_volt?)
+}
+
+fn ac_power_w(signal: &EnergySignal) -> Option<f64> {
+    signal
+        .ac_power_kw
+        .map(|kw| kw * 1000.0)
+        .or(signal.ac_power_w)
+}
+
+/// Summarizes the fuel and battery use of a trajectory whose signals are ordered by time
+/// stamp. Rates are left empty for trajectories without length.
+pub fn summarize_energy(signals: &[EnergySignal], length_m: f64) -> TrajectoryEnergy {
+    let length_km = length_m / 1000.0;
+    let per_km = |total: f64| (length_km > 0.0).then(|| total / length_km);
+    let to_kwh = |ws: f64| ws / 3_600_000.0;
+
+    let fuel_l = integrate(signals, |s| s.fuel_rate).map(|l_s| l_s / 3600.0);
+    let energy_kwh = integrate(signals, battery_power_w).map(to_kwh);
+    let oats: Vec<f64> = signals.iter().filter_map(|s| s.oat).collect();
+
+    TrajectoryEnergy {
+        fuel_l,
+        fuel_l_per_100km: fuel_l.and_then(per_km).map(|l_km| l_km * 100.0),
+        energy_kwh,
+        energy_kwh_per_km: energy_kwh.and_then(per_km),
+        soc_ini: signals.iter().find_map(|s| s.hv_bat_soc),
+        soc_end: signals.iter().rev().find_map(|s| s.hv_bat_soc),
+        mean_oat: (!oats.is_empty()).then(|| oats.iter().sum::<f64>() / oats.len() as f64),
+        ac_energy_kwh: integrate(signals, ac_power_w).map(to_kwh),
+        heater_energy_kwh: integrate(signals, |s| s.heater_power_w).map(to_kwh),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrate_skips_missing() {
+        let signals = vec![
+            EnergySignal {
+                time_stamp: 0,
+                fuel_rate: Some(1.0),
+                ..Default::default()
+            },
+            EnergySignal {
+                time_stamp: 1000,
+                fuel_rate: Some(3.0),
+                ..Default::default()
+            },
+            EnergySignal {
+                time_stamp: 2000,
+                fuel_rate: None,
+                ..Default::default()
+            },
+            EnergySignal {
+                time_stamp: 3000,
+                fuel_rate: Some(5.0),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(integrate(&signals, |s| s.fuel_rate), Some(2.0));
+        assert_eq!(integrate(&signals, |s| s.oat), None);
+        assert_eq!(integrate(&signals[..1], |s| s.fuel_rate), None);
+    }
+
+    #[test]
+    fn test_summarize_energy_ice() {
+        // 3.6 L/hr for one hour over 50 km
+        let signals = vec![
+            EnergySignal {
+                time_stamp: 0,
+                fuel_rate: Some(3.6),
+                oat: Some(10.0),
+                ..Default::default()
+            },
+            EnergySignal {
+                time_stamp: 3_600_000,
+                fuel_rate: Some(3.6),
+                oat: Some(20.0),
+                ..Default::default()
+            },
+        ];
+        let energy = summarize_energy(&signals, 50_000.0);
+
+        assert!((energy.fuel_l.unwrap() - 3.6).abs() < 1e-9);
+        assert!((energy.fuel_l_per_100km.unwrap() - 7.2).abs() < 1e-9);
+        assert_eq!(energy.mean_oat, Some(15.0));
+        assert_eq!(energy.energy_kwh, None);
+        assert_eq!(energy.soc_ini, None);
+        assert_eq!(energy.soc_end, None);
+    }
+
+    #[test]
+    fn test_summarize_energy_ev() {
+        // 10 A at 360 V for half an hour: 1.8 kWh over 9 km
+        let signal = |time_stamp: i64, soc: Option<f64>| EnergySignal {
+            time_stamp,
+            hv_bat_current: Some(10.0),
+            hv_bat_volt: Some(360.0),
+            hv_bat_soc: soc,
+            ac_power_kw: Some(2.0),
+            heater_power_w: Some(1000.0),
+            ..Default::default()
+        };
+        let signals = vec![
+            signal(0, Some(80.0)),
+            signal(900_000, None),
+            signal(1_800_000, Some(78.5)),
+        ];
+        let energy = summarize_energy(&signals, 9_000.0);
+
+        assert!((energy.energy_kwh.unwrap() - 1.8).abs() < 1e-9);
+        assert!((energy.energy_kwh_per_km.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(energy.soc_ini, Some(80.0));
+        assert_eq!(energy.soc_end, Some(78.5));
+        assert!((energy.ac_energy_kwh.unwrap() - 1.0).abs() < 1e-9);
+        assert!((energy.heater_energy_kwh.unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(energy.fuel_l, None);
+        assert_eq!(summarize_energy(&signals, 0.0).energy_kwh_per_km, None);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::cli::{Cli, EventsCommandArgs};
+use crate::db::evedb::EveDb;
+use crate::models::event::{EventKind, EventSignal, PassageEvent};
+use crate::tools::{TimeBase, lat_lng_to_h3_12};
+use indicatif::ProgressIterator;
+use std::ops::Range;
+
+/// Finds the index ranges of consecutive signals flagged with the event kind.
+fn collapse_flags(signals: &[EventSignal], kind: EventKind) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for i in 0..=signals.len() {
+        let flagged = i < signals.len() && signals[i].is_flagged(kind);
+        match (start, flagged) {
+            (None, true) => start = Some(i),
+            (Some(ini), false) => {
+                ranges.push(ini..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// Builds the event of a range of flagged signals. The approach speed is the last speed
+/// reading before the range, or the first one within it when the trajectory starts there.
+fn build_passage_event(
+    traj_id: i64,
+    signals: &[EventSignal],
+    range: Range<usize>,
+    time_base: &TimeBase,
+    stop_speed: f64,
+) -> PassageEvent {
+    let first = &signals[range.start];
+    let last = &signals[range.end - 1];
+    let approach_speed = signals[..range.start]
+        .iter()
+        .rev()
+        .find_map(|s| s.speed)
+        .or_else(|| signals[range.clone()].iter().find_map(|s| s.speed));
+    let min_speed = signals[range.clone()]
+        .iter()
+        .filter_map(|s| s.speed)
+        .min_by(f64::total_cmp);
+
+    PassageEvent::builder()
+        .traj_id(traj_id)
+        .latitude(first.latitude)
+        .longitude(first.longitude)
+        .h3_12(lat_lng_to_h3_12(first.latitude, first.longitude))
+        .time_stamp_ini(first.time_stamp)
+        .time_stamp_end(last.time_stamp)
+        .dt_ini(time_base.date_time(first.day_num, first.time_stamp).to_rfc3339())
+        .dt_end(time_base.date_time(first.day_num, last.time_stamp).to_rfc3339())
+        .duration_s((last.time_stamp - first.time_stamp) as f64 / 1000.0)
+        .maybe_approach_speed(approach_speed)
+        .maybe_min_speed(min_speed)
+        .stopped(min_speed.is_some_and(|speed| speed <= stop_speed))
+        .build()
+}
+
+fn build_events_of_kind(
+    cli: &Cli,
+    db: &EveDb,
+    kind: EventKind,
+    events: &[PassageEvent],
+) -> bool {
+    let table = kind.table_name();
+    if cli.verbose {
+        println!("Inserting {} {} records", events.len(), table)
+    }
+
+    if let Err(e) = db.create_event_table(kind) {
+        eprintln!("Failed to create {} table: {}", table, e);
+        return false;
+    }
+
+    if let Err(e) = db.insert_passage_events(kind, events) {
+        eprintln!("Failed to insert {} records: {}", table, e);
+        return false;
+    }
+
+    if let Err(e) = db.create_event_indexes(kind) {
+        eprintln!("Failed to create {} indexes: {}", table, e);
+        return false;
+    }
+    true
+}
+
+pub fn build_events(cli: &Cli, args: &EventsCommandArgs) -> bool {
+    let time_base = match cli.time_base() {
+        Ok(time_base) => time_base,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Collapsing intersection and bus stop flags into events")
+    }
+
+    let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
+    let mut intersections: Vec<PassageEvent> = Vec::new();
+    let mut bus_stops: Vec<PassageEvent> = Vec::new();
+
+    for trajectory_id in trajectory_ids.iter().progress() {
+        let signals = db.get_event_signals(*trajectory_id).unwrap_or(vec![]);
+        for (kind, events) in [
+            (EventKind::Intersection, &mut intersections),
+            (EventKind::BusStop, &mut bus_stops),
+        ] {
+            events.extend(collapse_flags(&signals, kind).into_iter().map(|range| {
+                build_passage_event(*trajectory_id, &signals, range, &time_base, args.stop_speed)
+            }));
+        }
+    }
+
+    build_events_of_kind(cli, &db, EventKind::Intersection, &intersections)
+        && build_events_of_kind(cli, &db, EventKind::BusStop, &bus_stops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(time_stamp: i64, speed: Option<f64>, intersection: bool) -> EventSignal {
+        EventSignal {
+            day_num: 1.0,
+            time_stamp,
+            latitude: 42.28,
+            longitude: -83.74,
+            speed,
+            intersection,
+            bus_stop: false,
+        }
+    }
+
+    #[test]
+    fn test_collapse_flags() {
+        let signals = vec![
+            signal(0, Some(40.0), true),
+            signal(1000, Some(30.0), false),
+            signal(2000, Some(20.0), true),
+            signal(3000, Some(10.0), true),
+            signal(4000, Some(15.0), false),
+            signal(5000, Some(25.0), true),
+        ];
+
+        assert_eq!(
+            collapse_flags(&signals, EventKind::Intersection),
+            vec![0..1, 2..4, 5..6]
+        );
+        assert!(collapse_flags(&signals, EventKind::BusStop).is_empty());
+    }
+
+    #[test]
+    fn test_build_passage_event() {
+        let signals = vec![
+            signal(0, Some(40.0), false),
+            signal(1000, None, false),
+            signal(2000, Some(20.0), true),
+            signal(3000, Some(0.5), true),
+            signal(4000, None, true),
+            signal(5000, Some(15.0), false),
+        ];
+        let event = build_passage_event(7, &signals, 2..5, &TimeBase::default(), 1.0);
+
+        assert_eq!(event.traj_id, 7);
+        assert_eq!((event.time_stamp_ini, event.time_stamp_end), (2000, 4000));
+        assert_eq!(event.duration_s, 2.0);
+        assert_eq!(event.approach_speed, Some(40.0));
+        assert_eq!(event.min_speed, Some(0.5));
+        assert!(event.stopped);
+
+        // Without earlier signals the approach speed is the first reading of the event
+        let event = build_passage_event(7, &signals[2..], 0..3, &TimeBase::default(), 0.1);
+        assert_eq!(event.approach_speed, Some(20.0));
+        assert!(!event.stopped);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::cli::{Cli, GraphExportArgs};
+use crate::db::evedb::EveDb;
+use crate::export::graph::export_graph;
+
+pub fn export_network_graph(cli: &Cli, args: &GraphExportArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let nodes = match db.get_nodes() {
+        Ok(nodes) => nodes,
+        Err(e) => {
+            eprintln!("Failed to get nodes: {}", e);
+            return false;
+        }
+    };
+
+    let edges = match db.get_network_edges() {
+        Ok(edges) => edges,
+        Err(e) => {
+            eprintln!("Failed to get edges: {}", e);
+            return false;
+        }
+    };
+
+    if cli.verbose {
+        println!(
+            "Exporting {} nodes and {} edges to {}",
+            nodes.len(),
+            edges.len(),
+            args.output
+        )
+    }
+
+    if let Err(e) = export_graph(&args.output, args.format, &nodes, &edges) {
+        eprintln!("Failed to export graph: {}", e);
+        return false;
+    }
+    true
+}
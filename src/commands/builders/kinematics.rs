@@ -0,0 +1,205 @@
+use crate::cli::Cli;
+use crate::db::evedb::EveDb;
+use crate::models::kinematics::SignalKinematics;
+use crate::models::signal::TrajectorySignal;
+use geo::{Distance, Haversine, Point};
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+
+/// Fills in missing speeds by linear interpolation in time between the nearest known
+/// readings. Leading and trailing gaps take the nearest known speed. Returns the speeds
+/// with a flag telling whether each one was interpolated.
+fn interpolate_speeds(signals: &[TrajectorySignal]) -> Vec<(Option<f64>, bool)> {
+    let known: Vec<usize> = (0..signals.len())
+        .filter(|&i| signals[i].speed.is_some_and(|s| s.is_finite()))
+        .collect();
+
+    (0..signals.len())
+        .map(|i| {
+            if known.binary_search(&i).is_ok() {
+                return (signals[i].speed, false);
+            }
+            let next = known.partition_point(|&k| k < i);
+            let speed = match (next.checked_sub(1).map(|p| known[p]), known.get(next)) {
+                (Some(p), Some(&n)) => {
+                    let (v0, v1) = (signals[p].speed.unwrap(), signals[n].speed.unwrap());
+                    let span = (signals[n].time_stamp - signals[p].time_stamp) as f64;
+                    if span > 0.0 {
+                        let t = (signals[i].time_stamp - signals[p].time_stamp) as f64 / span;
+                        Some(v0 + (v1 - v0) * t)
+                    } else {
+                        Some(v0)
+                    }
+                }
+                (Some(p), None) => signals[p].speed,
+                (None, Some(&n)) => signals[n].speed,
+                (None, None) => None,
+            };
+            (speed, speed.is_some())
+        })
+        .collect()
+}
+
+/// Computes the time deltas, step and cumulative distances, acceleration (m/s²) and
+/// jerk (m/s³) of a trajectory's signals, ordered by time stamp. Derivatives are left
+/// empty where the time delta is not positive or the previous value is missing.
+fn compute_kinematics(traj_id: i64, signals: &[TrajectorySignal]) -> Vec<SignalKinematics> {
+    let speeds = interpolate_speeds(signals);
+    let mut rows: Vec<SignalKinematics> = Vec::with_capacity(signals.len());
+    let mut distance_m = 0.0;
+
+    for (i, signal) in signals.iter().enumerate() {
+        let (speed, speed_interpolated) = speeds[i];
+        let (mut dt_s, mut step_m, mut acceleration, mut jerk) = (None, None, None, None);
+
+        if i > 0 {
+            let prev = &signals[i - 1];
+            let dt = (signal.time_stamp - prev.time_stamp) as f64 / 1000.0;
+            let step = Haversine.distance(
+                Point::new(prev.longitude, prev.latitude),
+                Point::new(signal.longitude, signal.latitude),
+            );
+            distance_m += step;
+            dt_s = Some(dt);
+            step_m = Some(step);
+
+            if dt > 0.0 {
+                acceleration = speed
+                    .zip(speeds[i - 1].0)
+                    .map(|(v1, v0)| (v1 - v0) / 3.6 / dt);
+                jerk = acceleration
+                    .zip(rows[i - 1].acceleration)
+                    .map(|(a1, a0)| (a1 - a0) / dt);
+            }
+        }
+
+        rows.push(SignalKinematics {
+            signal_id: signal.signal_id,
+            traj_id,
+            dt_s,
+            step_m,
+            distance_m,
+            speed,
+            speed_interpolated,
+            acceleration,
+            jerk,
+        });
+    }
+    rows
+}
+
+/// Number of trajectories whose kinematics are computed and inserted together, which
+/// bounds the rows held in memory
+const TRAJECTORY_CHUNK: usize = 1000;
+
+pub fn build_kinematics(cli: &Cli) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the signal_kinematics table")
+    }
+
+    if let Err(e) = db.create_kinematics_table() {
+        eprintln!("Failed to create signal_kinematics table: {}", e);
+        return false;
+    }
+
+    let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
+    let progress = ProgressBar::new(trajectory_ids.len() as u64);
+    let mut inserted: usize = 0;
+    for chunk in trajectory_ids.chunks(TRAJECTORY_CHUNK) {
+        let rows: Vec<SignalKinematics> = chunk
+            .par_iter()
+            .flat_map_iter(|trajectory_id| {
+                let signals = db.get_trajectory_signals(*trajectory_id).unwrap_or(vec![]);
+                progress.inc(1);
+                compute_kinematics(*trajectory_id, &signals)
+            })
+            .collect();
+
+        if let Err(e) = db.insert_signal_kinematics(&rows) {
+            progress.abandon();
+            eprintln!("Failed to insert signal kinematics: {}", e);
+            return false;
+        }
+        inserted += rows.len();
+    }
+    progress.finish();
+
+    if cli.verbose {
+        println!("Inserted {} signal kinematics records", inserted)
+    }
+
+    if let Err(e) = db.create_kinematics_indexes() {
+        eprintln!("Failed to create signal_kinematics indexes: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(time_stamp: i64, latitude: f64, speed: Option<f64>) -> TrajectorySignal {
+        TrajectorySignal {
+            signal_id: time_stamp,
+            day_num: 1.0,
+            time_stamp,
+            latitude,
+            longitude: -83.74,
+            speed,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_speeds() {
+        let signals = vec![
+            signal(0, 42.0, None),
+            signal(1000, 42.0, Some(10.0)),
+            signal(2000, 42.0, None),
+            signal(5000, 42.0, Some(50.0)),
+            signal(6000, 42.0, None),
+        ];
+        let speeds = interpolate_speeds(&signals);
+
+        assert_eq!(
+            speeds,
+            vec![
+                (Some(10.0), true),
+                (Some(10.0), false),
+                (Some(20.0), true),
+                (Some(50.0), false),
+                (Some(50.0), true),
+            ]
+        );
+        assert_eq!(interpolate_speeds(&[signal(0, 42.0, None)]), vec![(None, false)]);
+    }
+
+    #[test]
+    fn test_compute_kinematics() {
+        // 0 -> 36 -> 36 km/h over one-second steps: 10 m/s² then -10 m/s³
+        let signals = vec![
+            signal(0, 42.0, Some(0.0)),
+            signal(1000, 42.0001, Some(36.0)),
+            signal(2000, 42.0002, Some(36.0)),
+            signal(2000, 42.0002, Some(36.0)),
+        ];
+        let rows = compute_kinematics(7, &signals);
+
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|r| r.traj_id == 7));
+        assert_eq!(rows[0].dt_s, None);
+        assert_eq!(rows[0].distance_m, 0.0);
+        assert_eq!(rows[1].dt_s, Some(1.0));
+        assert!((rows[1].step_m.unwrap() - 11.12).abs() < 0.01);
+        assert!((rows[2].distance_m - 22.24).abs() < 0.01);
+        assert_eq!(rows[1].acceleration, Some(10.0));
+        assert_eq!(rows[1].jerk, None);
+        assert_eq!(rows[2].acceleration, Some(0.0));
+        assert_eq!(rows[2].jerk, Some(-10.0));
+        // Repeated time stamps have no derivatives
+        assert_eq!(rows[3].dt_s, Some(0.0));
+        assert_eq!(rows[3].acceleration, None);
+    }
+}
@@ -1,4 +1,16 @@
+pub mod aggregate;
+pub mod charge;
+pub mod energy;
+pub mod event;
+pub mod graph;
+pub mod kinematics;
+pub mod network;
 pub mod node;
+pub mod od;
+pub mod osm;
+pub mod quality;
 pub mod signal;
+pub mod stop;
 pub mod trajectory;
+pub mod turn;
 pub mod vehicle;
@@ -0,0 +1,410 @@
+use crate::cli::{Cli, NetworkCheckArgs};
+use crate::db::evedb::EveDb;
+use crate::models::edge::NetworkEdge;
+use crate::models::node::Node;
+use std::collections::{BTreeMap, HashMap};
+
+/// Metres per degree of latitude, used to bound the near-node sweep
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+#[derive(Debug, Default)]
+struct NetworkReport {
+    weak_components: Vec<Vec<i64>>,
+    strong_components: Vec<Vec<i64>>,
+    dead_ends: Vec<i64>,
+    self_loops: Vec<i64>,
+    duplicate_edges: Vec<Vec<i64>>,
+    near_nodes: Vec<(i64, i64, f64)>,
+    zero_length: Vec<i64>,
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut i = i;
+    while parent[i] != root {
+        let next = parent[i];
+        parent[i] = root;
+        i = next;
+    }
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (ra, rb) = (find(parent, a), find(parent, b));
+    // The lowest index becomes the root, so merges keep the lowest node id
+    if ra < rb {
+        parent[rb] = ra;
+    } else {
+        parent[ra] = rb;
+    }
+}
+
+/// Groups the node ids by root, largest groups first
+fn groups(ids: &[i64], mut parent: Vec<usize>) -> Vec<Vec<i64>> {
+    let mut by_root: BTreeMap<usize, Vec<i64>> = BTreeMap::new();
+    for (i, &id) in ids.iter().enumerate() {
+        by_root.entry(find(&mut parent, i)).or_default().push(id);
+    }
+    let mut groups: Vec<Vec<i64>> = by_root.into_values().collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+    groups
+}
+
+/// Kosaraju's algorithm with explicit stacks, so that long roads do not overflow
+fn strong_components(ids: &[i64], arcs: &[(usize, usize)]) -> Vec<Vec<i64>> {
+    let n = ids.len();
+    let mut forward: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut backward: Vec<Vec<usize>> = vec![vec![]; n];
+    for &(a, b) in arcs {
+        forward[a].push(b);
+        backward[b].push(a);
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    for start in 0..n {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![(start, 0usize)];
+        while let Some((node, next)) = stack.pop() {
+            if let Some(&child) = forward[node].get(next) {
+                stack.push((node, next + 1));
+                if !visited[child] {
+                    visited[child] = true;
+                    stack.push((child, 0));
+                }
+            } else {
+                order.push(node);
+            }
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut assigned = vec![false; n];
+    for &root in order.iter().rev() {
+        if assigned[root] {
+            continue;
+        }
+        assigned[root] = true;
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for &child in &backward[node] {
+                if !assigned[child] {
+                    assigned[child] = true;
+                    union(&mut parent, root, child);
+                    stack.push(child);
+                }
+            }
+        }
+    }
+    groups(ids, parent)
+}
+
+/// Finds the node pairs closer than `max_m` with a latitude sweep
+fn near_nodes(nodes: &[Node], max_m: f64) -> Vec<(i64, i64, f64)> {
+    let mut sorted: Vec<&Node> = nodes.iter().collect();
+    sorted.sort_by(|a, b| a.latitude.total_cmp(&b.latitude));
+    let max_dlat = max_m / METERS_PER_DEGREE;
+
+    let mut pairs = Vec::new();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in sorted[i + 1..]
+            .iter()
+            .take_while(|b| b.latitude - a.latitude <= max_dlat)
+        {
+            let distance_m = a.distance_to(b);
+            if distance_m < max_m {
+                pairs.push((a.id.min(b.id), a.id.max(b.id), distance_m));
+            }
+        }
+    }
+    pairs.sort_by_key(|&(a, b, _)| (a, b));
+    pairs
+}
+
+fn diagnose(nodes: &[Node], edges: &[NetworkEdge], near_m: f64) -> NetworkReport {
+    let ids: Vec<i64> = nodes.iter().map(|n| n.id).collect();
+    let index: HashMap<i64, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let arcs: Vec<(usize, usize)> = edges
+        .iter()
+        .filter_map(|e| Some((*index.get(&e.node_ini)?, *index.get(&e.node_end)?)))
+        .collect();
+
+    let mut parent: Vec<usize> = (0..ids.len()).collect();
+    for &(a, b) in &arcs {
+        union(&mut parent, a, b);
+    }
+
+    let mut out_degree = vec![0usize; ids.len()];
+    let mut in_degree = vec![0usize; ids.len()];
+    for &(a, b) in arcs.iter().filter(|(a, b)| a != b) {
+        out_degree[a] += 1;
+        in_degree[b] += 1;
+    }
+
+    let mut by_nodes: BTreeMap<(i64, i64), Vec<i64>> = BTreeMap::new();
+    for edge in edges {
+        by_nodes
+            .entry((edge.node_ini, edge.node_end))
+            .or_default()
+            .push(edge.id);
+    }
+
+    NetworkReport {
+        weak_components: groups(&ids, parent),
+        strong_components: strong_components(&ids, &arcs),
+        dead_ends: (0..ids.len())
+            .filter(|&i| in_degree[i] > 0 && out_degree[i] == 0)
+            .map(|i| ids[i])
+            .collect(),
+        self_loops: edges
+            .iter()
+            .filter(|e| e.node_ini == e.node_end)
+            .map(|e| e.id)
+            .collect(),
+        duplicate_edges: by_nodes.into_values().filter(|g| g.len() > 1).collect(),
+        near_nodes: near_nodes(nodes, near_m),
+        zero_length: edges
+            .iter()
+            .filter(|e| e.length_m <= 0.0)
+            .map(|e| e.id)
+            .collect(),
+    }
+}
+
+/// Maps every near-duplicate node to the lowest node id of its cluster, and returns the
+/// merges together with the edges whose endpoints move, with their geometry recomputed.
+/// Pairs are merged from the closest up, and a merge is skipped when it would leave a
+/// node `near_m` or more from its cluster's surviving node, so that a chain of close
+/// nodes along a road does not collapse into one.
+fn plan_repair(
+    nodes: &[Node],
+    edges: &[NetworkEdge],
+    near: &[(i64, i64, f64)],
+    near_m: f64,
+) -> (Vec<(i64, i64)>, Vec<NetworkEdge>) {
+    let mut ids: Vec<i64> = nodes.iter().map(|n| n.id).collect();
+    ids.sort();
+    let index: HashMap<i64, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let by_id: HashMap<i64, &Node> = nodes.iter().map(|n| (n.id, n)).collect();
+
+    let mut near: Vec<&(i64, i64, f64)> = near.iter().collect();
+    near.sort_by(|x, y| x.2.total_cmp(&y.2));
+
+    let mut parent: Vec<usize> = (0..ids.len()).collect();
+    let mut members: Vec<Vec<usize>> = (0..ids.len()).map(|i| vec![i]).collect();
+    for (a, b, _) in near {
+        let (ra, rb) = (find(&mut parent, index[a]), find(&mut parent, index[b]));
+        if ra == rb {
+            continue;
+        }
+        let (root, other) = (ra.min(rb), ra.max(rb));
+        let survivor = by_id[&ids[root]];
+        let within = members[root]
+            .iter()
+            .chain(&members[other])
+            .all(|&i| by_id[&ids[i]].distance_to(survivor) < near_m);
+        if within {
+            union(&mut parent, root, other);
+            let moved = std::mem::take(&mut members[other]);
+            members[root].extend(moved);
+        }
+    }
+
+    let merges: Vec<(i64, i64)> = (0..ids.len())
+        .map(|i| (ids[i], ids[find(&mut parent, i)]))
+        .filter(|(id, into)| id != into)
+        .collect();
+    let into: HashMap<i64, i64> = merges.iter().copied().collect();
+
+    let updates = edges
+        .iter()
+        .filter(|e| into.contains_key(&e.node_ini) || into.contains_key(&e.node_end))
+        .filter_map(|e| {
+            let node_ini = *into.get(&e.node_ini).unwrap_or(&e.node_ini);
+            let node_end = *into.get(&e.node_end).unwrap_or(&e.node_end);
+            let (ini, end) = (by_id.get(&node_ini)?, by_id.get(&node_end)?);
+            Some(NetworkEdge {
+                node_ini,
+                node_end,
+                length_m: ini.distance_to(end),
+                bearing_deg: ini.bearing_to(end),
+                ..e.clone()
+            })
+        })
+        .collect();
+    (merges, updates)
+}
+
+fn print_report(cli: &Cli, report: &NetworkReport) {
+    let largest = |components: &Vec<Vec<i64>>| components.first().map_or(0, |c| c.len());
+    println!(
+        "Weakly connected components: {} (largest: {} nodes)",
+        report.weak_components.len(),
+        largest(&report.weak_components)
+    );
+    println!(
+        "Strongly connected components: {} (largest: {} nodes)",
+        report.strong_components.len(),
+        largest(&report.strong_components)
+    );
+    println!("Dead ends: {}", report.dead_ends.len());
+    println!("Self-loops: {}", report.self_loops.len());
+    println!("Duplicate edge groups: {}", report.duplicate_edges.len());
+    println!("Near-duplicate node pairs: {}", report.near_nodes.len());
+    println!("Zero-length edges: {}", report.zero_length.len());
+
+    if cli.verbose {
+        println!("Dead end nodes: {:?}", report.dead_ends);
+        println!("Self-loop edges: {:?}", report.self_loops);
+        println!("Duplicate edges: {:?}", report.duplicate_edges);
+        for (a, b, distance_m) in &report.near_nodes {
+            println!("Near nodes: {} {} ({:.2} m)", a, b, distance_m);
+        }
+        println!("Zero-length edges: {:?}", report.zero_length);
+    }
+}
+
+pub fn check_network(cli: &Cli, args: &NetworkCheckArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let (nodes, edges) = match (db.get_nodes(), db.get_network_edges()) {
+        (Ok(nodes), Ok(edges)) => (nodes, edges),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to load the road network: {}", e);
+            return false;
+        }
+    };
+
+    let report = diagnose(&nodes, &edges, args.near_distance);
+    print_report(cli, &report);
+
+    if !args.repair {
+        return true;
+    }
+
+    let (merges, updates) = plan_repair(&nodes, &edges, &report.near_nodes, args.near_distance);
+    if cli.verbose {
+        println!(
+            "Merging {} nodes and rewriting {} edges",
+            merges.len(),
+            updates.len()
+        )
+    }
+
+    match db.repair_network(&merges, &updates) {
+        Ok(dropped) => {
+            for table in dropped {
+                let command = match table {
+                    "turn" => "stage turns",
+                    _ => "network coverage",
+                };
+                println!("Dropped the stale {} table, re-run `{}`", table, command);
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to repair the network: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, latitude: f64, longitude: f64) -> Node {
+        Node {
+            id,
+            latitude,
+            longitude,
+            altitude: 0.0,
+            h3_12: 0,
+        }
+    }
+
+    fn edge(id: i64, node_ini: i64, node_end: i64, length_m: f64) -> NetworkEdge {
+        NetworkEdge {
+            id,
+            node_ini,
+            node_end,
+            length_m,
+            bearing_deg: 0.0,
+            traversals: 1,
+            mean_speed_kmh: None,
+        }
+    }
+
+    #[test]
+    fn test_diagnose() {
+        // 1 <-> 2 -> 3 -> 3, a duplicate 2 -> 3, node 4 half a metre from 3 and
+        // the isolated node 5
+        let nodes = vec![
+            node(1, 42.000, -83.7),
+            node(2, 42.001, -83.7),
+            node(3, 42.002, -83.7),
+            node(4, 42.002, -83.700006),
+            node(5, 42.010, -83.7),
+        ];
+        let edges = vec![
+            edge(1, 1, 2, 111.0),
+            edge(2, 2, 1, 111.0),
+            edge(3, 2, 3, 111.0),
+            edge(4, 2, 3, 111.0),
+            edge(5, 3, 3, 0.0),
+        ];
+        let report = diagnose(&nodes, &edges, 1.0);
+
+        assert_eq!(report.weak_components, vec![vec![1, 2, 3], vec![4], vec![5]]);
+        assert_eq!(report.strong_components[0], vec![1, 2]);
+        assert_eq!(report.strong_components.len(), 4);
+        assert_eq!(report.dead_ends, vec![3]);
+        assert_eq!(report.self_loops, vec![5]);
+        assert_eq!(report.duplicate_edges, vec![vec![3, 4]]);
+        assert_eq!(report.near_nodes.len(), 1);
+        assert_eq!((report.near_nodes[0].0, report.near_nodes[0].1), (3, 4));
+        assert_eq!(report.zero_length, vec![5]);
+    }
+
+    #[test]
+    fn test_plan_repair() {
+        let nodes = vec![
+            node(1, 42.000, -83.7),
+            node(7, 42.001, -83.7),
+            node(3, 42.001, -83.700006),
+        ];
+        let edges = vec![edge(1, 1, 7, 111.0), edge(2, 7, 1, 111.0)];
+        let near = near_nodes(&nodes, 1.0);
+        let (merges, updates) = plan_repair(&nodes, &edges, &near, 1.0);
+
+        assert_eq!(merges, vec![(7, 3)]);
+        assert_eq!(updates.len(), 2);
+        assert_eq!((updates[0].node_ini, updates[0].node_end), (1, 3));
+        assert_eq!((updates[1].node_ini, updates[1].node_end), (3, 1));
+        assert!((updates[0].length_m - nodes[0].distance_to(&nodes[2])).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_plan_repair_chain() {
+        // Five nodes 0.8 m apart along a road, each near its neighbours only
+        let nodes: Vec<Node> = (0..5)
+            .map(|i| node(i + 1, 42.0 + i as f64 * 0.0000072, -83.7))
+            .collect();
+        let near = near_nodes(&nodes, 1.0);
+        assert_eq!(near.len(), 4);
+        let (merges, _) = plan_repair(&nodes, &[], &near, 1.0);
+
+        // Each surviving node absorbs at most its neighbour
+        assert_eq!(merges, vec![(2, 1), (4, 3)]);
+        for (id, into) in merges {
+            let (a, b) = (&nodes[id as usize - 1], &nodes[into as usize - 1]);
+            assert!(a.distance_to(b) < 1.0);
+        }
+    }
+}
@@ -1,40 +1,21 @@
-use crate::cli::Cli;
+use crate::cache::MatchCache;
+use crate::cli::{Cli, HmmNetwork, MatchCommandArgs, Matcher};
+use crate::commands::builders::osm::osm_edges;
 use crate::db::dml::node::get_ring;
 use crate::db::evedb::EveDb;
+use crate::etl::filters::{DistanceFilter, DuplicateFilter, FilterPipeline, SpeedFilter};
+use crate::matcher::MapMatcher;
+use crate::matcher::hmm::HmmMatcher;
+use crate::matcher::pool::ValhallaPool;
+use crate::matcher::valhalla::{ValhallaMatcher, ValhallaSettings};
 use crate::models::node::Node;
 use crate::models::trajectory::WayPoint;
 use crate::tools::lat_lng_to_h3_12;
-use anyhow::{anyhow, Result};
-use geo::Point;
-use indicatif::ProgressIterator;
+use futures::stream::{self, StreamExt};
+use geo::{Distance, Haversine, Point};
+use indicatif::ProgressBar;
 use std::cmp::Ordering;
-use url::Url;
-use valhalla_client::costing::{AutoCostingOptions, Costing};
-use valhalla_client::route::{DirectionsType, ShapePoint, Trip};
-use valhalla_client::trace_route::{Manifest, ShapeMatchType, TraceOptions};
-use valhalla_client::{Error, Valhalla};
-
-async fn map_match(
-    valhalla: &Valhalla,
-    locations: impl Iterator<Item = ShapePoint>,
-) -> Result<Trip> {
-    let trace_options = TraceOptions::builder()
-        .search_radius(100.0)
-        .gps_accuracy(5.0);
-    let manifest: Manifest = Manifest::builder()
-        .shape_match(ShapeMatchType::MapSnap)
-        .shape(locations)
-        .use_timestamps(false)
-        .verbose(true)
-        .trace_options(trace_options)
-        .directions_type(DirectionsType::None)
-        .costing(Costing::Auto(AutoCostingOptions::default()));
-
-    valhalla
-        .trace_route(manifest)
-        .await
-        .map_err(|e| anyhow!("Failed to map match: {:?}", e))
-}
+use valhalla_client::route::ShapePoint;
 
 fn build_node(db: &EveDb, pt: &ShapePoint) -> Node {
     match find_node(db, pt) {
@@ -51,26 +32,27 @@ fn build_node(db: &EveDb, pt: &ShapePoint) -> Node {
     }
 }
 
-pub fn find_node(db: &EveDb, pt: &ShapePoint) -> Option<Node> {
-    let index = lat_lng_to_h3_12(pt.lat, pt.lon);
-    let ring = crate::tools::get_ring(index, 1);
-    let point  = Point::new(pt.lon, pt.lat);
+pub fn find_nearest_node(db: &EveDb, lat: f64, lon: f64, k: u32) -> Option<(Node, f64)> {
+    let index = lat_lng_to_h3_12(lat, lon);
+    let ring = crate::tools::get_ring(index, k);
+    let point  = Point::new(lon, lat);
 
     match get_ring(db, ring) {
-        Ok(nodes) => {
-            let nearest = nodes.iter()
-                .map(|n| (n, n.distance_to_point(&point)))
-                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-            if let Some((node, distance)) = nearest && distance <= 1.0 {
-                return Some(*node);
-            }
-        }
+        Ok(nodes) => nodes.into_iter()
+            .map(|n| (n, n.distance_to_point(&point)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal)),
         Err(e) => {
             eprintln!("Failed to get ring: {}", e);
-            return None;
+            None
         }
     }
-    None
+}
+
+pub fn find_node(db: &EveDb, pt: &ShapePoint) -> Option<Node> {
+    match find_nearest_node(db, pt.lat, pt.lon, 1) {
+        Some((node, distance)) if distance <= 1.0 => Some(node),
+        _ => None,
+    }
 }
 
 fn create_tables(cli: &Cli, db: &EveDb) -> bool {
@@ -122,54 +104,176 @@ fn create_tables(cli: &Cli, db: &EveDb) -> bool {
         eprintln!("Failed to create trajectory_error table");
         return false;
     }
-    true
-}
 
-async fn connect_to_valhalla(
-    cli: &Cli,
-    valhalla_url: &Url,
-) -> std::result::Result<Valhalla, Error> {
-    let valhalla = Valhalla::new(valhalla_url.clone());
-
-    if cli.verbose {
-        println!("Checking Valhalla instance at {}", valhalla_url);
+    if db.create_match_metadata_table().is_err() {
+        eprintln!("Failed to create match_metadata table");
+        return false;
     }
 
-    valhalla
-        .status(valhalla_client::status::Manifest::default())
-        .await?;
-    Ok(valhalla)
+    if db.create_match_filter_table().is_err() {
+        eprintln!("Failed to create match_filter table");
+        return false;
+    }
+    true
 }
 
-fn get_valhalla_url() -> Result<Url> {
-    let url =
-        std::env::var("VALHALLA_URL").unwrap_or_else(|_| "http://localhost:8002/".to_string());
-    Url::parse(&url).map_err(|e| anyhow!("Invalid Valhalla URL '{}': {}", url, e))
+fn get_filter_pipeline(args: &MatchCommandArgs) -> FilterPipeline {
+    if args.no_filter {
+        return FilterPipeline::new();
+    }
+    FilterPipeline::new()
+        .with(DuplicateFilter {
+            precision: args.duplicate_precision,
+        })
+        .with(SpeedFilter {
+            max_speed_kmh: args.max_speed,
+        })
+        .with(DistanceFilter {
+            min_distance_m: args.min_distance,
+        })
 }
 
-pub async fn build_nodes(cli: &Cli) {
-    let db: EveDb = EveDb::new(&cli.db_path);
+async fn valhalla_matcher(cli: &Cli, args: &MatchCommandArgs) -> Option<ValhallaMatcher> {
+    let cache = match args.cache_dir.as_deref().map(MatchCache::new) {
+        Some(Ok(cache)) => Some(cache),
+        Some(Err(e)) => {
+            eprintln!("Failed to open the match cache: {}", e);
+            return None;
+        }
+        None => None,
+    };
 
-    let valhalla_url = match get_valhalla_url() {
-        Ok(url) => url,
+    let settings = match ValhallaSettings::resolve(&cli.valhalla, args) {
+        Ok(settings) => settings,
         Err(e) => {
-            eprintln!("Invalid Valhalla URL: {}", e);
-            return;
+            eprintln!("Invalid Valhalla settings: {}", e);
+            return None;
         }
     };
 
-    // Check if we can connect to Valhalla
-    let valhalla_result = connect_to_valhalla(cli, &valhalla_url).await;
+    // Offline runs answer every request from the cache
+    if args.offline {
+        return Some(ValhallaMatcher::new(None, cache, settings));
+    }
 
-    let valhalla = match valhalla_result {
-        Ok(valhalla) => { valhalla }
+    // Check if we can connect to Valhalla
+    match ValhallaPool::connect(&settings.urls, settings.timeout, cli.verbose).await {
+        Ok(pool) => Some(ValhallaMatcher::new(Some(pool), cache, settings)),
         Err(e) => {
             eprintln!("Failed to connect to Valhalla: {}", e);
-            return;
+            None
         }
+    }
+}
+
+/// Loads the road network for the HMM matcher. The network of an earlier match must
+/// be loaded before `create_tables` drops it.
+fn hmm_matcher(cli: &Cli, db: &EveDb, args: &MatchCommandArgs) -> Option<HmmMatcher> {
+    if cli.verbose {
+        println!("Loading the road network for the HMM matcher")
+    }
+
+    let network = match args.hmm_network {
+        HmmNetwork::Matched => db
+            .get_nodes()
+            .and_then(|nodes| Ok((nodes, db.get_network_edges()?))),
+        HmmNetwork::Osm => db.get_osm_nodes().and_then(|nodes| {
+            let edges = osm_edges(&nodes, &db.get_osm_ways()?);
+            Ok((nodes, edges))
+        }),
     };
+    let (nodes, edges) = match network {
+        Ok(network) => network,
+        Err(e) => {
+            eprintln!("Failed to load the road network: {}", e);
+            return None;
+        }
+    };
+    let matcher = HmmMatcher::new(nodes, &edges, args.hmm_sigma, args.hmm_beta);
+    if matcher.is_empty() {
+        match args.hmm_network {
+            HmmNetwork::Matched => {
+                eprintln!("The HMM matcher needs the node and edge tables of an earlier match")
+            }
+            HmmNetwork::Osm => eprintln!("The HMM matcher needs an imported OSM network"),
+        }
+        return None;
+    }
+    Some(matcher)
+}
 
-    if !create_tables(cli, &db) {
+pub async fn build_nodes(cli: &Cli, args: &MatchCommandArgs) {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    match args.matcher {
+        Matcher::Valhalla => {
+            if let Some(matcher) = valhalla_matcher(cli, args).await {
+                match_trajectories(cli, &db, args, &matcher).await;
+            }
+        }
+        Matcher::Hmm => {
+            if let Some(matcher) = hmm_matcher(cli, &db, args) {
+                match_trajectories(cli, &db, args, &matcher).await;
+            }
+        }
+    }
+}
+
+/// Observed travel time (s) of each movement between consecutive matched nodes. The
+/// distance along the GPS trace is scaled to the matched path length, so that the time
+/// at each node is interpolated between the way points around it. Waits at a node count
+/// towards the movement that leaves it. Without a usable trace the times are unknown.
+fn edge_times(way_points: &[WayPoint], nodes: &[Node]) -> Vec<Option<f64>> {
+    let edge_count = nodes.len().saturating_sub(1);
+    let mut trace_m = Vec::with_capacity(way_points.len());
+    let mut total_m = 0.0;
+    for (i, point) in way_points.iter().enumerate() {
+        if i > 0 {
+            let prev = &way_points[i - 1];
+            total_m += Haversine.distance(
+                Point::new(prev.longitude, prev.latitude),
+                Point::new(point.longitude, point.latitude),
+            );
+        }
+        trace_m.push(total_m);
+    }
+    let path_m: Vec<f64> = std::iter::once(0.0)
+        .chain(nodes.windows(2).scan(0.0, |sum, pair| {
+            *sum += pair[0].distance_to(&pair[1]);
+            Some(*sum)
+        }))
+        .collect();
+    let path_total_m = path_m.last().copied().unwrap_or(0.0);
+    if way_points.len() < 2 || total_m <= 0.0 || path_total_m <= 0.0 {
+        return vec![None; edge_count];
+    }
+
+    // Earliest time at which the trace reaches `distance_m`
+    let time_at = |distance_m: f64| {
+        let target_m = distance_m / path_total_m * total_m;
+        let next = trace_m.partition_point(|&d| d < target_m);
+        if next == 0 {
+            return way_points[0].time as f64;
+        }
+        let Some(after) = way_points.get(next) else {
+            return way_points[way_points.len() - 1].time as f64;
+        };
+        let before = &way_points[next - 1];
+        let span_m = trace_m[next] - trace_m[next - 1];
+        let t = (target_m - trace_m[next - 1]) / span_m;
+        before.time as f64 + (after.time - before.time) as f64 * t
+    };
+    let node_times: Vec<f64> = path_m.iter().map(|&d| time_at(d)).collect();
+    node_times.windows(2).map(|t| Some(t[1] - t[0])).collect()
+}
+
+async fn match_trajectories(
+    cli: &Cli,
+    db: &EveDb,
+    args: &MatchCommandArgs,
+    matcher: &impl MapMatcher,
+) {
+    if !create_tables(cli, db) {
         return;
     }
 
@@ -177,64 +281,136 @@ pub async fn build_nodes(cli: &Cli) {
         println!("Populating the node table")
     }
 
+    let pipeline = get_filter_pipeline(args);
+    let filter_settings = pipeline.settings().to_string();
+
     let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
-    for trajectory_id in trajectory_ids.iter().progress() {
-        if let Ok(way_points) = db.get_way_points(*trajectory_id) {
-            let locations = way_points.iter().map(|p: &WayPoint| p.into());
+    let progress = ProgressBar::new(trajectory_ids.len() as u64);
+
+    // Up to `concurrency` requests are in flight, while the results are stored in
+    // trajectory order so that the node ids do not depend on response timing
+    let pipeline = &pipeline;
+    let mut matches = stream::iter(trajectory_ids)
+        .map(|trajectory_id| async move {
+            let Ok(way_points) = db.get_way_points(trajectory_id) else {
+                return (trajectory_id, None);
+            };
+            let point_count = way_points.len();
+            let (way_points, reports) = pipeline.apply(way_points);
+            let trip = matcher.map_match(&way_points).await;
+            (trajectory_id, Some((point_count, way_points, reports, trip)))
+        })
+        .buffered(args.concurrency.max(1));
+
+    while let Some((trajectory_id, result)) = matches.next().await {
+        progress.inc(1);
+        let Some((point_count, way_points, reports, trip)) = result else {
+            eprintln!("Failed to get way points for trajectory {}", trajectory_id);
+            continue;
+        };
+
+        if let Err(e) = db.insert_match_metadata(
+            trajectory_id,
+            point_count,
+            way_points.len(),
+            &filter_settings,
+            matcher.search_radius_m(),
+            &reports,
+        ) {
+            eprintln!("Failed to insert match metadata: {}", e);
+        }
+
+        match trip {
+            Ok(trip) => {
+                if let Some(warnings) = trip.warnings {
+                    let message = format!(
+                        "Map match for trajectory {} has warnings: {}",
+                        trajectory_id, warnings
+                    );
+                    eprintln!("{}", message);
+                    if let Err(e) = db.insert_match_error(trajectory_id, &message) {
+                        eprintln!("Failed to insert match error: {}", e);
+                    }
+                } else {
+                    let mut nodes: Vec<Node> = trip
+                        .shape
+                        .iter()
+                        .map(|&[lat, lon]| build_node(db, &ShapePoint { lat, lon }))
+                        .collect();
 
-            match map_match(&valhalla, locations).await {
-                Ok(trip) => {
-                    if let Some(warnings) = trip.warnings {
+                    // Insert the nodes into the database
+                    if let Err(e) = db.insert_nodes(trajectory_id, &mut nodes) {
                         let message = format!(
-                            "Map match for trajectory {} has warnings: {:?}",
-                            trajectory_id, warnings
+                            "Failed to insert nodes for trajectory {}: {:?}",
+                            trajectory_id, e
                         );
                         eprintln!("{}", message);
-                        if let Err(e) = db.insert_match_error(*trajectory_id, &message) {
+                        if let Err(e) = db.insert_match_error(trajectory_id, &message) {
                             eprintln!("Failed to insert match error: {}", e);
                         }
-                    } else {
-                        let mut nodes: Vec<Node> = trip
-                            .legs
-                            .iter()
-                            .flat_map(|leg| leg.shape.iter())
-                            .map(|pt| build_node(&db, pt))
-                            .collect();
-
-                        // Insert the nodes into the database
-                        if let Err(e) = db.insert_nodes(*trajectory_id, &mut nodes) {
-                            let message = format!(
-                                "Failed to insert nodes for trajectory {}: {:?}",
-                                trajectory_id, e
-                            );
-                            eprintln!("{}", message);
-                            if let Err(e) = db.insert_match_error(*trajectory_id, &message) {
-                                eprintln!("Failed to insert match error: {}", e);
-                            }
-                        }
+                    }
 
-                        // Insert the edges into the database
-                        if let Err(e) = db.insert_edges(*trajectory_id, &nodes) {
-                            let message = format!(
-                                "Failed to insert edges for trajectory {}: {:?}",
-                                trajectory_id, e
-                            );
-                            eprintln!("{}", message);
-                        }
+                    // Insert the edges into the database
+                    let times_s = edge_times(&way_points, &nodes);
+                    if let Err(e) = db.insert_edges(trajectory_id, &nodes, &times_s) {
+                        let message = format!(
+                            "Failed to insert edges for trajectory {}: {:?}",
+                            trajectory_id, e
+                        );
+                        eprintln!("{}", message);
                     }
                 }
-                Err(e) => {
-                    let message =
-                        format!("Failed to map match trajectory {}: {:?}", trajectory_id, e.to_string());
-                    eprintln!("{}", message);
-                    if let Err(e) = db.insert_match_error(*trajectory_id, &message) {
-                        eprintln!("Failed to insert match error: {}", e);
-                    }
-                    // return;
+            }
+            Err(e) => {
+                let message =
+                    format!("Failed to map match trajectory {}: {:?}", trajectory_id, e.to_string());
+                eprintln!("{}", message);
+                if let Err(e) = db.insert_match_error(trajectory_id, &message) {
+                    eprintln!("Failed to insert match error: {}", e);
                 }
             }
-        } else {
-            eprintln!("Failed to get way points for trajectory {}", trajectory_id);
         }
     }
+    progress.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(latitude: f64) -> Node {
+        Node {
+            id: 0,
+            latitude,
+            longitude: -83.7,
+            altitude: 0.0,
+            h3_12: 0,
+        }
+    }
+
+    fn wp(time: i64, latitude: f64) -> WayPoint {
+        WayPoint {
+            time,
+            latitude,
+            longitude: -83.7,
+        }
+    }
+
+    #[test]
+    fn test_edge_times() {
+        // Two equal edges, a 20 s wait halfway and a slower second half
+        let nodes = vec![node(42.000), node(42.001), node(42.002)];
+        let way_points = vec![
+            wp(0, 42.000),
+            wp(10, 42.001),
+            wp(30, 42.001),
+            wp(50, 42.002),
+        ];
+        let times = edge_times(&way_points, &nodes);
+        assert_eq!(times.len(), 2);
+        assert!((times[0].unwrap() - 10.0).abs() < 1e-6);
+        assert!((times[1].unwrap() - 40.0).abs() < 1e-6);
+
+        assert_eq!(edge_times(&way_points[..1], &nodes), vec![None, None]);
+    }
 }
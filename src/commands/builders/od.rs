@@ -0,0 +1,157 @@
+use crate::cli::{Cli, OdCommandArgs};
+use crate::db::evedb::EveDb;
+use crate::export::od::export_od_cells;
+use crate::models::od::{OdCell, OdTrip};
+use crate::tools::{h3_to_parent, median};
+use chrono::{DateTime, Datelike, Timelike};
+use std::collections::HashMap;
+
+#[derive(Hash, PartialEq, Eq)]
+struct OdKey {
+    h3_ini: u64,
+    h3_end: u64,
+    vehicle_type: Option<String>,
+    hour: u32,
+    weekday: u32,
+}
+
+fn aggregate_od(trips: &[OdTrip], resolution: u8) -> Vec<OdCell> {
+    let mut groups: HashMap<OdKey, (Vec<f64>, Vec<f64>)> = HashMap::new();
+
+    for trip in trips {
+        // The start time keeps the local offset, so hour and weekday are local
+        let Ok(dt_ini) = DateTime::parse_from_rfc3339(&trip.dt_ini) else {
+            continue;
+        };
+        let (Some(h3_ini), Some(h3_end)) = (
+            h3_to_parent(trip.h3_12_ini, resolution),
+            h3_to_parent(trip.h3_12_end, resolution),
+        ) else {
+            continue;
+        };
+        let key = OdKey {
+            h3_ini,
+            h3_end,
+            vehicle_type: trip.vehicle_type.clone(),
+            hour: dt_ini.hour(),
+            weekday: dt_ini.weekday().num_days_from_monday(),
+        };
+        let (durations, lengths) = groups.entry(key).or_default();
+        durations.push(trip.duration_s);
+        lengths.push(trip.length_m);
+    }
+
+    let mut cells: Vec<OdCell> = groups
+        .into_iter()
+        .map(|(key, (mut durations, mut lengths))| OdCell {
+            h3_ini: key.h3_ini,
+            h3_end: key.h3_end,
+            resolution,
+            vehicle_type: key.vehicle_type,
+            hour: key.hour,
+            weekday: key.weekday,
+            trip_count: durations.len() as i64,
+            median_duration_s: median(&mut durations).unwrap_or(0.0),
+            median_length_m: median(&mut lengths).unwrap_or(0.0),
+        })
+        .collect();
+    cells.sort_by(|a, b| {
+        (a.h3_ini, a.h3_end, &a.vehicle_type, a.hour, a.weekday)
+            .cmp(&(b.h3_ini, b.h3_end, &b.vehicle_type, b.hour, b.weekday))
+    });
+    cells
+}
+
+pub fn build_od(cli: &Cli, args: &OdCommandArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the od_matrix table")
+    }
+
+    if let Err(e) = db.create_od_table() {
+        eprintln!("Failed to create od_matrix table: {}", e);
+        return false;
+    }
+
+    let trips = match db.get_od_trips() {
+        Ok(trips) => trips,
+        Err(e) => {
+            eprintln!("Failed to read trajectories: {}", e);
+            return false;
+        }
+    };
+
+    let cells = aggregate_od(&trips, args.resolution);
+    if cli.verbose {
+        println!(
+            "Aggregated {} trips into {} origin-destination cells at resolution {}",
+            trips.len(),
+            cells.len(),
+            args.resolution
+        )
+    }
+
+    if let Err(e) = db.insert_od_cells(&cells) {
+        eprintln!("Failed to insert od_matrix cells: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.create_od_indexes() {
+        eprintln!("Failed to create od_matrix indexes: {}", e);
+        return false;
+    }
+
+    if let Some(path) = &args.export {
+        if cli.verbose {
+            println!("Exporting the od_matrix table to {}", path)
+        }
+        if let Err(e) = export_od_cells(path, args.format, &cells) {
+            eprintln!("Failed to export od_matrix table: {}", e);
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::lat_lng_to_h3_12;
+
+    fn trip(vehicle_type: &str, dt_ini: &str, duration_s: f64, length_m: f64) -> OdTrip {
+        OdTrip {
+            vehicle_type: Some(vehicle_type.to_string()),
+            dt_ini: dt_ini.to_string(),
+            duration_s,
+            length_m,
+            h3_12_ini: lat_lng_to_h3_12(42.2808, -83.7430),
+            h3_12_end: lat_lng_to_h3_12(42.3314, -83.0458),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_od() {
+        let trips = vec![
+            trip("ICE", "2017-11-06T08:15:00-05:00", 600.0, 1000.0),
+            trip("ICE", "2017-11-06T08:45:00-05:00", 900.0, 3000.0),
+            trip("ICE", "2017-11-06T08:50:00-05:00", 300.0, 2000.0),
+            trip("EV", "2017-11-06T08:30:00-05:00", 700.0, 5000.0),
+            trip("ICE", "not a date", 100.0, 100.0),
+        ];
+        let cells = aggregate_od(&trips, 7);
+
+        assert_eq!(cells.len(), 2);
+        let ice = cells
+            .iter()
+            .find(|c| c.vehicle_type.as_deref() == Some("ICE"))
+            .unwrap();
+        assert_eq!(ice.resolution, 7);
+        assert_eq!(ice.hour, 8);
+        assert_eq!(ice.weekday, 0);
+        assert_eq!(ice.trip_count, 3);
+        assert_eq!(ice.median_duration_s, 600.0);
+        assert_eq!(ice.median_length_m, 2000.0);
+        assert_eq!(ice.h3_ini, h3_to_parent(trips[0].h3_12_ini, 7).unwrap());
+    }
+}
@@ -0,0 +1,402 @@
+use crate::cli::{Cli, OsmImportArgs};
+use crate::db::evedb::EveDb;
+use crate::models::edge::NetworkEdge;
+use crate::models::node::Node;
+use crate::models::osm::{OsmWay, RoadClassCoverage};
+use crate::tools::lat_lng_to_h3_12;
+use anyhow::{Result, anyhow};
+use osmpbf::{Element, ElementReader};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Metres per degree of latitude, used to widen the signal bounding box
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Distance (m) below which a matched node is taken to be an OSM node
+const SAME_NODE_M: f64 = 1.0;
+
+/// OSM node coordinates as `(lat, lon)` by node id
+type Coordinates = HashMap<i64, (f64, f64)>;
+
+/// Reads the drivable ways of the extract and the coordinates of their nodes. Ways
+/// come after nodes in a PBF file, so this takes two passes.
+fn read_pbf(path: &str) -> Result<(Vec<OsmWay>, Coordinates)> {
+    let mut ways: Vec<OsmWay> = Vec::new();
+    ElementReader::from_path(path)
+        .and_then(|reader| {
+            reader.for_each(|element| {
+                if let Element::Way(way) = element
+                    && let Some(way) = OsmWay::from_tags(way.id(), way.tags(), way.refs().collect())
+                {
+                    ways.push(way);
+                }
+            })
+        })
+        .map_err(|e| anyhow!("Failed to read ways from {}: {}", path, e))?;
+
+    let referenced: HashSet<i64> = ways.iter().flat_map(|w| w.nodes.iter().copied()).collect();
+    let mut coordinates: Coordinates = HashMap::new();
+    ElementReader::from_path(path)
+        .and_then(|reader| {
+            reader.for_each(|element| {
+                let (id, lat, lon) = match element {
+                    Element::Node(node) => (node.id(), node.lat(), node.lon()),
+                    Element::DenseNode(node) => (node.id(), node.lat(), node.lon()),
+                    _ => return,
+                };
+                if referenced.contains(&id) {
+                    coordinates.insert(id, (lat, lon));
+                }
+            })
+        })
+        .map_err(|e| anyhow!("Failed to read nodes from {}: {}", path, e))?;
+    Ok((ways, coordinates))
+}
+
+/// Keeps the ways with at least one node inside `(min_lat, min_lon, max_lat, max_lon)`
+/// and all their nodes, so that roads crossing the edge of the box stay whole. Ways
+/// with nodes missing from the extract are dropped.
+fn clip(
+    ways: Vec<OsmWay>,
+    coordinates: &Coordinates,
+    bounds: (f64, f64, f64, f64),
+) -> (Vec<Node>, Vec<OsmWay>) {
+    let (min_lat, min_lon, max_lat, max_lon) = bounds;
+    let inside = |&(lat, lon): &(f64, f64)| {
+        (min_lat..=max_lat).contains(&lat) && (min_lon..=max_lon).contains(&lon)
+    };
+
+    let mut nodes: BTreeMap<i64, Node> = BTreeMap::new();
+    let mut kept: Vec<OsmWay> = Vec::new();
+    for mut way in ways {
+        let Some(points) = way
+            .nodes
+            .iter()
+            .map(|id| coordinates.get(id).map(|&(lat, lon)| (*id, lat, lon)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        if !points.iter().any(|&(_, lat, lon)| inside(&(lat, lon))) {
+            continue;
+        }
+
+        for &(id, latitude, longitude) in &points {
+            nodes.entry(id).or_insert_with(|| Node {
+                id,
+                latitude,
+                longitude,
+                altitude: 0.0,
+                h3_12: lat_lng_to_h3_12(latitude, longitude) as i64,
+            });
+        }
+        way.length_m = way
+            .nodes
+            .windows(2)
+            .map(|pair| nodes[&pair[0]].distance_to(&nodes[&pair[1]]))
+            .sum();
+        kept.push(way);
+    }
+    (nodes.into_values().collect(), kept)
+}
+
+/// Splits the ways into one edge per pair of consecutive nodes, in both directions
+/// unless the way is one-way, numbering the edges from 1
+pub fn osm_edges(nodes: &[Node], ways: &[OsmWay]) -> Vec<NetworkEdge> {
+    let by_id: HashMap<i64, &Node> = nodes.iter().map(|n| (n.id, n)).collect();
+    let mut edges: Vec<NetworkEdge> = Vec::new();
+    for way in ways {
+        for pair in way.nodes.windows(2) {
+            let (Some(a), Some(b)) = (by_id.get(&pair[0]), by_id.get(&pair[1])) else {
+                continue;
+            };
+            let mut push = |from: &Node, to: &Node| {
+                edges.push(NetworkEdge {
+                    id: edges.len() as i64 + 1,
+                    node_ini: from.id,
+                    node_end: to.id,
+                    length_m: from.distance_to(to),
+                    bearing_deg: from.bearing_to(to),
+                    traversals: 0,
+                    mean_speed_kmh: way.max_speed_kmh,
+                });
+            };
+            push(a, b);
+            if !way.oneway {
+                push(b, a);
+            }
+        }
+    }
+    edges
+}
+
+/// Links the matched edges to the OSM ways whose consecutive nodes they join, and sums
+/// the OSM length per road class alongside the part of it that some edge traverses.
+/// Matched nodes are OSM nodes when they lie in the same H3 cell within 1 m.
+fn osm_coverage(
+    nodes: &[Node],
+    edges: &[NetworkEdge],
+    osm_nodes: &[Node],
+    ways: &[OsmWay],
+) -> (Vec<(i64, i64)>, Vec<RoadClassCoverage>) {
+    let mut cells: HashMap<i64, Vec<&Node>> = HashMap::new();
+    for node in osm_nodes {
+        cells.entry(node.h3_12).or_default().push(node);
+    }
+    let to_osm: HashMap<i64, i64> = nodes
+        .iter()
+        .filter_map(|node| {
+            cells
+                .get(&node.h3_12)?
+                .iter()
+                .map(|osm| (osm.id, node.distance_to(osm)))
+                .filter(|&(_, distance_m)| distance_m <= SAME_NODE_M)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(osm_id, _)| (node.id, osm_id))
+        })
+        .collect();
+
+    // Undirected OSM segments and the ways they belong to
+    let key = |a: i64, b: i64| (a.min(b), a.max(b));
+    let mut segments: HashMap<(i64, i64), usize> = HashMap::new();
+    for (i, way) in ways.iter().enumerate() {
+        for pair in way.nodes.windows(2) {
+            segments.entry(key(pair[0], pair[1])).or_insert(i);
+        }
+    }
+
+    let mut links: Vec<(i64, i64)> = Vec::new();
+    let mut covered: HashSet<(i64, i64)> = HashSet::new();
+    for edge in edges {
+        let (Some(&a), Some(&b)) = (to_osm.get(&edge.node_ini), to_osm.get(&edge.node_end)) else {
+            continue;
+        };
+        if let Some(&way) = segments.get(&key(a, b)) {
+            links.push((edge.id, ways[way].id));
+            covered.insert(key(a, b));
+        }
+    }
+
+    let by_id: HashMap<i64, &Node> = osm_nodes.iter().map(|n| (n.id, n)).collect();
+    let mut classes: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+    let mut counted: HashSet<(i64, i64)> = HashSet::new();
+    for way in ways {
+        let totals = classes.entry(way.highway.as_str()).or_default();
+        for pair in way.nodes.windows(2) {
+            let segment = key(pair[0], pair[1]);
+            let (Some(a), Some(b)) = (by_id.get(&pair[0]), by_id.get(&pair[1])) else {
+                continue;
+            };
+            if !counted.insert(segment) {
+                continue;
+            }
+            let length_m = a.distance_to(b);
+            totals.0 += length_m;
+            if covered.contains(&segment) {
+                totals.1 += length_m;
+            }
+        }
+    }
+
+    let coverage = classes
+        .into_iter()
+        .map(|(highway, (osm_length_m, covered_length_m))| RoadClassCoverage {
+            highway: highway.to_string(),
+            osm_length_m,
+            covered_length_m,
+        })
+        .collect();
+    (links, coverage)
+}
+
+pub fn import_osm(cli: &Cli, args: &OsmImportArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let bounds = match db.get_signal_bounds() {
+        Ok(Some(bounds)) => bounds,
+        Ok(None) => {
+            eprintln!("There are no signals to clip the OSM extract to");
+            return false;
+        }
+        Err(e) => {
+            eprintln!("Failed to get the signal bounds: {}", e);
+            return false;
+        }
+    };
+    let (min_lat, min_lon, max_lat, max_lon) = bounds;
+    let margin_lat = args.margin / METERS_PER_DEGREE;
+    let margin_lon = margin_lat / min_lat.abs().max(max_lat.abs()).to_radians().cos();
+    let bounds = (
+        min_lat - margin_lat,
+        min_lon - margin_lon,
+        max_lat + margin_lat,
+        max_lon + margin_lon,
+    );
+
+    if cli.verbose {
+        println!("Reading the drivable ways from {}", args.pbf)
+    }
+
+    let (ways, coordinates) = match read_pbf(&args.pbf) {
+        Ok(network) => network,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    let (nodes, ways) = clip(ways, &coordinates, bounds);
+
+    if cli.verbose {
+        println!("Inserting {} OSM nodes and {} ways", nodes.len(), ways.len())
+    }
+
+    if let Err(e) = db.create_osm_tables() {
+        eprintln!("Failed to create OSM tables: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.insert_osm_network(&nodes, &ways) {
+        eprintln!("Failed to insert the OSM network: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.create_osm_indexes() {
+        eprintln!("Failed to create OSM indexes: {}", e);
+        return false;
+    }
+    true
+}
+
+pub fn network_coverage(cli: &Cli) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let network = (
+        db.get_nodes(),
+        db.get_network_edges(),
+        db.get_osm_nodes(),
+        db.get_osm_ways(),
+    );
+    let (nodes, edges, osm_nodes, ways) = match network {
+        (Ok(nodes), Ok(edges), Ok(osm_nodes), Ok(ways)) => (nodes, edges, osm_nodes, ways),
+        (Err(e), ..) | (_, Err(e), ..) | (.., Err(e), _) | (.., Err(e)) => {
+            eprintln!("Failed to load the road networks: {}", e);
+            return false;
+        }
+    };
+
+    let (links, coverage) = osm_coverage(&nodes, &edges, &osm_nodes, &ways);
+
+    if let Err(e) = db.create_edge_osm_table() {
+        eprintln!("Failed to create edge_osm table: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.insert_edge_osm(&links) {
+        eprintln!("Failed to insert edge_osm: {}", e);
+        return false;
+    }
+
+    println!("Edges on OSM ways: {} of {}", links.len(), edges.len());
+    for class in &coverage {
+        let share = if class.osm_length_m > 0.0 {
+            100.0 * class.covered_length_m / class.osm_length_m
+        } else {
+            0.0
+        };
+        println!(
+            "{}: {:.1} of {:.1} km ({:.1}%)",
+            class.highway,
+            class.covered_length_m / 1000.0,
+            class.osm_length_m / 1000.0,
+            share
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn way(id: i64, highway: &str, oneway: bool, nodes: Vec<i64>) -> OsmWay {
+        OsmWay {
+            id,
+            highway: highway.to_string(),
+            name: None,
+            oneway,
+            max_speed_kmh: None,
+            length_m: 0.0,
+            nodes,
+        }
+    }
+
+    fn coordinates() -> Coordinates {
+        HashMap::from([
+            (1, (42.000, -83.700)),
+            (2, (42.000, -83.699)),
+            (3, (42.000, -83.698)),
+            (4, (42.100, -83.700)),
+            (5, (42.100, -83.699)),
+        ])
+    }
+
+    #[test]
+    fn test_clip_and_osm_edges() {
+        let ways = vec![
+            way(10, "primary", false, vec![1, 2, 3]),
+            way(11, "residential", true, vec![3, 4]),
+            way(12, "residential", false, vec![4, 5]),
+            way(13, "service", false, vec![5, 6]),
+        ];
+        let (nodes, ways) = clip(ways, &coordinates(), (41.99, -83.71, 42.01, -83.69));
+
+        // 11 leaves the box but stays whole, 12 is outside and 13 lacks a node
+        assert_eq!(ways.iter().map(|w| w.id).collect::<Vec<_>>(), vec![10, 11]);
+        assert_eq!(nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!((ways[0].length_m - nodes[0].distance_to(&nodes[2])).abs() < 1e-6);
+
+        let edges = osm_edges(&nodes, &ways);
+        let pairs: Vec<(i64, i64)> = edges.iter().map(|e| (e.node_ini, e.node_end)).collect();
+        assert_eq!(pairs, vec![(1, 2), (2, 1), (2, 3), (3, 2), (3, 4)]);
+        assert_eq!(edges.last().unwrap().id, 5);
+    }
+
+    #[test]
+    fn test_osm_coverage() {
+        let ways = vec![
+            way(10, "primary", false, vec![1, 2, 3]),
+            way(11, "residential", true, vec![3, 4]),
+        ];
+        let (osm_nodes, ways) = clip(ways, &coordinates(), (41.99, -83.71, 42.01, -83.69));
+
+        // Matched nodes on OSM nodes 2 and 3, and one off the OSM network
+        let matched = |id: i64, osm: &Node| Node { id, ..*osm };
+        let nodes = vec![
+            matched(100, &osm_nodes[1]),
+            matched(101, &osm_nodes[2]),
+            Node {
+                id: 102,
+                latitude: 42.05,
+                longitude: -83.7,
+                altitude: 0.0,
+                h3_12: lat_lng_to_h3_12(42.05, -83.7) as i64,
+            },
+        ];
+        let edge = |id: i64, node_ini: i64, node_end: i64| NetworkEdge {
+            id,
+            node_ini,
+            node_end,
+            length_m: 0.0,
+            bearing_deg: 0.0,
+            traversals: 1,
+            mean_speed_kmh: None,
+        };
+        let edges = vec![edge(1, 101, 100), edge(2, 101, 102)];
+
+        let (links, coverage) = osm_coverage(&nodes, &edges, &osm_nodes, &ways);
+        assert_eq!(links, vec![(1, 10)]);
+        assert_eq!(coverage[0].highway, "primary");
+        assert!((coverage[0].covered_length_m * 2.0 - coverage[0].osm_length_m).abs() < 1e-6);
+        assert_eq!(coverage[1].highway, "residential");
+        assert_eq!(coverage[1].covered_length_m, 0.0);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::cli::Cli;
+use crate::db::evedb::EveDb;
+use crate::matcher::SEARCH_RADIUS_M;
+use crate::models::node::Node;
+use crate::models::quality::MatchQuality;
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+
+/// Mean Earth radius (m), matching the one used by the Haversine distances
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Distance (m) from a point to the nearest segment of a shape, projecting both onto a
+/// local plane around the point, which is accurate at map-matching distances
+fn distance_to_shape(latitude: f64, longitude: f64, shape: &[Node]) -> Option<f64> {
+    let scale_x = latitude.to_radians().cos() * EARTH_RADIUS_M;
+    let project = |n: &Node| {
+        (
+            (n.longitude - longitude).to_radians() * scale_x,
+            (n.latitude - latitude).to_radians() * EARTH_RADIUS_M,
+        )
+    };
+
+    let segment_distance = |(ax, ay): (f64, f64), (bx, by): (f64, f64)| {
+        let (dx, dy) = (bx - ax, by - ay);
+        let length2 = dx * dx + dy * dy;
+        let t = if length2 > 0.0 {
+            (-(ax * dx + ay * dy) / length2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (ax + t * dx).hypot(ay + t * dy)
+    };
+
+    match shape {
+        [] => None,
+        [node] => Some(segment_distance(project(node), project(node))),
+        _ => shape
+            .windows(2)
+            .map(|pair| segment_distance(project(&pair[0]), project(&pair[1])))
+            .reduce(f64::min),
+    }
+}
+
+fn distance_stats(
+    points: &[(f64, f64)],
+    shape: &[Node],
+    search_radius_m: f64,
+) -> Option<(f64, f64, usize)> {
+    let distances: Vec<f64> = points
+        .iter()
+        .filter_map(|&(lat, lon)| distance_to_shape(lat, lon, shape))
+        .collect();
+    if distances.is_empty() {
+        return None;
+    }
+    let mean = distances.iter().sum::<f64>() / distances.len() as f64;
+    let max = distances.iter().copied().fold(0.0, f64::max);
+    let beyond = distances.iter().filter(|&&d| d > search_radius_m).count();
+    Some((mean, max, beyond))
+}
+
+/// Compares the GPS points and eVED's matched points of a trajectory with our matched
+/// shape, counting the points beyond the search radius the matcher used. Returns `None`
+/// for trajectories without a shape or GPS points.
+fn measure_quality(
+    traj_id: i64,
+    length_m: Option<f64>,
+    search_radius_m: f64,
+    gps_points: &[(f64, f64)],
+    eved_points: &[(f64, f64)],
+    shape: &[Node],
+) -> Option<MatchQuality> {
+    let (mean_distance_m, max_distance_m, beyond) =
+        distance_stats(gps_points, shape, search_radius_m)?;
+    let matched_length_m: f64 = shape.windows(2).map(|p| p[0].distance_to(&p[1])).sum();
+    let eved = distance_stats(eved_points, shape, search_radius_m);
+
+    Some(MatchQuality {
+        traj_id,
+        point_count: gps_points.len() as i64,
+        mean_distance_m,
+        max_distance_m,
+        matched_length_m,
+        length_ratio: length_m
+            .filter(|&l| l > 0.0)
+            .map(|l| matched_length_m / l),
+        beyond_radius_share: beyond as f64 / gps_points.len() as f64,
+        eved_mean_distance_m: eved.map(|(mean, _, _)| mean),
+        eved_max_distance_m: eved.map(|(_, max, _)| max),
+    })
+}
+
+pub fn build_match_quality(cli: &Cli) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the match_quality table")
+    }
+
+    if let Err(e) = db.create_match_quality_table() {
+        eprintln!("Failed to create match_quality table: {}", e);
+        return false;
+    }
+
+    let trajectories = db.get_trajectory_lengths().unwrap_or(vec![]);
+    let progress = ProgressBar::new(trajectories.len() as u64);
+    let rows: Vec<MatchQuality> = trajectories
+        .par_iter()
+        .filter_map(|&(traj_id, length_m)| {
+            progress.inc(1);
+            let shape = db.get_matched_shape(traj_id).unwrap_or(vec![]);
+            if shape.is_empty() {
+                return None;
+            }
+            let gps_points: Vec<(f64, f64)> = db
+                .get_way_points(traj_id)
+                .unwrap_or(vec![])
+                .iter()
+                .map(|p| (p.latitude, p.longitude))
+                .collect();
+            let eved_points: Vec<(f64, f64)> = db
+                .get_trajectory_points(traj_id)
+                .unwrap_or(vec![])
+                .iter()
+                .map(|p| (p.latitude, p.longitude))
+                .collect();
+            // Matches stored before the radius was recorded used the default one
+            let search_radius_m = db
+                .get_search_radius(traj_id)
+                .ok()
+                .flatten()
+                .unwrap_or(SEARCH_RADIUS_M);
+            measure_quality(
+                traj_id,
+                length_m,
+                search_radius_m,
+                &gps_points,
+                &eved_points,
+                &shape,
+            )
+        })
+        .collect();
+    progress.finish();
+
+    if cli.verbose {
+        println!("Inserting {} match quality records", rows.len())
+    }
+
+    if let Err(e) = db.insert_match_quality(&rows) {
+        eprintln!("Failed to insert match quality: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, latitude: f64, longitude: f64) -> Node {
+        Node {
+            id,
+            latitude,
+            longitude,
+            altitude: 0.0,
+            h3_12: 0,
+        }
+    }
+
+    #[test]
+    fn test_distance_to_shape() {
+        // East-west street along 42° N
+        let shape = vec![node(1, 42.0, -83.70), node(2, 42.0, -83.69)];
+        let north = distance_to_shape(42.0001, -83.695, &shape).unwrap();
+        assert!((north - 11.12).abs() < 0.01);
+
+        // Beyond the end of the street the distance is to the end node
+        let east = distance_to_shape(42.0, -83.689, &shape).unwrap();
+        assert!((east - shape[1].distance_to(&node(0, 42.0, -83.689))).abs() < 0.01);
+
+        assert_eq!(distance_to_shape(42.0, -83.7, &[]), None);
+        assert!(distance_to_shape(42.0, -83.7, &shape[..1]).unwrap() < 1e-9);
+    }
+
+    #[test]
+    fn test_measure_quality() {
+        let shape = vec![node(1, 42.0, -83.70), node(2, 42.0, -83.69)];
+        let gps = vec![(42.0001, -83.699), (42.0001, -83.695), (42.002, -83.691)];
+        let eved = vec![(42.0, -83.699), (42.0, -83.691)];
+        let length_m = shape[0].distance_to(&shape[1]);
+        let quality =
+            measure_quality(7, Some(length_m), SEARCH_RADIUS_M, &gps, &eved, &shape).unwrap();
+
+        assert_eq!(quality.point_count, 3);
+        assert!((quality.max_distance_m - 222.4).abs() < 0.1);
+        assert!((quality.length_ratio.unwrap() - 1.0).abs() < 1e-9);
+        assert!((quality.beyond_radius_share - 1.0 / 3.0).abs() < 1e-9);
+        assert!(quality.eved_max_distance_m.unwrap() < 1e-6);
+        assert!(measure_quality(7, None, SEARCH_RADIUS_M, &[], &eved, &shape).is_none());
+
+        // A wider search radius covers the far point
+        let quality = measure_quality(7, None, 250.0, &gps, &eved, &shape).unwrap();
+        assert_eq!(quality.beyond_radius_share, 0.0);
+    }
+}
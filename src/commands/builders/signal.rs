@@ -1,17 +1,26 @@
-use crate::cli::Cli;
+use crate::cli::{BuildCommandArgs, Cli};
 use crate::db::evedb::EveDb;
 use crate::etl::extract::signals::{get_signal_filenames, insert_signals};
+use crate::tools::TimeBase;
 use indicatif::ProgressIterator;
 
-fn process_signal_file(cli: &Cli, filename: &str) {
-    let result = insert_signals(cli, filename);
+fn process_signal_file(cli: &Cli, filename: &str, time_base: &TimeBase, local_time: bool) {
+    let result = insert_signals(cli, filename, time_base, local_time);
 
     if let Err(e) = result {
         eprintln!("Failed to insert signals {}", e);
     };
 }
 
-pub fn build_signals(cli: &Cli) -> bool {
+pub fn build_signals(cli: &Cli, args: &BuildCommandArgs) -> bool {
+    let time_base = match cli.time_base() {
+        Ok(time_base) => time_base,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+
     if cli.verbose {
         println!("Creating the signal table")
     }
@@ -22,9 +31,14 @@ pub fn build_signals(cli: &Cli) -> bool {
         return false;
     }
 
+    if db.create_focus_point_table().is_err() {
+        eprintln!("Failed to create focus_point table");
+        return false;
+    }
+
     if let Ok(filenames) = get_signal_filenames(cli) {
         for filename in filenames.iter().progress() {
-            process_signal_file(cli, filename);
+            process_signal_file(cli, filename, &time_base, args.local_time);
         }
     } else {
         eprintln!("Failed to get signal file names");
@@ -0,0 +1,179 @@
+use crate::cli::{Cli, StopsCommandArgs};
+use crate::commands::builders::node::find_nearest_node;
+use crate::db::evedb::EveDb;
+use crate::models::signal::TrajectorySignal;
+use crate::models::stop::StopEvent;
+use crate::models::trajectory::TrajectoryStops;
+use crate::tools::{TimeBase, lat_lng_to_h3_12};
+use indicatif::ProgressIterator;
+use std::ops::Range;
+
+fn is_stopped(signal: &TrajectorySignal, speed_threshold: f64) -> bool {
+    matches!(signal.speed, Some(speed) if speed <= speed_threshold)
+}
+
+/// Finds the index ranges of consecutive stopped signals lasting at least `min_duration_ms`.
+/// Signals without a speed reading end the current episode.
+fn detect_stops(
+    signals: &[TrajectorySignal],
+    speed_threshold: f64,
+    min_duration_ms: i64,
+) -> Vec<Range<usize>> {
+    let mut episodes: Vec<Range<usize>> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for i in 0..=signals.len() {
+        let stopped = i < signals.len() && is_stopped(&signals[i], speed_threshold);
+        match (start, stopped) {
+            (None, true) => start = Some(i),
+            (Some(ini), false) => {
+                if signals[i - 1].time_stamp - signals[ini].time_stamp >= min_duration_ms {
+                    episodes.push(ini..i);
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    episodes
+}
+
+fn build_stop_event(
+    db: &EveDb,
+    traj_id: i64,
+    signals: &[TrajectorySignal],
+    time_base: &TimeBase,
+    use_nodes: bool,
+) -> StopEvent {
+    let first = &signals[0];
+    let last = &signals[signals.len() - 1];
+    let latitude = signals.iter().map(|s| s.latitude).sum::<f64>() / signals.len() as f64;
+    let longitude = signals.iter().map(|s| s.longitude).sum::<f64>() / signals.len() as f64;
+    let node_id = if use_nodes {
+        find_nearest_node(db, latitude, longitude, 2).map(|(node, _)| node.id)
+    } else {
+        None
+    };
+
+    StopEvent::builder()
+        .traj_id(traj_id)
+        .latitude(latitude)
+        .longitude(longitude)
+        .h3_12(lat_lng_to_h3_12(latitude, longitude))
+        .time_stamp_ini(first.time_stamp)
+        .time_stamp_end(last.time_stamp)
+        .dt_ini(time_base.date_time(first.day_num, first.time_stamp).to_rfc3339())
+        .dt_end(time_base.date_time(first.day_num, last.time_stamp).to_rfc3339())
+        .duration_s((last.time_stamp - first.time_stamp) as f64 / 1000.0)
+        .maybe_node_id(node_id)
+        .build()
+}
+
+pub fn build_stops(cli: &Cli, args: &StopsCommandArgs) -> bool {
+    let time_base = match cli.time_base() {
+        Ok(time_base) => time_base,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the stop_event table")
+    }
+
+    if let Err(e) = db.create_stop_table() {
+        eprintln!("Failed to create stop_event table: {}", e);
+        return false;
+    }
+
+    // Stops are only linked to nodes after the trajectories were map-matched
+    let use_nodes = db.table_exists("node").unwrap_or(false);
+    let min_duration_ms = (args.min_duration * 1000.0) as i64;
+    let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
+    let mut stops: Vec<StopEvent> = Vec::new();
+    let mut trajectory_stops: Vec<TrajectoryStops> = Vec::with_capacity(trajectory_ids.len());
+
+    for trajectory_id in trajectory_ids.iter().progress() {
+        let signals = db.get_trajectory_signals(*trajectory_id).unwrap_or(vec![]);
+        let episodes = detect_stops(&signals, args.speed_threshold, min_duration_ms);
+        let events: Vec<StopEvent> = episodes
+            .into_iter()
+            .map(|range| {
+                build_stop_event(&db, *trajectory_id, &signals[range], &time_base, use_nodes)
+            })
+            .collect();
+
+        trajectory_stops.push(TrajectoryStops {
+            trajectory_id: *trajectory_id,
+            stop_count: events.len() as i64,
+            idle_s: events.iter().map(|e| e.duration_s).sum(),
+        });
+        stops.extend(events);
+    }
+
+    if cli.verbose {
+        println!("Inserting {} stop events", stops.len())
+    }
+
+    if let Err(e) = db.insert_stop_events(&stops) {
+        eprintln!("Failed to insert stop events: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.update_trajectory_stops(&trajectory_stops) {
+        eprintln!("Failed to update trajectory stops: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.create_stop_indexes() {
+        eprintln!("Failed to create stop_event indexes: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(time_stamp: i64, speed: Option<f64>) -> TrajectorySignal {
+        TrajectorySignal {
+            signal_id: time_stamp,
+            day_num: 1.0,
+            time_stamp,
+            latitude: 42.28,
+            longitude: -83.74,
+            speed,
+        }
+    }
+
+    #[test]
+    fn test_detect_stops() {
+        let signals = vec![
+            signal(0, Some(30.0)),
+            signal(1000, Some(0.0)),
+            signal(4000, Some(0.5)),
+            signal(8000, Some(0.0)),
+            signal(9000, Some(20.0)),
+            // Too short
+            signal(10000, Some(0.0)),
+            signal(11000, Some(0.0)),
+            signal(12000, Some(25.0)),
+            // Interrupted by a missing speed, then runs to the end
+            signal(13000, Some(0.0)),
+            signal(14000, None),
+            signal(15000, Some(0.0)),
+            signal(25000, Some(0.0)),
+        ];
+        let episodes = detect_stops(&signals, 1.0, 5000);
+
+        assert_eq!(episodes, vec![1..4, 10..12]);
+    }
+
+    #[test]
+    fn test_detect_stops_empty() {
+        assert!(detect_stops(&[], 1.0, 5000).is_empty());
+    }
+}
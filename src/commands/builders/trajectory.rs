@@ -1,23 +1,69 @@
-use crate::cli::Cli;
+use crate::cli::{BuildCommandArgs, Cli};
+use crate::commands::builders::energy::summarize_energy;
 use crate::db::evedb::EveDb;
-use crate::models::trajectory::TrajectoryUpdate;
-use crate::tools::lat_lng_to_h3_12;
-use chrono::{DateTime, Duration, TimeZone};
-use chrono_tz::America::Detroit;
+use crate::models::trajectory::{TrajectoryPoint, TrajectorySplit, TrajectoryUpdate};
+use crate::tools::{TimeBase, lat_lng_to_h3_12};
 use geo::line_measures::LengthMeasurable;
-use geo::{Haversine, LineString};
+use geo::{Distance, Haversine, LineString, Point};
 use indicatif::ProgressIterator;
+use std::ops::Range;
 
-fn get_date_time(
-    base_dt: DateTime<chrono_tz::Tz>,
-    day_num: i64,
-    time_stamp: i64,
-) -> DateTime<chrono_tz::Tz> {
-    base_dt + Duration::days(day_num) + Duration::milliseconds(time_stamp)
+/// Splits the points wherever consecutive points are further apart than the time gap or
+/// distance jump thresholds.
+fn split_points(
+    points: &[TrajectoryPoint],
+    max_gap_ms: Option<i64>,
+    max_jump_m: Option<f64>,
+) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+
+    for i in 1..points.len() {
+        let (prev, curr) = (&points[i - 1], &points[i]);
+        let is_gap = max_gap_ms.is_some_and(|gap| curr.time_stamp - prev.time_stamp > gap);
+        let is_jump = max_jump_m.is_some_and(|jump| {
+            Haversine.distance(
+                Point::new(prev.longitude, prev.latitude),
+                Point::new(curr.longitude, curr.latitude),
+            ) > jump
+        });
+        if is_gap || is_jump {
+            ranges.push(start..i);
+            start = i;
+        }
+    }
+    if start < points.len() {
+        ranges.push(start..points.len());
+    }
+    ranges
+}
+
+fn get_trajectory_splits(
+    db: &EveDb,
+    max_gap_ms: Option<i64>,
+    max_jump_m: Option<f64>,
+) -> Vec<TrajectorySplit> {
+    let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
+    let mut splits: Vec<TrajectorySplit> = Vec::new();
+
+    for trajectory_id in trajectory_ids.iter().progress() {
+        let points = db.get_trajectory_points(*trajectory_id).unwrap_or(vec![]);
+        let ranges = split_points(&points, max_gap_ms, max_jump_m);
+
+        if ranges.len() > 1 {
+            splits.push(TrajectorySplit {
+                trajectory_id: *trajectory_id,
+                bounds: ranges
+                    .into_iter()
+                    .map(|r| (points[r.start].time_stamp, points[r.end - 1].time_stamp))
+                    .collect(),
+            });
+        }
+    }
+    splits
 }
 
-fn get_trajectory_updates(db: &EveDb) -> Vec<TrajectoryUpdate> {
-    let base_dt: DateTime<chrono_tz::Tz> = Detroit.with_ymd_and_hms(2017, 11, 1, 0, 0, 0).unwrap();
+fn get_trajectory_updates(db: &EveDb, time_base: &TimeBase) -> Vec<TrajectoryUpdate> {
     let trajectory_ids = db.get_trajectory_ids().unwrap_or(vec![]);
     let mut updates: Vec<TrajectoryUpdate> = Vec::with_capacity(trajectory_ids.len());
 
@@ -35,10 +81,11 @@ fn get_trajectory_updates(db: &EveDb) -> Vec<TrajectoryUpdate> {
                 .collect::<Vec<_>>(),
         );
         let length_m = line_string.length(&Haversine); // Haversine.length(&line_string);
-        let day_num = (trajectory_points[0].day_num as i64) - 1;
+        let day_num = trajectory_points[0].day_num;
         let last = trajectory_points.len() - 1;
-        let dt_ini = get_date_time(base_dt, day_num, trajectory_points[0].time_stamp);
-        let dt_end = get_date_time(base_dt, day_num, trajectory_points[last].time_stamp);
+        let dt_ini = time_base.date_time(day_num, trajectory_points[0].time_stamp);
+        let dt_end = time_base.date_time(day_num, trajectory_points[last].time_stamp);
+        let energy_signals = db.get_energy_signals(*trajectory_id).unwrap_or(vec![]);
         let h3_ini = lat_lng_to_h3_12(
             trajectory_points[0].latitude,
             trajectory_points[0].longitude,
@@ -58,13 +105,21 @@ fn get_trajectory_updates(db: &EveDb) -> Vec<TrajectoryUpdate> {
             h3_12_ini: h3_ini,
             h3_12_end: h3_end,
             trajectory_id: *trajectory_id,
+            energy: summarize_energy(&energy_signals, length_m),
         };
         updates.push(update);
     }
     updates
 }
 
-pub fn build_trajectories(cli: &Cli) {
+pub fn build_trajectories(cli: &Cli, args: &BuildCommandArgs) {
+    let time_base = match cli.time_base() {
+        Ok(time_base) => time_base,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
     let db: EveDb = EveDb::new(&cli.db_path);
 
     if cli.verbose {
@@ -92,7 +147,23 @@ pub fn build_trajectories(cli: &Cli) {
         }
     }
 
-    let updates = get_trajectory_updates(&db);
+    if args.split_gap.is_some() || args.split_jump.is_some() {
+        if cli.verbose {
+            println!("Splitting trajectories on gaps")
+        }
+
+        let max_gap_ms = args.split_gap.map(|gap| (gap * 1000.0) as i64);
+        let splits = get_trajectory_splits(&db, max_gap_ms, args.split_jump);
+        if cli.verbose {
+            println!("Splitting {} trajectory records", splits.len())
+        }
+
+        if let Err(e) = db.split_trajectories(&splits) {
+            eprintln!("Failed to split trajectory records: {}", e);
+        }
+    }
+
+    let updates = get_trajectory_updates(&db, &time_base);
     if cli.verbose {
         println!("Updating {} trajectory records", updates.len())
     }
@@ -121,9 +192,93 @@ mod tests {
         let db = EveDb::new(db_path);
         db.create_trajectory_table().unwrap();
 
-        let updates = get_trajectory_updates(&db);
+        let updates = get_trajectory_updates(&db, &TimeBase::default());
         assert_eq!(updates.len(), 0);
 
         fs::remove_file(db_path).unwrap();
     }
+
+    fn point(time_stamp: i64, latitude: f64) -> TrajectoryPoint {
+        TrajectoryPoint {
+            signal_id: time_stamp,
+            vehicle_id: 1,
+            day_num: 1.0,
+            time_stamp,
+            latitude,
+            longitude: -83.0,
+        }
+    }
+
+    #[test]
+    fn test_split_points() {
+        let points = vec![
+            point(0, 42.0000),
+            point(1000, 42.0001),
+            point(600000, 42.0002), // 10 min gap
+            point(601000, 42.0003),
+            point(602000, 42.1000), // ~11 km jump
+            point(603000, 42.1001),
+        ];
+
+        assert_eq!(split_points(&points, None, None), vec![0..6]);
+        assert_eq!(split_points(&points, Some(300000), None), vec![0..2, 2..6]);
+        assert_eq!(
+            split_points(&points, Some(300000), Some(1000.0)),
+            vec![0..2, 2..4, 4..6]
+        );
+        assert!(split_points(&[], Some(300000), None).is_empty());
+    }
+
+    #[test]
+    fn test_split_trajectories() {
+        let db_path = "test_split.db";
+        if std::path::Path::new(db_path).exists() {
+            fs::remove_file(db_path).unwrap();
+        }
+        let db = EveDb::new(db_path);
+        db.create_signal_table().unwrap();
+        db.create_trajectory_table().unwrap();
+
+        let conn = db.connect().unwrap();
+        for (time_stamp, latitude) in [(0, 42.0), (1000, 42.0001), (600000, 42.0002)] {
+            conn.execute(
+                "INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 10, 100, ?1, ?2, -83.0, ?2, -83.0, 1)",
+                rusqlite::params!(time_stamp, latitude),
+            )
+            .unwrap();
+        }
+        db.insert_trajectories().unwrap();
+
+        let splits = get_trajectory_splits(&db, Some(300000), None);
+        assert_eq!(splits.len(), 1);
+        db.split_trajectories(&splits).unwrap();
+
+        let ids = db.get_trajectory_ids().unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(db.get_trajectory_points(ids[0]).unwrap().len(), 2);
+        assert_eq!(db.get_trajectory_points(ids[1]).unwrap().len(), 1);
+
+        let segments: Vec<i64> = conn
+            .prepare("SELECT segment FROM trajectory WHERE trip_id = 100 ORDER BY segment")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(segments, vec![0, 1]);
+
+        let parents: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM trajectory WHERE parent_trip = 100",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(parents, 2);
+
+        // A segment can only be stored once per trip
+        assert!(db.split_trajectories(&splits).is_err());
+
+        fs::remove_file(db_path).unwrap();
+    }
 }
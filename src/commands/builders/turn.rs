@@ -0,0 +1,168 @@
+use crate::cli::{Cli, TurnsCommandArgs};
+use crate::db::evedb::EveDb;
+use crate::models::edge::NetworkEdge;
+use crate::models::turn::{Turn, TurnType};
+use std::collections::{BTreeMap, HashMap};
+
+/// Heading change from one bearing to the next in (-180, 180], positive clockwise
+fn turn_angle(from_bearing: f64, to_bearing: f64) -> f64 {
+    let angle = (to_bearing - from_bearing).rem_euclid(360.0);
+    if angle > 180.0 { angle - 360.0 } else { angle }
+}
+
+fn classify(angle_deg: f64, args: &TurnsCommandArgs) -> TurnType {
+    match angle_deg.abs() {
+        a if a <= args.straight_angle => TurnType::Straight,
+        a if a >= args.u_turn_angle => TurnType::UTurn,
+        _ if angle_deg > 0.0 => TurnType::Right,
+        _ => TurnType::Left,
+    }
+}
+
+/// Counts the movements between consecutive edges of each trajectory, given as
+/// `(traj_id, edge_id)` pairs in traversal order. Pairs of edges that do not share a
+/// node, and self-loops, whose bearing is meaningless, break the sequence.
+fn count_turns(
+    sequences: &[(i64, i64)],
+    edges: &[NetworkEdge],
+    args: &TurnsCommandArgs,
+) -> Vec<Turn> {
+    let by_id: HashMap<i64, &NetworkEdge> = edges.iter().map(|e| (e.id, e)).collect();
+    let mut counts: BTreeMap<(i64, i64), i64> = BTreeMap::new();
+
+    for pair in sequences.windows(2) {
+        let ((traj_a, edge_a), (traj_b, edge_b)) = (pair[0], pair[1]);
+        if traj_a != traj_b || edge_a == edge_b {
+            continue;
+        }
+        let (Some(from), Some(to)) = (by_id.get(&edge_a), by_id.get(&edge_b)) else {
+            continue;
+        };
+        let is_loop = |e: &NetworkEdge| e.node_ini == e.node_end;
+        if from.node_end != to.node_ini || is_loop(from) || is_loop(to) {
+            continue;
+        }
+        *counts.entry((edge_a, edge_b)).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|((from_edge, to_edge), turn_count)| {
+            let (from, to) = (by_id[&from_edge], by_id[&to_edge]);
+            let angle_deg = turn_angle(from.bearing_deg, to.bearing_deg);
+            Turn {
+                from_edge,
+                via_node: from.node_end,
+                to_edge,
+                angle_deg,
+                turn_type: classify(angle_deg, args),
+                turn_count,
+            }
+        })
+        .collect()
+}
+
+pub fn build_turns(cli: &Cli, args: &TurnsCommandArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    if cli.verbose {
+        println!("Creating the turn table")
+    }
+
+    if let Err(e) = db.create_turn_table() {
+        eprintln!("Failed to create turn table: {}", e);
+        return false;
+    }
+
+    let (sequences, edges) = match (db.get_edge_sequences(), db.get_network_edges()) {
+        (Ok(sequences), Ok(edges)) => (sequences, edges),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to get the matched edges: {}", e);
+            return false;
+        }
+    };
+
+    let turns = count_turns(&sequences, &edges, args);
+    if cli.verbose {
+        println!("Inserting {} turns", turns.len())
+    }
+
+    if let Err(e) = db.insert_turns(&turns) {
+        eprintln!("Failed to insert turns: {}", e);
+        return false;
+    }
+
+    if let Err(e) = db.create_turn_indexes() {
+        eprintln!("Failed to create turn indexes: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> TurnsCommandArgs {
+        TurnsCommandArgs {
+            straight_angle: 30.0,
+            u_turn_angle: 150.0,
+        }
+    }
+
+    fn edge(id: i64, node_ini: i64, node_end: i64, bearing_deg: f64) -> NetworkEdge {
+        NetworkEdge {
+            id,
+            node_ini,
+            node_end,
+            length_m: 100.0,
+            bearing_deg,
+            traversals: 1,
+            mean_speed_kmh: None,
+        }
+    }
+
+    #[test]
+    fn test_turn_angle_and_classify() {
+        assert_eq!(turn_angle(350.0, 10.0), 20.0);
+        assert_eq!(turn_angle(10.0, 350.0), -20.0);
+        assert_eq!(turn_angle(0.0, 90.0), 90.0);
+        assert_eq!(turn_angle(0.0, -90.0), -90.0);
+        assert_eq!(turn_angle(90.0, 270.0), 180.0);
+
+        assert_eq!(classify(20.0, &args()), TurnType::Straight);
+        assert_eq!(classify(90.0, &args()), TurnType::Right);
+        assert_eq!(classify(-90.0, &args()), TurnType::Left);
+        assert_eq!(classify(-170.0, &args()), TurnType::UTurn);
+    }
+
+    #[test]
+    fn test_count_turns() {
+        // North on 1, then right onto 2 or straight onto 3; 4 leaves from another node
+        let edges = vec![
+            edge(1, 10, 11, 0.0),
+            edge(2, 11, 12, 90.0),
+            edge(3, 11, 13, 5.0),
+            edge(4, 14, 15, 180.0),
+        ];
+        let sequences = vec![
+            (100, 1),
+            (100, 2),
+            (101, 1),
+            (101, 2),
+            (102, 1),
+            (102, 3),
+            (102, 4),
+            (103, 3),
+        ];
+        let turns = count_turns(&sequences, &edges, &args());
+
+        assert_eq!(turns.len(), 2);
+        assert_eq!((turns[0].from_edge, turns[0].to_edge), (1, 2));
+        assert_eq!(turns[0].via_node, 11);
+        assert_eq!(turns[0].turn_type, TurnType::Right);
+        assert_eq!(turns[0].turn_count, 2);
+        assert_eq!(turns[1].turn_type, TurnType::Straight);
+        assert_eq!(turns[1].turn_count, 1);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::cli::{Cli, ConfigCommandArgs, ConfigGetArgs, ConfigSetArgs, Configs};
+use crate::models::config::{PROJECT_FILE, config_paths, load_values, set_value};
+use std::path::Path;
+use toml::Value;
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn get_value(cli: &Cli, args: &ConfigGetArgs) -> bool {
+    let values = match load_values(Path::new(PROJECT_FILE), cli.profile.as_deref(), env) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("Failed to load the config: {:#}", e);
+            return false;
+        }
+    };
+    match values.get(&args.key) {
+        Some(config_value) => {
+            println!("{}", display(&config_value.value));
+            true
+        }
+        None => {
+            eprintln!("Unknown config key `{}`", args.key);
+            false
+        }
+    }
+}
+
+fn list_values(cli: &Cli) -> bool {
+    let values = match load_values(Path::new(PROJECT_FILE), cli.profile.as_deref(), env) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("Failed to load the config: {:#}", e);
+            return false;
+        }
+    };
+    for (key, config_value) in &values {
+        println!(
+            "{} = {}  # {}",
+            key, config_value.value, config_value.source
+        );
+    }
+    true
+}
+
+fn write_value(cli: &Cli, args: &ConfigSetArgs) -> bool {
+    let paths = config_paths(args.scope, Path::new(PROJECT_FILE), &env);
+    let Some(path) = paths.into_iter().next() else {
+        eprintln!("No user config directory: set XDG_CONFIG_HOME or HOME");
+        return false;
+    };
+    match set_value(&path, cli.profile.as_deref(), &args.key, &args.value) {
+        Ok(value) => {
+            println!("Set {} = {} in {}", args.key, value, path.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to set `{}`: {:#}", args.key, e);
+            false
+        }
+    }
+}
+
+pub fn run_config(cli: &Cli, args: &ConfigCommandArgs) -> bool {
+    match &args.config {
+        Configs::Get(get_args) => get_value(cli, get_args),
+        Configs::Set(set_args) => write_value(cli, set_args),
+        Configs::List => list_values(cli),
+    }
+}
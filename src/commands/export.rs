@@ -0,0 +1,8 @@
+use crate::cli::{Cli, ExportCommandArgs, Exports};
+use crate::commands::builders::graph::export_network_graph;
+
+pub fn run_export(cli: &Cli, args: &ExportCommandArgs) -> bool {
+    match &args.export {
+        Exports::Graph(graph_args) => export_network_graph(cli, graph_args),
+    }
+}
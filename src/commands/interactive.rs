@@ -1,14 +1,16 @@
-use crate::cli::{BuildCommandArgs, Cli};
+use crate::cli::{BuildCommandArgs, Cli, MatchCommandArgs, ValhallaCosting};
 use crate::commands::build::build_database;
 use crate::commands::builders::node::build_nodes;
 use crate::commands::clean::clean_data;
 use crate::commands::clone::clone_data;
 use crate::commands::status::display_status;
-use inquire::{Select, Text, error::InquireError};
+use crate::models::config::{PROJECT_FILE, load_values, save_changes};
+use inquire::{CustomType, Select, Text, error::InquireError};
+use std::path::Path;
 
 fn get_config_menu_option() -> String {
     loop {
-        let options: Vec<&str> = vec!["database", "repository", "exit"];
+        let options: Vec<&str> = vec!["database", "repository", "valhalla", "save", "exit"];
         let ans: Result<&str, InquireError> =
             Select::new("Please select an option:", options).prompt();
 
@@ -44,12 +46,112 @@ fn config_menu(cli: &mut Cli) {
                     cli.repo_path = repository;
                 }
             }
+            "valhalla" => {
+                valhalla_menu(cli);
+            }
+            "save" => {
+                save_config(cli);
+            }
             "exit" => {}
             _ => {}
         }
     }
 }
 
+/// Writes the settings changed in this session to the project config file
+fn save_config(cli: &Cli) {
+    let profile = cli.profile.as_deref();
+    let path = Path::new(PROJECT_FILE);
+    let saved = load_values(path, profile, |name| std::env::var(name).ok())
+        .and_then(|values| save_changes(path, profile, &cli.get_config(), &values));
+    match saved {
+        Ok(keys) if keys.is_empty() => println!("Nothing to save"),
+        Ok(keys) => println!("Saved {} to {}", keys.join(", "), PROJECT_FILE),
+        Err(e) => eprintln!("Failed to save the config: {:#}", e),
+    }
+    println!();
+}
+
+fn get_valhalla_menu_option() -> String {
+    loop {
+        let options: Vec<&str> = vec![
+            "url",
+            "timeout",
+            "costing",
+            "search radius",
+            "gps accuracy",
+            "back",
+        ];
+        let ans: Result<&str, InquireError> =
+            Select::new("Please select a Valhalla option:", options).prompt();
+
+        if let Ok(option) = ans {
+            return option.to_string();
+        }
+    }
+}
+
+fn valhalla_menu(cli: &mut Cli) {
+    let mut option: String = "".to_string();
+
+    while option != "back" {
+        let valhalla = &cli.valhalla;
+        println!("url          : {}", valhalla.url);
+        println!("timeout      : {} s", valhalla.timeout_s);
+        println!("costing      : {:?}", valhalla.costing);
+        println!("search radius: {} m", valhalla.search_radius_m);
+        println!("gps accuracy : {} m", valhalla.gps_accuracy_m);
+        println!();
+
+        option = get_valhalla_menu_option();
+        let valhalla = &mut cli.valhalla;
+        match option.as_str() {
+            "url" => {
+                let url = Text::new("Valhalla URL (several separated by commas)")
+                    .with_initial_value(&valhalla.url)
+                    .prompt();
+                if let Ok(url) = url {
+                    valhalla.url = url;
+                }
+            }
+            "timeout" => {
+                let timeout = CustomType::<u64>::new("Request timeout (s)")
+                    .with_default(valhalla.timeout_s)
+                    .prompt();
+                if let Ok(timeout) = timeout {
+                    valhalla.timeout_s = timeout;
+                }
+            }
+            "costing" => {
+                let costing = Select::new("Costing model", vec!["auto", "truck"]).prompt();
+                match costing {
+                    Ok("auto") => valhalla.costing = ValhallaCosting::Auto,
+                    Ok("truck") => valhalla.costing = ValhallaCosting::Truck,
+                    _ => {}
+                }
+            }
+            "search radius" => {
+                let radius = CustomType::<f64>::new("Search radius (m)")
+                    .with_default(valhalla.search_radius_m)
+                    .prompt();
+                if let Ok(radius) = radius {
+                    valhalla.search_radius_m = radius;
+                }
+            }
+            "gps accuracy" => {
+                let accuracy = CustomType::<f64>::new("GPS accuracy (m)")
+                    .with_default(valhalla.gps_accuracy_m)
+                    .prompt();
+                if let Ok(accuracy) = accuracy {
+                    valhalla.gps_accuracy_m = accuracy;
+                }
+            }
+            "back" => {}
+            _ => {}
+        }
+    }
+}
+
 fn get_menu_option() -> String {
     loop {
         let options: Vec<&str> = vec![
@@ -85,11 +187,14 @@ pub async fn interactive(cli: &mut Cli) {
                 let args = BuildCommandArgs {
                     no_clone: true,
                     no_clean: true,
+                    split_gap: None,
+                    split_jump: None,
+                    local_time: false,
                 };
                 build_database(cli, &args).await;
             }
             "match" => {
-                build_nodes(cli).await;
+                build_nodes(cli, &MatchCommandArgs::default()).await;
             }
             "status" => {
                 display_status(cli);
@@ -1,6 +1,13 @@
+pub mod aggregate;
 pub mod build;
 pub mod builders;
 pub mod clean;
 pub mod clone;
+pub mod config;
+pub mod export;
 pub mod interactive;
+pub mod network;
+pub mod route;
+pub mod stage;
 mod status;
+pub mod validate;
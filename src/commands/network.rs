@@ -0,0 +1,11 @@
+use crate::cli::{Cli, NetworkCommandArgs, Networks};
+use crate::commands::builders::network::check_network;
+use crate::commands::builders::osm::{import_osm, network_coverage};
+
+pub fn run_network(cli: &Cli, args: &NetworkCommandArgs) -> bool {
+    match &args.network {
+        Networks::Check(check_args) => check_network(cli, check_args),
+        Networks::ImportOsm(import_args) => import_osm(cli, import_args),
+        Networks::Coverage => network_coverage(cli),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cli::{Cli, RouteCommandArgs};
+use crate::commands::builders::node::find_nearest_node;
+use crate::db::evedb::EveDb;
+use crate::export::route::export_route_geojson;
+use crate::models::node::Node;
+use crate::routing::RoadGraph;
+
+fn snap(db: &EveDb, (lat, lon): (f64, f64), k: u32) -> Option<Node> {
+    match find_nearest_node(db, lat, lon, k) {
+        Some((node, _)) => Some(node),
+        None => {
+            eprintln!("No node within {} H3 rings of {},{}", k, lat, lon);
+            None
+        }
+    }
+}
+
+pub fn run_route(cli: &Cli, args: &RouteCommandArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let (Some(from), Some(to)) = (
+        snap(&db, args.from, args.snap_ring),
+        snap(&db, args.to, args.snap_ring),
+    ) else {
+        return false;
+    };
+
+    let graph = match (db.get_nodes(), db.get_network_edges()) {
+        (Ok(nodes), Ok(edges)) => RoadGraph::new(nodes, &edges),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to load the road network: {}", e);
+            return false;
+        }
+    };
+
+    if cli.verbose {
+        println!("Routing from node {} to node {}", from.id, to.id)
+    }
+
+    let Some(route) = graph.shortest_path(from.id, to.id, args.weight, args.algorithm) else {
+        eprintln!("No path from node {} to node {}", from.id, to.id);
+        return false;
+    };
+
+    println!("node_id,latitude,longitude");
+    for node in &route.nodes {
+        println!("{},{},{}", node.id, node.latitude, node.longitude);
+    }
+    println!(
+        "{} nodes, {:.1} m, {:.1} s",
+        route.nodes.len(),
+        route.length_m,
+        route.time_s
+    );
+
+    if let Some(path) = &args.geojson
+        && let Err(e) = export_route_geojson(path, &route)
+    {
+        eprintln!("Failed to export route: {}", e);
+        return false;
+    }
+    true
+}
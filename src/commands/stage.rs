@@ -0,0 +1,20 @@
+use crate::cli::{Cli, StageCommandArgs, Stages};
+use crate::commands::builders::charge::build_charges;
+use crate::commands::builders::event::build_events;
+use crate::commands::builders::kinematics::build_kinematics;
+use crate::commands::builders::od::build_od;
+use crate::commands::builders::quality::build_match_quality;
+use crate::commands::builders::stop::build_stops;
+use crate::commands::builders::turn::build_turns;
+
+pub fn run_stage(cli: &Cli, args: &StageCommandArgs) -> bool {
+    match &args.stage {
+        Stages::Od(od_args) => build_od(cli, od_args),
+        Stages::Stops(stops_args) => build_stops(cli, stops_args),
+        Stages::Kinematics => build_kinematics(cli),
+        Stages::Charge(charge_args) => build_charges(cli, charge_args),
+        Stages::Events(events_args) => build_events(cli, events_args),
+        Stages::Turns(turns_args) => build_turns(cli, turns_args),
+        Stages::MatchQuality => build_match_quality(cli),
+    }
+}
@@ -0,0 +1,25 @@
+use crate::cli::{Cli, ValidateCommandArgs};
+use crate::db::evedb::EveDb;
+
+pub fn validate(cli: &Cli, args: &ValidateCommandArgs) -> bool {
+    let db: EveDb = EveDb::new(&cli.db_path);
+
+    let outliers = match db.flag_match_outliers(
+        args.max_mean_distance,
+        args.max_beyond_share,
+        args.min_length_ratio,
+        args.max_length_ratio,
+    ) {
+        Ok(outliers) => outliers,
+        Err(e) => {
+            eprintln!("Failed to flag match outliers (run `stage match-quality` first): {}", e);
+            return false;
+        }
+    };
+
+    println!("Map-match outliers: {}", outliers.len());
+    if cli.verbose {
+        println!("Outlier trajectories: {:?}", outliers);
+    }
+    true
+}
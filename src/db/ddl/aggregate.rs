@@ -0,0 +1,28 @@
+use crate::db::evedb::EveDb;
+use crate::models::aggregate::H3Metric;
+use anyhow::anyhow;
+
+pub fn h3_table_name(resolution: u8) -> String {
+    format!("h3_agg_r{}", resolution)
+}
+
+pub fn create_h3_table(db: &EveDb, resolution: u8, metrics: &[H3Metric]) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    let table = h3_table_name(resolution);
+
+    conn.execute(&format!("DROP TABLE IF EXISTS {};", table), ())?;
+
+    let columns: String = metrics
+        .iter()
+        .map(|m| match m {
+            H3Metric::Count | H3Metric::Vehicles => format!(",\n    {} INTEGER", m.column_name()),
+            _ => format!(",\n    {} DOUBLE", m.column_name()),
+        })
+        .collect();
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (\n    h3 INTEGER PRIMARY KEY{}\n);",
+        table, columns
+    );
+    conn.execute(&sql, ())
+        .map_err(|e| anyhow!("Failed to create {} table: {:?}", table, e))
+}
@@ -0,0 +1,21 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS charge_event;", ())?;
+
+    let sql = include_str!("sql/create_table_charge_event.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create charge_event table: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS charge_event_vehicle_day_idx ON charge_event (vehicle_id, day);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create charge_event indexes: {:?}", e))
+}
@@ -0,0 +1,47 @@
+use crate::db::evedb::EveDb;
+use crate::models::event::EventKind;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb, kind: EventKind) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    let table = kind.table_name();
+
+    conn.execute(&format!("DROP TABLE IF EXISTS {};", table), ())?;
+
+    let sql = format!(
+        "
+            CREATE TABLE IF NOT EXISTS {} (
+                event_id        INTEGER PRIMARY KEY,
+                traj_id         INTEGER NOT NULL,
+                latitude        DOUBLE NOT NULL,
+                longitude       DOUBLE NOT NULL,
+                h3_12           INTEGER NOT NULL,
+                time_stamp_ini  INTEGER NOT NULL,
+                time_stamp_end  INTEGER NOT NULL,
+                dt_ini          TEXT,
+                dt_end          TEXT,
+                duration_s      DOUBLE NOT NULL,
+                approach_speed  DOUBLE,
+                min_speed       DOUBLE,
+                stopped         INTEGER NOT NULL,
+                FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id)
+            )",
+        table
+    );
+    conn.execute(&sql, ())
+        .map_err(|e| anyhow!("Failed to create {} table: {:?}", table, e))
+}
+
+pub fn create_indexes(db: &EveDb, kind: EventKind) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    let table = kind.table_name();
+    conn.execute(
+        &format!("CREATE INDEX IF NOT EXISTS {0}_traj_idx ON {0} (traj_id);", table),
+        (),
+    )?;
+    conn.execute(
+        &format!("CREATE INDEX IF NOT EXISTS {0}_h3_idx ON {0} (h3_12);", table),
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create {} indexes: {:?}", table, e))
+}
@@ -0,0 +1,21 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS signal_kinematics;", ())?;
+
+    let sql = include_str!("sql/create_table_signal_kinematics.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create signal_kinematics table: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS signal_kinematics_traj_idx ON signal_kinematics (traj_id);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create signal_kinematics indexes: {:?}", e))
+}
@@ -0,0 +1,36 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_metadata_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS match_metadata;", ())?;
+
+    let sql = include_str!("sql/create_table_match_metadata.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create match_metadata table: {:?}", e))
+}
+
+pub fn create_filter_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS match_filter;", ())?;
+
+    let sql = include_str!("sql/create_table_match_filter.sql");
+    conn.execute(sql, ())?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS match_filter_traj_idx ON match_filter (traj_id);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create match_filter table: {:?}", e))
+}
+
+pub fn create_quality_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS match_quality;", ())?;
+
+    let sql = include_str!("sql/create_table_match_quality.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create match_quality table: {:?}", e))
+}
@@ -1,5 +1,14 @@
+pub mod aggregate;
+pub mod charge;
 pub mod edge;
+pub mod event;
+pub mod kinematics;
+pub mod matching;
 pub mod node;
+pub mod od;
+pub mod osm;
 pub mod signal;
+pub mod stop;
 pub mod trajectory;
+pub mod turn;
 pub mod vehicle;
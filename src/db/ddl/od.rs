@@ -0,0 +1,21 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS od_matrix;", ())?;
+
+    let sql = include_str!("sql/create_table_od_matrix.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create od_matrix table: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS od_matrix_cells_idx ON od_matrix (h3_ini, h3_end);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create od_matrix indexes: {:?}", e))
+}
@@ -0,0 +1,39 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_tables(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS edge_osm;", ())?;
+    conn.execute("DROP TABLE IF EXISTS osm_way_node;", ())?;
+    conn.execute("DROP TABLE IF EXISTS osm_way;", ())?;
+    conn.execute("DROP TABLE IF EXISTS osm_node;", ())?;
+
+    conn.execute(include_str!("sql/create_table_osm_node.sql"), ())?;
+    conn.execute(include_str!("sql/create_table_osm_way.sql"), ())?;
+    conn.execute(include_str!("sql/create_table_osm_way_node.sql"), ())
+        .map_err(|e| anyhow!("Failed to create OSM tables: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS osm_node_h3_12_idx ON osm_node (h3_12);",
+        (),
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS osm_way_node_node_idx ON osm_way_node (osm_node_id);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create OSM indexes: {:?}", e))
+}
+
+pub fn create_edge_osm_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS edge_osm;", ())?;
+
+    let sql = include_str!("sql/create_table_edge_osm.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create edge_osm table: {:?}", e))
+}
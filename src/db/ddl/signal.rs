@@ -44,11 +44,30 @@ pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
     "   intersection       INTEGER,"
     "   bus_stop           INTEGER,"
     "   focus_points       TEXT,"
-    "   h3_12              INTEGER);"};
+    "   h3_12              INTEGER,"
+    "   ts_utc             TEXT,"
+    "   ts_local           TEXT,"
+    "   speed_limit_kmh    DOUBLE,"
+    "   speed_limit_dir    TEXT,"
+    "   speed_limit_bwd_kmh DOUBLE);"};
     conn.execute(sql, ())
         .map_err(|e| anyhow!("Failed to create signal table: {:?}", e))
 }
 
+pub fn create_focus_point_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS focus_point;", ())?;
+    let sql = text_block! {
+    "CREATE TABLE focus_point ("
+    "   signal_id          INTEGER NOT NULL,"
+    "   point_type         TEXT    NOT NULL,"
+    "   PRIMARY KEY (signal_id, point_type),"
+    "   FOREIGN KEY (signal_id) REFERENCES signal(signal_id));"};
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create focus_point table: {:?}", e))
+}
+
 pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
     let conn = db.connect()?;
     conn.execute(
@@ -60,6 +79,10 @@ pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
         );",
         (),
     )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS focus_point_type_idx ON focus_point (point_type);",
+        (),
+    )?;
     conn.execute(
         "CREATE INDEX IF NOT EXISTS signal_h3_idx ON signal (h3_12);",
         (),
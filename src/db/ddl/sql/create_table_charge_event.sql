@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS charge_event (
+    charge_id       INTEGER PRIMARY KEY,
+    vehicle_id      INTEGER NOT NULL,
+    day             TEXT NOT NULL,
+    traj_id_before  INTEGER NOT NULL,
+    traj_id_after   INTEGER NOT NULL,
+    dt_ini          TEXT NOT NULL,
+    dt_end          TEXT NOT NULL,
+    gap_s           DOUBLE NOT NULL,
+    soc_ini         DOUBLE NOT NULL,
+    soc_end         DOUBLE NOT NULL,
+    soc_gain        DOUBLE NOT NULL,
+    FOREIGN KEY (vehicle_id) REFERENCES vehicle(vehicle_id),
+    FOREIGN KEY (traj_id_before) REFERENCES trajectory(traj_id),
+    FOREIGN KEY (traj_id_after) REFERENCES trajectory(traj_id)
+);
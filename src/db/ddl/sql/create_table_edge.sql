@@ -3,4 +3,5 @@ CREATE TABLE IF NOT EXISTS edge (
     node_ini        INTEGER,
     node_end        INTEGER,
     length_m        DOUBLE,
-    bearing_deg     DOUBLE;
+    bearing_deg     DOUBLE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS edge_osm (
+    edge_id         INTEGER PRIMARY KEY,
+    osm_way_id      INTEGER NOT NULL,
+    FOREIGN KEY (edge_id) REFERENCES edge(edge_id),
+    FOREIGN KEY (osm_way_id) REFERENCES osm_way(osm_way_id)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS match_filter (
+    match_filter_id INTEGER PRIMARY KEY,
+    traj_id         INTEGER NOT NULL,
+    filter          TEXT NOT NULL,
+    removed         INTEGER NOT NULL,
+    FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id)
+);
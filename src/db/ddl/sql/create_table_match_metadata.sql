@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS match_metadata (
+    traj_id         INTEGER PRIMARY KEY,
+    point_count     INTEGER NOT NULL,
+    filtered_count  INTEGER NOT NULL,
+    filter_settings TEXT,
+    search_radius_m DOUBLE,
+    FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id)
+);
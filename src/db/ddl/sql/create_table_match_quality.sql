@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS match_quality (
+    traj_id                 INTEGER PRIMARY KEY,
+    point_count             INTEGER NOT NULL,
+    mean_distance_m         DOUBLE NOT NULL,
+    max_distance_m          DOUBLE NOT NULL,
+    matched_length_m        DOUBLE NOT NULL,
+    length_ratio            DOUBLE,
+    beyond_radius_share     DOUBLE NOT NULL,
+    eved_mean_distance_m    DOUBLE,
+    eved_max_distance_m     DOUBLE,
+    outlier                 INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id)
+);
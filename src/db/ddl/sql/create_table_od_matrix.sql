@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS od_matrix (
+    od_id               INTEGER PRIMARY KEY,
+    h3_ini              INTEGER NOT NULL,
+    h3_end              INTEGER NOT NULL,
+    resolution          INTEGER NOT NULL,
+    vehicle_type        TEXT,
+    hour                INTEGER NOT NULL,
+    weekday             INTEGER NOT NULL,
+    trip_count          INTEGER NOT NULL,
+    median_duration_s   DOUBLE,
+    median_length_m     DOUBLE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS osm_node (
+    osm_node_id     INTEGER PRIMARY KEY,
+    latitude        DOUBLE NOT NULL,
+    longitude       DOUBLE NOT NULL,
+    h3_12           INTEGER NOT NULL
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS osm_way (
+    osm_way_id      INTEGER PRIMARY KEY,
+    highway         TEXT NOT NULL,
+    name            TEXT,
+    oneway          INTEGER NOT NULL,
+    max_speed_kmh   DOUBLE,
+    length_m        DOUBLE NOT NULL
+);
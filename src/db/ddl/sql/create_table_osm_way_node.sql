@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS osm_way_node (
+    osm_way_id      INTEGER NOT NULL,
+    seq             INTEGER NOT NULL,
+    osm_node_id     INTEGER NOT NULL,
+    PRIMARY KEY (osm_way_id, seq),
+    FOREIGN KEY (osm_way_id) REFERENCES osm_way(osm_way_id),
+    FOREIGN KEY (osm_node_id) REFERENCES osm_node(osm_node_id)
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS signal_kinematics (
+    signal_id           INTEGER PRIMARY KEY,
+    traj_id             INTEGER NOT NULL,
+    dt_s                DOUBLE,
+    step_m              DOUBLE,
+    distance_m          DOUBLE NOT NULL,
+    speed               DOUBLE,
+    speed_interpolated  INTEGER NOT NULL DEFAULT 0,
+    acceleration        DOUBLE,
+    jerk                DOUBLE,
+    FOREIGN KEY (signal_id) REFERENCES signal(signal_id),
+    FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id)
+);
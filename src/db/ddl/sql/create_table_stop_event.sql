@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS stop_event (
+    stop_id         INTEGER PRIMARY KEY,
+    traj_id         INTEGER NOT NULL,
+    latitude        DOUBLE NOT NULL,
+    longitude       DOUBLE NOT NULL,
+    h3_12           INTEGER NOT NULL,
+    time_stamp_ini  INTEGER NOT NULL,
+    time_stamp_end  INTEGER NOT NULL,
+    dt_ini          TEXT,
+    dt_end          TEXT,
+    duration_s      DOUBLE NOT NULL,
+    node_id         INTEGER,
+    FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id),
+    FOREIGN KEY (node_id) REFERENCES node(node_id)
+);
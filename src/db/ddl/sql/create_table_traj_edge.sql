@@ -2,6 +2,11 @@ CREATE TABLE IF NOT EXISTS traj_edge (
     traj_edge_id   INTEGER PRIMARY KEY,
     traj_id        INTEGER NOT NULL,
     edge_id        INTEGER NOT NULL,
+    seq            INTEGER NOT NULL,
+    shape_index    INTEGER NOT NULL,
+    distance_m     DOUBLE NOT NULL,
+    time_s         DOUBLE,
+    UNIQUE (traj_id, seq),
     FOREIGN KEY (traj_id) REFERENCES trajectory(traj_id),
     FOREIGN KEY (edge_id) REFERENCES edge(edge_id)
 );
@@ -1,11 +1,28 @@
 CREATE TABLE IF NOT EXISTS main.trajectory (
-    traj_id     INTEGER PRIMARY KEY,
-    vehicle_id  INTEGER NOT NULL,
-    trip_id     INTEGER NOT NULL,
-    length_m    DOUBLE,
-    dt_ini      TEXT,
-    dt_end      TEXT,
-    duration_s  DOUBLE,
-    h3_12_ini   INTEGER,
-    h3_12_end   INTEGER
+    traj_id             INTEGER PRIMARY KEY,
+    vehicle_id          INTEGER NOT NULL,
+    trip_id             INTEGER NOT NULL,
+    parent_trip         INTEGER,
+    segment             INTEGER NOT NULL DEFAULT 0,
+    time_stamp_ini      INTEGER,
+    time_stamp_end      INTEGER,
+    length_m            DOUBLE,
+    dt_ini              TEXT,
+    dt_end              TEXT,
+    duration_s          DOUBLE,
+    h3_12_ini           INTEGER,
+    h3_12_end           INTEGER,
+    stop_count          INTEGER,
+    idle_s              DOUBLE,
+    fuel_l              DOUBLE,
+    fuel_l_per_100km    DOUBLE,
+    energy_kwh          DOUBLE,
+    energy_kwh_per_km   DOUBLE,
+    soc_ini             DOUBLE,
+    soc_end             DOUBLE,
+    mean_oat            DOUBLE,
+    ac_energy_kwh       DOUBLE,
+    heater_energy_kwh   DOUBLE,
+    regen_kwh           DOUBLE,
+    UNIQUE (vehicle_id, trip_id, segment)
 );
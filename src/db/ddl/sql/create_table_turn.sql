@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS turn (
+    from_edge       INTEGER NOT NULL,
+    via_node        INTEGER NOT NULL,
+    to_edge         INTEGER NOT NULL,
+    angle_deg       DOUBLE NOT NULL,
+    turn_type       TEXT NOT NULL,
+    turn_count      INTEGER NOT NULL,
+    PRIMARY KEY (from_edge, to_edge),
+    FOREIGN KEY (from_edge) REFERENCES edge(edge_id),
+    FOREIGN KEY (via_node) REFERENCES node(node_id),
+    FOREIGN KEY (to_edge) REFERENCES edge(edge_id)
+);
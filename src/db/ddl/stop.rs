@@ -0,0 +1,25 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS stop_event;", ())?;
+
+    let sql = include_str!("sql/create_table_stop_event.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create stop_event table: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS stop_event_traj_idx ON stop_event (traj_id);",
+        (),
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS stop_event_h3_idx ON stop_event (h3_12);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create stop_event indexes: {:?}", e))
+}
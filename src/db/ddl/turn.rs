@@ -0,0 +1,21 @@
+use crate::db::evedb::EveDb;
+use anyhow::anyhow;
+
+pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+
+    conn.execute("DROP TABLE IF EXISTS turn;", ())?;
+
+    let sql = include_str!("sql/create_table_turn.sql");
+    conn.execute(sql, ())
+        .map_err(|e| anyhow!("Failed to create turn table: {:?}", e))
+}
+
+pub fn create_indexes(db: &EveDb) -> anyhow::Result<usize> {
+    let conn = db.connect()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS turn_via_node_idx ON turn (via_node);",
+        (),
+    )
+    .map_err(|e| anyhow!("Failed to create turn indexes: {:?}", e))
+}
@@ -1,20 +1,57 @@
 use crate::db::evedb::EveDb;
+use crate::models::vehicle::{DriveWheels, Transmission, VehicleCategory, VehicleClass, VehicleType};
 use anyhow::anyhow;
+use rusqlite::{Connection, params};
+
+fn create_lookup_table<C: VehicleCategory>(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(&format!("DROP TABLE IF EXISTS {};", C::TABLE), ())?;
+
+    let sql = format!(
+        "
+            CREATE TABLE IF NOT EXISTS {} (
+                code          TEXT PRIMARY KEY,
+                description   TEXT,
+                known         INTEGER NOT NULL
+            ) STRICT",
+        C::TABLE
+    );
+    conn.execute(&sql, ())
+        .map_err(|e| anyhow!("Failed to create {} table: {:?}", C::TABLE, e))?;
+
+    let sql = format!(
+        "INSERT INTO {} (code, description, known) VALUES (?1, ?2, 1)",
+        C::TABLE
+    );
+    for category in C::known() {
+        conn.execute(&sql, params!(category.code(), category.description()))?;
+    }
+    Ok(())
+}
 
 pub fn create_table(db: &EveDb) -> anyhow::Result<usize> {
     let conn = db.connect()?;
 
     conn.execute("DROP TABLE IF EXISTS vehicle;", ())?;
 
+    create_lookup_table::<VehicleType>(&conn)?;
+    create_lookup_table::<VehicleClass>(&conn)?;
+    create_lookup_table::<Transmission>(&conn)?;
+    create_lookup_table::<DriveWheels>(&conn)?;
+
     let sql = "
             CREATE TABLE IF NOT EXISTS vehicle (
-                vehicle_id    INTEGER PRIMARY KEY,
-                vehicle_type  TEXT,
-                vehicle_class TEXT,
-                engine        TEXT,
-                transmission  TEXT,
-                drive_wheels  TEXT,
-                weight        INTEGER
+                vehicle_id     INTEGER PRIMARY KEY,
+                vehicle_type   TEXT REFERENCES ref_vehicle_type (code),
+                vehicle_class  TEXT REFERENCES ref_vehicle_class (code),
+                engine         TEXT,
+                displacement_l REAL,
+                cylinders      INTEGER,
+                turbo          INTEGER,
+                transmission   TEXT REFERENCES ref_transmission (code),
+                drive_wheels   TEXT REFERENCES ref_drive_wheels (code),
+                weight         INTEGER,
+                weight_kg      REAL,
+                weight_unit    TEXT
             ) STRICT";
     conn.execute(sql, ())
         .map_err(|e| anyhow!("Failed to create vehicle table: {:?}", e))
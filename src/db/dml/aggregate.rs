@@ -0,0 +1,72 @@
+use crate::db::ddl::aggregate::h3_table_name;
+use crate::db::evedb::EveDb;
+use crate::models::aggregate::{H3Aggregate, H3Metric, H3SignalStats};
+use anyhow::anyhow;
+use rusqlite::types::Value;
+use rusqlite::{Error, Row};
+use text_block_macros::text_block;
+
+pub fn get_h3_signal_stats(db: &EveDb) -> anyhow::Result<Vec<H3SignalStats>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     h3_12"
+        ",          vehicle_id"
+        ",          count(*)"
+        ",          coalesce(sum(speed), 0.0)"
+        ",          count(speed)"
+        ",          coalesce(sum(energy_consumption), 0.0)"
+        ",          count(energy_consumption)"
+        ",          coalesce(sum(fuel_rate), 0.0)"
+        ",          count(fuel_rate)"
+        "from       signal"
+        "where      h3_12 is not null"
+        "group by   h3_12, vehicle_id;"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let stats = stmt.query_map([], |row: &Row| {
+        Ok(H3SignalStats {
+            h3_12: row.get::<_, i64>(0)? as u64,
+            vehicle_id: row.get(1)?,
+            point_count: row.get(2)?,
+            speed_sum: row.get(3)?,
+            speed_count: row.get(4)?,
+            energy_sum: row.get(5)?,
+            energy_count: row.get(6)?,
+            fuel_sum: row.get(7)?,
+            fuel_count: row.get(8)?,
+        })
+    })?;
+    let results = stats.collect::<anyhow::Result<Vec<H3SignalStats>, Error>>()?;
+    Ok(results)
+}
+
+pub fn insert_h3_aggregates(
+    db: &EveDb,
+    resolution: u8,
+    metrics: &[H3Metric],
+    cells: &[H3Aggregate],
+) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let table = h3_table_name(resolution);
+
+    let columns: Vec<&str> = metrics.iter().map(|m| m.column_name()).collect();
+    let vars = vec!["?"; columns.len() + 1].join(", ");
+    let sql = format!(
+        "INSERT INTO {} (h3{}) VALUES ({});",
+        table,
+        columns.iter().map(|c| format!(", {}", c)).collect::<String>(),
+        vars
+    );
+
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(&sql)?;
+        for cell in cells {
+            let mut values: Vec<Value> = vec![Value::Integer(cell.h3 as i64)];
+            values.extend(metrics.iter().map(|m| cell.metric_value(*m)));
+            stmt.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert {} cells: {:?}", table, e))
+}
@@ -0,0 +1,64 @@
+use crate::db::evedb::EveDb;
+use crate::models::charge::{ChargeEvent, TripSoc};
+use anyhow::anyhow;
+use rusqlite::{Error, Row, params};
+use text_block_macros::text_block;
+
+pub fn get_trip_socs(db: &EveDb) -> anyhow::Result<Vec<TripSoc>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     t.traj_id"
+        ",          t.vehicle_id"
+        ",          t.dt_ini"
+        ",          t.dt_end"
+        ",          t.soc_ini"
+        ",          t.soc_end"
+        "from       trajectory t"
+        "inner join vehicle v on t.vehicle_id = v.vehicle_id"
+        "where      v.vehicle_type in ('PHEV', 'EV');"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let trips = stmt.query_map([], |row: &Row| {
+        Ok(TripSoc {
+            traj_id: row.get(0)?,
+            vehicle_id: row.get(1)?,
+            dt_ini: row.get(2)?,
+            dt_end: row.get(3)?,
+            soc_ini: row.get(4)?,
+            soc_end: row.get(5)?,
+        })
+    })?;
+    let results = trips.collect::<anyhow::Result<Vec<TripSoc>, Error>>()?;
+    Ok(results)
+}
+
+pub fn insert_charge_events(db: &EveDb, charges: &[ChargeEvent]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO charge_event "
+        "    (vehicle_id, day, traj_id_before, traj_id_after, dt_ini, dt_end, gap_s, "
+        "     soc_ini, soc_end, soc_gain) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for charge in charges {
+            stmt.execute(params!(
+                charge.vehicle_id,
+                charge.day,
+                charge.traj_id_before,
+                charge.traj_id_after,
+                charge.dt_ini,
+                charge.dt_end,
+                charge.gap_s,
+                charge.soc_ini,
+                charge.soc_end,
+                charge.soc_end - charge.soc_ini,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert charge events: {:?}", e))
+}
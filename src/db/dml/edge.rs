@@ -1,21 +1,28 @@
 use anyhow::anyhow;
-use rusqlite::params;
+use rusqlite::{Connection, Error, OptionalExtension, Row, params};
+use std::collections::BTreeSet;
 use text_block_macros::text_block;
 use crate::db::evedb::EveDb;
+use crate::models::edge::NetworkEdge;
 use crate::models::node::Node;
 
 
-fn find_edge(db: &EveDb, node_ini: i64, node_end: i64) -> anyhow::Result<i64> {
-    let conn = db.connect()?;
+/// Looks the edge up through the inserting connection, so that edges added earlier in
+/// the same transaction are found when a trajectory loops over them again
+fn find_edge(conn: &Connection, node_ini: i64, node_end: i64) -> anyhow::Result<Option<i64>> {
     let sql = text_block! {
         "SELECT edge_id FROM edge WHERE node_ini = ?1 AND node_end = ?2;"
     };
-    let edge_id = conn.query_row(sql, params!(node_ini, node_end), |row| row.get(0))?;
+    let edge_id = conn
+        .query_row(sql, params!(node_ini, node_end), |row| row.get(0))
+        .optional()?;
     Ok(edge_id)
 }
 
+/// Inserts the edges between consecutive nodes of a matched path, with the observed
+/// travel time of each movement when known
 pub fn insert_edges(db: &EveDb, traj_id: i64,
-                    nodes: &[Node]) -> anyhow::Result<()> {
+                    nodes: &[Node], times_s: &[Option<f64>]) -> anyhow::Result<()> {
     let mut conn = db.connect()?;
 
     let sql_edge = text_block! {
@@ -27,31 +34,211 @@ pub fn insert_edges(db: &EveDb, traj_id: i64,
         };
     let sql_traj_edge = text_block! {
             "INSERT INTO traj_edge "
-            "    (traj_id, edge_id) "
+            "    (traj_id, edge_id, seq, shape_index, distance_m, time_s) "
             "VALUES "
-            "    (?1, ?2);"
+            "    (?1, ?2, ?3, ?4, ?5, ?6);"
         };
     let tx = conn.transaction()?;
     {
         let mut stmt = tx.prepare(sql_edge)?;
         let mut stmt_traj_edge = tx.prepare(sql_traj_edge)?;
 
-        for nodes in nodes.windows(2) {
-            match find_edge(db, nodes[0].id, nodes[1].id) {
-                Ok(edge_id) => {
-                    stmt_traj_edge.execute(params!(traj_id, edge_id))?;
-                }
-                Err(_) => {
-                    let distance_m = nodes[0].distance_to(&nodes[1]);
+        // Each edge starts at the shape point of its first node
+        let mut start_m = 0.0;
+        for (seq, nodes) in nodes.windows(2).enumerate() {
+            let distance_m = nodes[0].distance_to(&nodes[1]);
+            let edge_id: i64 = match find_edge(&tx, nodes[0].id, nodes[1].id)? {
+                Some(edge_id) => edge_id,
+                None => {
                     let bearing_deg = nodes[0].bearing_to(&nodes[1]);
-                    let edge_id: i64 =  stmt.query_row(
-                            params!(nodes[0].id, nodes[1].id, distance_m, bearing_deg),
-                            |row| row.get(0))?;
-                    stmt_traj_edge.execute(params!(traj_id, edge_id))?;
+                    stmt.query_row(
+                        params!(nodes[0].id, nodes[1].id, distance_m, bearing_deg),
+                        |row| row.get(0),
+                    )?
+                }
+            };
+            let time_s = times_s.get(seq).copied().flatten();
+            stmt_traj_edge.execute(params!(traj_id, edge_id, seq as i64, seq as i64, start_m, time_s))?;
+            start_m += distance_m;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert nodes: {:?}", e))
+}
+
+pub fn get_network_edges(db: &EveDb) -> anyhow::Result<Vec<NetworkEdge>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     e.edge_id"
+        ",          e.node_ini"
+        ",          e.node_end"
+        ",          e.length_m"
+        ",          e.bearing_deg"
+        ",          count(te.traj_edge_id)"
+        ",          sum(case when te.time_s > 0 then e.length_m end)"
+        "           / sum(case when te.time_s > 0 then te.time_s end) * 3.6"
+        "from       edge e"
+        "left join  traj_edge te on e.edge_id = te.edge_id"
+        "group by   e.edge_id"
+        "order by   e.edge_id;"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let edges = stmt.query_map([], |row: &Row| {
+        Ok(NetworkEdge {
+            id: row.get(0)?,
+            node_ini: row.get(1)?,
+            node_end: row.get(2)?,
+            length_m: row.get(3)?,
+            bearing_deg: row.get(4)?,
+            traversals: row.get(5)?,
+            mean_speed_kmh: row.get(6)?,
+        })
+    })?;
+    let results = edges.collect::<anyhow::Result<Vec<NetworkEdge>, Error>>()?;
+    Ok(results)
+}
+
+/// Drops the consecutive repeats of a node from a trajectory's path, left behind when
+/// near nodes were merged, and renumbers `seq` and recomputes `distance_m` of its nodes
+/// and edges. Edge `k` starts at node `k`, as when the path was inserted.
+fn renumber_path(conn: &Connection, traj_id: i64) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare(text_block! {
+        "SELECT   tn.traj_node_id, n.node_id, n.latitude, n.longitude, n.altitude, n.h3_12"
+        "FROM     traj_node tn"
+        "JOIN     node n ON tn.node_id = n.node_id"
+        "WHERE    tn.traj_id = ?1"
+        "ORDER BY tn.seq;"
+    })?;
+    let rows = stmt
+        .query_map(params!(traj_id), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                Node {
+                    id: row.get(1)?,
+                    latitude: row.get(2)?,
+                    longitude: row.get(3)?,
+                    altitude: row.get(4)?,
+                    h3_12: row.get(5)?,
                 },
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let traj_edge_ids = conn
+        .prepare("SELECT traj_edge_id FROM traj_edge WHERE traj_id = ?1 ORDER BY seq;")?
+        .query_map(params!(traj_id), |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Negated first, so that renumbering never collides with a row not yet renumbered
+    conn.execute("UPDATE traj_node SET seq = -1 - seq WHERE traj_id = ?1;", params!(traj_id))?;
+    conn.execute("UPDATE traj_edge SET seq = -1 - seq WHERE traj_id = ?1;", params!(traj_id))?;
+
+    let mut stmt_delete = conn.prepare("DELETE FROM traj_node WHERE traj_node_id = ?1;")?;
+    let mut stmt_node =
+        conn.prepare("UPDATE traj_node SET seq = ?2, distance_m = ?3 WHERE traj_node_id = ?1;")?;
+    let mut stmt_edge =
+        conn.prepare("UPDATE traj_edge SET seq = ?2, distance_m = ?3 WHERE traj_edge_id = ?1;")?;
+    let mut distance_m = 0.0;
+    let mut previous: Option<Node> = None;
+    let mut seq: usize = 0;
+    for (traj_node_id, node) in rows {
+        if let Some(previous) = previous {
+            if previous.id == node.id {
+                stmt_delete.execute(params!(traj_node_id))?;
+                continue;
+            }
+            distance_m += previous.distance_to(&node);
+        }
+        stmt_node.execute(params!(traj_node_id, seq as i64, distance_m))?;
+        if let Some(traj_edge_id) = traj_edge_ids.get(seq) {
+            stmt_edge.execute(params!(traj_edge_id, seq as i64, distance_m))?;
+        }
+        previous = Some(node);
+        seq += 1;
+    }
+    Ok(())
+}
+
+/// Tables derived from the edges, whose rows a repair leaves stale
+const EDGE_DERIVED_TABLES: [&str; 2] = ["turn", "edge_osm"];
+
+fn has_table(conn: &Connection, table: &str) -> anyhow::Result<bool> {
+    let sql = "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1;";
+    Ok(conn.query_row(sql, params!(table), |row| row.get(0))?)
+}
+
+/// Merges each `(node_id, into_node_id)` pair, rewrites the given edges, and then
+/// collapses the duplicate edges and self-loops left behind, in a single transaction.
+/// The paths of the trajectories through merged nodes are renumbered and their stop
+/// events moved to the surviving nodes. The tables derived from the edges are dropped,
+/// and their names returned, so that they are rebuilt rather than left pointing at
+/// edges that no longer exist.
+pub fn repair_network(
+    db: &EveDb,
+    merges: &[(i64, i64)],
+    edges: &[NetworkEdge],
+) -> anyhow::Result<Vec<&'static str>> {
+    let mut conn = db.connect()?;
+    let tx = conn.transaction()?;
+    let mut traj_ids: BTreeSet<i64> = BTreeSet::new();
+    let mut dropped = Vec::new();
+    for table in EDGE_DERIVED_TABLES {
+        if has_table(&tx, table)? {
+            tx.execute(&format!("DROP TABLE {};", table), ())?;
+            dropped.push(table);
+        }
+    }
+    {
+        let mut stmt_stop = match has_table(&tx, "stop_event")? {
+            true => Some(tx.prepare("UPDATE stop_event SET node_id = ?2 WHERE node_id = ?1;")?),
+            false => None,
+        };
+        let mut stmt_traj_ids = tx.prepare("SELECT DISTINCT traj_id FROM traj_node WHERE node_id = ?1;")?;
+        let mut stmt_traj_node = tx.prepare("UPDATE traj_node SET node_id = ?2 WHERE node_id = ?1;")?;
+        let mut stmt_node = tx.prepare("DELETE FROM node WHERE node_id = ?1;")?;
+        for (node_id, into_node_id) in merges {
+            for traj_id in stmt_traj_ids.query_map(params!(node_id), |row| row.get::<_, i64>(0))? {
+                traj_ids.insert(traj_id?);
+            }
+            stmt_traj_node.execute(params!(node_id, into_node_id))?;
+            if let Some(stmt_stop) = &mut stmt_stop {
+                stmt_stop.execute(params!(node_id, into_node_id))?;
             }
+            stmt_node.execute(params!(node_id))?;
+        }
+
+        let mut stmt_edge = tx.prepare(text_block! {
+            "UPDATE edge "
+            "SET    node_ini = ?2, node_end = ?3, length_m = ?4, bearing_deg = ?5 "
+            "WHERE  edge_id = ?1;"
+        })?;
+        for edge in edges {
+            stmt_edge.execute(params!(
+                edge.id,
+                edge.node_ini,
+                edge.node_end,
+                edge.length_m,
+                edge.bearing_deg
+            ))?;
         }
     }
+    tx.execute_batch(text_block! {
+        "UPDATE traj_edge SET edge_id = ("
+        "    SELECT min(d.edge_id) FROM edge e"
+        "    JOIN edge d ON d.node_ini = e.node_ini AND d.node_end = e.node_end"
+        "    WHERE e.edge_id = traj_edge.edge_id"
+        ");"
+        "DELETE FROM edge WHERE edge_id NOT IN ("
+        "    SELECT min(edge_id) FROM edge GROUP BY node_ini, node_end"
+        ");"
+        "DELETE FROM traj_edge WHERE edge_id IN ("
+        "    SELECT edge_id FROM edge WHERE node_ini = node_end"
+        ");"
+        "DELETE FROM edge WHERE node_ini = node_end;"
+    })?;
+    for traj_id in traj_ids {
+        renumber_path(&tx, traj_id)?;
+    }
     tx.commit()
-        .map_err(|e| anyhow!("Failed to insert nodes: {:?}", e))
+        .map_err(|e| anyhow!("Failed to repair network: {:?}", e))?;
+    Ok(dropped)
 }
@@ -0,0 +1,75 @@
+use crate::db::evedb::EveDb;
+use crate::models::event::{EventKind, EventSignal, PassageEvent};
+use anyhow::anyhow;
+use rusqlite::{Error, Row, params};
+use text_block_macros::text_block;
+
+pub fn get_event_signals(db: &EveDb, trajectory_id: i64) -> anyhow::Result<Vec<EventSignal>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     s.day_num "
+        ",          s.time_stamp "
+        ",          s.match_latitude "
+        ",          s.match_longitude "
+        ",          s.speed "
+        ",          coalesce(s.intersection, 0) > 0 "
+        ",          coalesce(s.bus_stop, 0) > 0 "
+        "from       signal s "
+        "inner join trajectory t on s.vehicle_id = t.vehicle_id and  s.trip_id = t.trip_id "
+        "           and s.time_stamp between t.time_stamp_ini and t.time_stamp_end "
+        "where      t.traj_id = ?1 "
+        "order by   s.time_stamp "
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let signals = stmt.query_map([trajectory_id], |row: &Row| {
+        Ok(EventSignal {
+            day_num: row.get(0)?,
+            time_stamp: row.get(1)?,
+            latitude: row.get(2)?,
+            longitude: row.get(3)?,
+            speed: row.get(4)?,
+            intersection: row.get(5)?,
+            bus_stop: row.get(6)?,
+        })
+    })?;
+    let results = signals.collect::<anyhow::Result<Vec<EventSignal>, Error>>()?;
+    Ok(results)
+}
+
+pub fn insert_passage_events(
+    db: &EveDb,
+    kind: EventKind,
+    events: &[PassageEvent],
+) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = format!(
+        "INSERT INTO {} \
+             (traj_id, latitude, longitude, h3_12, time_stamp_ini, time_stamp_end, \
+              dt_ini, dt_end, duration_s, approach_speed, min_speed, stopped) \
+         VALUES \
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12);",
+        kind.table_name()
+    );
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(&sql)?;
+        for event in events {
+            stmt.execute(params!(
+                event.traj_id,
+                event.latitude,
+                event.longitude,
+                event.h3_12 as i64,
+                event.time_stamp_ini,
+                event.time_stamp_end,
+                event.dt_ini,
+                event.dt_end,
+                event.duration_s,
+                event.approach_speed,
+                event.min_speed,
+                event.stopped,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert {} records: {:?}", kind.table_name(), e))
+}
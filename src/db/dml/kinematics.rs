@@ -0,0 +1,35 @@
+use crate::db::evedb::EveDb;
+use crate::models::kinematics::SignalKinematics;
+use anyhow::anyhow;
+use rusqlite::params;
+use text_block_macros::text_block;
+
+pub fn insert_signal_kinematics(db: &EveDb, rows: &[SignalKinematics]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO signal_kinematics "
+        "    (signal_id, traj_id, dt_s, step_m, distance_m, speed, speed_interpolated, "
+        "     acceleration, jerk) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for row in rows {
+            stmt.execute(params!(
+                row.signal_id,
+                row.traj_id,
+                row.dt_s,
+                row.step_m,
+                row.distance_m,
+                row.speed,
+                row.speed_interpolated,
+                row.acceleration,
+                row.jerk,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert signal kinematics: {:?}", e))
+}
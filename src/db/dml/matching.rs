@@ -0,0 +1,117 @@
+use crate::db::evedb::EveDb;
+use crate::etl::filters::FilterReport;
+use crate::models::quality::MatchQuality;
+use anyhow::anyhow;
+use rusqlite::{OptionalExtension, params};
+use text_block_macros::text_block;
+
+pub fn insert_match_metadata(
+    db: &EveDb,
+    traj_id: i64,
+    point_count: usize,
+    filtered_count: usize,
+    filter_settings: &str,
+    search_radius_m: f64,
+    reports: &[FilterReport],
+) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql_metadata = text_block! {
+        "INSERT OR REPLACE INTO match_metadata "
+        "    (traj_id, point_count, filtered_count, filter_settings, search_radius_m) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5);"
+    };
+    let sql_filter = text_block! {
+        "INSERT INTO match_filter "
+        "    (traj_id, filter, removed) "
+        "VALUES "
+        "    (?1, ?2, ?3);"
+    };
+    let tx = conn.transaction()?;
+    {
+        tx.execute(
+            sql_metadata,
+            params!(
+                traj_id,
+                point_count as i64,
+                filtered_count as i64,
+                filter_settings,
+                search_radius_m
+            ),
+        )?;
+        let mut stmt = tx.prepare(sql_filter)?;
+        for report in reports {
+            stmt.execute(params!(traj_id, report.filter, report.removed as i64))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert match metadata: {:?}", e))
+}
+
+/// Search radius (m) the map-matcher used for a trajectory, if it was matched
+pub fn get_search_radius(db: &EveDb, traj_id: i64) -> anyhow::Result<Option<f64>> {
+    let conn = db.connect()?;
+    let sql = "SELECT search_radius_m FROM match_metadata WHERE traj_id = ?1;";
+    let radius = conn
+        .query_row(sql, params!(traj_id), |row| row.get(0))
+        .optional()?;
+    Ok(radius.flatten())
+}
+
+pub fn insert_match_quality(db: &EveDb, rows: &[MatchQuality]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO match_quality "
+        "    (traj_id, point_count, mean_distance_m, max_distance_m, matched_length_m, "
+        "     length_ratio, beyond_radius_share, eved_mean_distance_m, eved_max_distance_m) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for row in rows {
+            stmt.execute(params!(
+                row.traj_id,
+                row.point_count,
+                row.mean_distance_m,
+                row.max_distance_m,
+                row.matched_length_m,
+                row.length_ratio,
+                row.beyond_radius_share,
+                row.eved_mean_distance_m,
+                row.eved_max_distance_m,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert match quality: {:?}", e))
+}
+
+/// Sets the `outlier` flag of every `match_quality` row and returns the flagged
+/// trajectory ids
+pub fn flag_match_outliers(
+    db: &EveDb,
+    max_mean_distance_m: f64,
+    max_beyond_share: f64,
+    min_length_ratio: f64,
+    max_length_ratio: f64,
+) -> anyhow::Result<Vec<i64>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "UPDATE match_quality "
+        "SET    outlier = coalesce(mean_distance_m > ?1 "
+        "                       or beyond_radius_share > ?2 "
+        "                       or length_ratio < ?3 "
+        "                       or length_ratio > ?4, 0);"
+    };
+    conn.execute(
+        sql,
+        params!(max_mean_distance_m, max_beyond_share, min_length_ratio, max_length_ratio),
+    )?;
+    let mut stmt = conn.prepare("SELECT traj_id FROM match_quality WHERE outlier = 1 ORDER BY traj_id;")?;
+    let traj_ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<i64>, _>>()?;
+    Ok(traj_ids)
+}
@@ -21,7 +21,7 @@ pub fn insert_match_error(
 }
 
 pub fn insert_nodes(db: &EveDb, traj_id: i64,
-                    nodes: &mut Vec<Node>) -> anyhow::Result<()> {
+                    nodes: &mut [Node]) -> anyhow::Result<()> {
     let mut conn = db.connect()?;
 
     let sql = text_block! {
@@ -31,12 +31,22 @@ pub fn insert_nodes(db: &EveDb, traj_id: i64,
         "    (?1, ?2, ?3, ?4)"
         " RETURNING node_id;"
     };
-    let sql_traj_node = "INSERT OR IGNORE INTO traj_node (traj_id, node_id) VALUES (?1, ?2);";
+    let sql_traj_node = text_block! {
+        "INSERT INTO traj_node "
+        "    (traj_id, node_id, seq, shape_index, distance_m) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5);"
+    };
     let tx = conn.transaction()?;
     {
         let mut stmt = tx.prepare(sql)?;
         let mut stmt_traj_node = tx.prepare(sql_traj_node)?;
-        for node in nodes {
+        let mut distance_m = 0.0;
+        let mut previous: Option<Node> = None;
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if let Some(previous) = previous {
+                distance_m += previous.distance_to(node);
+            }
             // Only insert new nodes in the table
             let node_id = if node.id == 0 {
                 stmt.query_row(
@@ -47,7 +57,9 @@ pub fn insert_nodes(db: &EveDb, traj_id: i64,
                 node.id
             };
             node.id = node_id;
-            stmt_traj_node.execute(params!(traj_id, node_id))?;
+            // Every node comes from one point of the matched shape, in order
+            stmt_traj_node.execute(params!(traj_id, node_id, i as i64, i as i64, distance_m))?;
+            previous = Some(*node);
         }
     }
     tx.commit()
@@ -79,4 +91,47 @@ pub fn get_ring(db: &EveDb, ring: Vec<u64>) -> anyhow::Result<Vec<Node>> {
     .collect::<Result<Vec<_>, _>>()?;
 
     Ok(nodes)
-}
\ No newline at end of file
+}
+
+pub fn get_nodes(db: &EveDb) -> anyhow::Result<Vec<Node>> {
+    let conn = db.connect()?;
+    let sql = "SELECT node_id, latitude, longitude, altitude, h3_12 FROM node ORDER BY node_id;";
+    let mut stmt = conn.prepare(sql)?;
+    let nodes = stmt
+        .query_map([], |row| {
+            Ok(Node {
+                id: row.get(0)?,
+                latitude: row.get(1)?,
+                longitude: row.get(2)?,
+                altitude: row.get(3)?,
+                h3_12: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(nodes)
+}
+
+/// Returns the matched nodes of a trajectory in traversal order
+pub fn get_matched_shape(db: &EveDb, trajectory_id: i64) -> anyhow::Result<Vec<Node>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     n.node_id, n.latitude, n.longitude, n.altitude, n.h3_12"
+        "from       traj_node tn"
+        "inner join node n on tn.node_id = n.node_id"
+        "where      tn.traj_id = ?1"
+        "order by   tn.seq;"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let nodes = stmt
+        .query_map([trajectory_id], |row| {
+            Ok(Node {
+                id: row.get(0)?,
+                latitude: row.get(1)?,
+                longitude: row.get(2)?,
+                altitude: row.get(3)?,
+                h3_12: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(nodes)
+}
@@ -0,0 +1,63 @@
+use crate::db::evedb::EveDb;
+use crate::models::od::{OdCell, OdTrip};
+use anyhow::anyhow;
+use rusqlite::{Error, Row, params};
+use text_block_macros::text_block;
+
+pub fn get_od_trips(db: &EveDb) -> anyhow::Result<Vec<OdTrip>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     v.vehicle_type"
+        ",          t.dt_ini"
+        ",          t.duration_s"
+        ",          t.length_m"
+        ",          t.h3_12_ini"
+        ",          t.h3_12_end"
+        "from       trajectory t"
+        "left join  vehicle v on t.vehicle_id = v.vehicle_id"
+        "where      t.dt_ini is not null and t.h3_12_ini is not null and t.h3_12_end is not null;"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let trips = stmt.query_map([], |row: &Row| {
+        Ok(OdTrip {
+            vehicle_type: row.get(0)?,
+            dt_ini: row.get(1)?,
+            duration_s: row.get(2)?,
+            length_m: row.get(3)?,
+            h3_12_ini: row.get::<_, i64>(4)? as u64,
+            h3_12_end: row.get::<_, i64>(5)? as u64,
+        })
+    })?;
+    let results = trips.collect::<anyhow::Result<Vec<OdTrip>, Error>>()?;
+    Ok(results)
+}
+
+pub fn insert_od_cells(db: &EveDb, cells: &[OdCell]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO od_matrix "
+        "    (h3_ini, h3_end, resolution, vehicle_type, hour, weekday, "
+        "     trip_count, median_duration_s, median_length_m) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for cell in cells {
+            stmt.execute(params!(
+                cell.h3_ini as i64,
+                cell.h3_end as i64,
+                cell.resolution,
+                cell.vehicle_type,
+                cell.hour,
+                cell.weekday,
+                cell.trip_count,
+                cell.median_duration_s,
+                cell.median_length_m,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert od_matrix cells: {:?}", e))
+}
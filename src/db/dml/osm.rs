@@ -0,0 +1,126 @@
+use crate::db::evedb::EveDb;
+use crate::models::node::Node;
+use crate::models::osm::OsmWay;
+use anyhow::anyhow;
+use rusqlite::params;
+use text_block_macros::text_block;
+
+/// Returns the `(min_lat, min_lon, max_lat, max_lon)` box of the GPS signals, or
+/// `None` when there are none
+pub fn get_signal_bounds(db: &EveDb) -> anyhow::Result<Option<(f64, f64, f64, f64)>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "SELECT MIN(latitude), MIN(longitude), MAX(latitude), MAX(longitude) "
+        "FROM signal "
+        "WHERE latitude IS NOT NULL AND longitude IS NOT NULL;"
+    };
+    let bounds: (Option<f64>, Option<f64>, Option<f64>, Option<f64>) =
+        conn.query_row(sql, [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+    Ok(match bounds {
+        (Some(min_lat), Some(min_lon), Some(max_lat), Some(max_lon)) => {
+            Some((min_lat, min_lon, max_lat, max_lon))
+        }
+        _ => None,
+    })
+}
+
+pub fn insert_osm_network(db: &EveDb, nodes: &[Node], ways: &[OsmWay]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO osm_node (osm_node_id, latitude, longitude, h3_12) VALUES (?1, ?2, ?3, ?4);",
+        )?;
+        for node in nodes {
+            stmt.execute(params!(node.id, node.latitude, node.longitude, node.h3_12))?;
+        }
+
+        let mut stmt = tx.prepare(text_block! {
+            "INSERT INTO osm_way "
+            "    (osm_way_id, highway, name, oneway, max_speed_kmh, length_m) "
+            "VALUES "
+            "    (?1, ?2, ?3, ?4, ?5, ?6);"
+        })?;
+        let mut node_stmt = tx.prepare(
+            "INSERT INTO osm_way_node (osm_way_id, seq, osm_node_id) VALUES (?1, ?2, ?3);",
+        )?;
+        for way in ways {
+            stmt.execute(params!(
+                way.id,
+                way.highway,
+                way.name,
+                way.oneway,
+                way.max_speed_kmh,
+                way.length_m,
+            ))?;
+            for (seq, node_id) in way.nodes.iter().enumerate() {
+                node_stmt.execute(params!(way.id, seq as i64, node_id))?;
+            }
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert the OSM network: {:?}", e))
+}
+
+pub fn get_osm_nodes(db: &EveDb) -> anyhow::Result<Vec<Node>> {
+    let conn = db.connect()?;
+    let sql = "SELECT osm_node_id, latitude, longitude, h3_12 FROM osm_node ORDER BY osm_node_id;";
+    let mut stmt = conn.prepare(sql)?;
+    let nodes = stmt
+        .query_map([], |row| {
+            Ok(Node {
+                id: row.get(0)?,
+                latitude: row.get(1)?,
+                longitude: row.get(2)?,
+                altitude: 0.0,
+                h3_12: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(nodes)
+}
+
+pub fn get_osm_ways(db: &EveDb) -> anyhow::Result<Vec<OsmWay>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "SELECT w.osm_way_id, w.highway, w.name, w.oneway, w.max_speed_kmh, w.length_m, "
+        "       n.osm_node_id "
+        "FROM osm_way w "
+        "JOIN osm_way_node n ON n.osm_way_id = w.osm_way_id "
+        "ORDER BY w.osm_way_id, n.seq;"
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([])?;
+
+    let mut ways: Vec<OsmWay> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let node_id: i64 = row.get(6)?;
+        match ways.last_mut() {
+            Some(way) if way.id == id => way.nodes.push(node_id),
+            _ => ways.push(OsmWay {
+                id,
+                highway: row.get(1)?,
+                name: row.get(2)?,
+                oneway: row.get(3)?,
+                max_speed_kmh: row.get(4)?,
+                length_m: row.get(5)?,
+                nodes: vec![node_id],
+            }),
+        }
+    }
+    Ok(ways)
+}
+
+pub fn insert_edge_osm(db: &EveDb, rows: &[(i64, i64)]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare("INSERT INTO edge_osm (edge_id, osm_way_id) VALUES (?1, ?2);")?;
+        for (edge_id, osm_way_id) in rows {
+            stmt.execute(params!(edge_id, osm_way_id))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert edge_osm: {:?}", e))
+}
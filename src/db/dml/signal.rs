@@ -1,12 +1,18 @@
 use crate::db::evedb::EveDb;
-use crate::models::signal::CsvSignal;
-use crate::tools::lat_lng_to_h3_12;
+use crate::models::signal::{CsvSignal, EnergySignal, TrajectorySignal};
+use crate::tools::{TimeBase, lat_lng_to_h3_12};
 use anyhow::anyhow;
+use chrono::{SecondsFormat, Utc};
 use csv::DeserializeRecordsIter;
-use rusqlite::{Transaction, params};
+use rusqlite::{Error, Row, Transaction, params};
 use text_block_macros::text_block;
 
-pub fn insert_signal(tx: &mut Transaction<'_>, signal: &CsvSignal) -> anyhow::Result<usize> {
+pub fn insert_signal(
+    tx: &mut Transaction<'_>,
+    signal: &CsvSignal,
+    time_base: &TimeBase,
+    local_time: bool,
+) -> anyhow::Result<usize> {
     let sql = text_block! {
         "INSERT INTO signal ("
         "   day_num, vehicle_id, trip_id, time_stamp, latitude, "
@@ -16,16 +22,25 @@ pub fn insert_signal(tx: &mut Transaction<'_>, signal: &CsvSignal) -> anyhow::Re
         "   lt_ftb_2, elevation, elevation_smooth, gradient, "
         "   energy_consumption, match_latitude, match_longitude, "
         "   match_type, speed_limit_type, speed_limit, speed_limit_direct, "
-        "   intersection, bus_stop, focus_points, h3_12) "
+        "   intersection, bus_stop, focus_points, h3_12, ts_utc, ts_local, "
+        "   speed_limit_kmh, speed_limit_dir, speed_limit_bwd_kmh) "
         "VALUES "
         "($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,"
         " $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34,"
-        " $35, $36);"
+        " $35, $36, $37, $38, $39, $40, $41);"
     };
 
     let index: i64 = lat_lng_to_h3_12(signal.match_latitude, signal.match_longitude) as i64;
+    let date_time = time_base.date_time(signal.day_num, signal.time_stamp as i64);
+    let ts_utc = date_time.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Millis, true);
+    let ts_local = if local_time {
+        Some(date_time.to_rfc3339_opts(SecondsFormat::Millis, false))
+    } else {
+        None
+    };
+    let speed_limit = signal.parse_speed_limit();
     let params = params!(
-        signal.day_num as i64,
+        signal.day_num,
         signal.vehicle_id as i64,
         signal.trip_id as i64,
         signal.time_stamp as i64,
@@ -61,23 +76,110 @@ pub fn insert_signal(tx: &mut Transaction<'_>, signal: &CsvSignal) -> anyhow::Re
         signal.bus_stop,
         signal.focus_points.clone(),
         index,
+        ts_utc,
+        ts_local,
+        speed_limit.as_ref().map(|l| l.kmh),
+        speed_limit.as_ref().map(|l| l.direction()),
+        speed_limit.as_ref().and_then(|l| l.backward_kmh),
     );
-    tx.execute(sql, params)
-        .map_err(|e| anyhow!("Failed to insert signal: {:?}", e))
+    let count = tx
+        .execute(sql, params)
+        .map_err(|e| anyhow!("Failed to insert signal: {:?}", e))?;
+
+    let signal_id = tx.last_insert_rowid();
+    for point_type in signal.parse_focus_points() {
+        tx.execute(
+            "INSERT INTO focus_point (signal_id, point_type) VALUES (?1, ?2);",
+            params!(signal_id, point_type),
+        )
+        .map_err(|e| anyhow!("Failed to insert focus point: {:?}", e))?;
+    }
+    Ok(count)
 }
 
 pub fn insert_signals(
     db: &EveDb,
     signals: DeserializeRecordsIter<'_, &[u8], CsvSignal>,
+    time_base: &TimeBase,
+    local_time: bool,
 ) -> anyhow::Result<usize> {
     let mut conn = db.connect()?;
     let mut counter: usize = 0;
 
     let mut tx = conn.transaction()?;
     for signal in signals.flatten() {
-        insert_signal(&mut tx, &signal)?;
+        insert_signal(&mut tx, &signal, time_base, local_time)?;
         counter += 1;
     }
     tx.commit()?;
     Ok(counter)
 }
+
+pub fn get_trajectory_signals(
+    db: &EveDb,
+    trajectory_id: i64,
+) -> anyhow::Result<Vec<TrajectorySignal>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     s.signal_id "
+        ",          s.day_num "
+        ",          s.time_stamp "
+        ",          s.match_latitude "
+        ",          s.match_longitude "
+        ",          s.speed "
+        "from       signal s "
+        "inner join trajectory t on s.vehicle_id = t.vehicle_id and  s.trip_id = t.trip_id "
+        "           and s.time_stamp between t.time_stamp_ini and t.time_stamp_end "
+        "where      t.traj_id = ?1 "
+        "order by   s.time_stamp "
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let signals = stmt.query_map([trajectory_id], |row: &Row| {
+        Ok(TrajectorySignal {
+            signal_id: row.get(0)?,
+            day_num: row.get(1)?,
+            time_stamp: row.get(2)?,
+            latitude: row.get(3)?,
+            longitude: row.get(4)?,
+            speed: row.get(5)?,
+        })
+    })?;
+    let results = signals.collect::<anyhow::Result<Vec<TrajectorySignal>, Error>>()?;
+    Ok(results)
+}
+
+pub fn get_energy_signals(db: &EveDb, trajectory_id: i64) -> anyhow::Result<Vec<EnergySignal>> {
+    let conn = db.connect()?;
+    let sql = text_block! {
+        "select     s.time_stamp "
+        ",          s.fuel_rate "
+        ",          s.hv_bat_current "
+        ",          s.hv_bat_volt "
+        ",          s.hv_bat_soc "
+        ",          s.oat "
+        ",          s.ac_power_kw "
+        ",          s.ac_power_w "
+        ",          s.heater_power_w "
+        "from       signal s "
+        "inner join trajectory t on s.vehicle_id = t.vehicle_id and  s.trip_id = t.trip_id "
+        "           and s.time_stamp between t.time_stamp_ini and t.time_stamp_end "
+        "where      t.traj_id = ?1 "
+        "order by   s.time_stamp "
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let signals = stmt.query_map([trajectory_id], |row: &Row| {
+        Ok(EnergySignal {
+            time_stamp: row.get(0)?,
+            fuel_rate: row.get(1)?,
+            hv_bat_current: row.get(2)?,
+            hv_bat_volt: row.get(3)?,
+            hv_bat_soc: row.get(4)?,
+            oat: row.get(5)?,
+            ac_power_kw: row.get(6)?,
+            ac_power_w: row.get(7)?,
+            heater_power_w: row.get(8)?,
+        })
+    })?;
+    let results = signals.collect::<anyhow::Result<Vec<EnergySignal>, Error>>()?;
+    Ok(results)
+}
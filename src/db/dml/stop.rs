@@ -0,0 +1,36 @@
+use crate::db::evedb::EveDb;
+use crate::models::stop::StopEvent;
+use anyhow::anyhow;
+use rusqlite::params;
+use text_block_macros::text_block;
+
+pub fn insert_stop_events(db: &EveDb, stops: &[StopEvent]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO stop_event "
+        "    (traj_id, latitude, longitude, h3_12, time_stamp_ini, time_stamp_end, "
+        "     dt_ini, dt_end, duration_s, node_id) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for stop in stops {
+            stmt.execute(params!(
+                stop.traj_id,
+                stop.latitude,
+                stop.longitude,
+                stop.h3_12 as i64,
+                stop.time_stamp_ini,
+                stop.time_stamp_end,
+                stop.dt_ini,
+                stop.dt_end,
+                stop.duration_s,
+                stop.node_id,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert stop events: {:?}", e))
+}
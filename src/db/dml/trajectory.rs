@@ -1,5 +1,8 @@
 use crate::db::evedb::EveDb;
-use crate::models::trajectory::{TrajectoryPoint, TrajectoryUpdate, WayPoint};
+use crate::models::trajectory::{
+    TrajectoryPoint, TrajectoryRegen, TrajectorySplit, TrajectoryStops, TrajectoryUpdate,
+    WayPoint,
+};
 use anyhow::anyhow;
 use indicatif::ProgressIterator;
 use rusqlite::{Error, Row, params};
@@ -12,8 +15,10 @@ pub fn insert_trajectories(db: &EveDb) -> anyhow::Result<usize> {
     db.create_trajectory_indexes()?;
 
     let sql = text_block! {
-        "INSERT INTO trajectory (vehicle_id, trip_id)"
-        "    SELECT DISTINCT vehicle_id, trip_id FROM signal;"
+        "INSERT INTO trajectory (vehicle_id, trip_id, segment, time_stamp_ini, time_stamp_end)"
+        "    SELECT   vehicle_id, trip_id, 0, min(time_stamp), max(time_stamp)"
+        "    FROM     signal"
+        "    GROUP BY vehicle_id, trip_id;"
     };
     conn.execute(sql, ())
         .map_err(|e| anyhow!("Failed to insert trajectories: {:?}", e))
@@ -31,6 +36,15 @@ pub fn update_trajectories(db: &EveDb, updates: &[TrajectoryUpdate]) -> anyhow::
             ,      dt_end = ?
             ,      h3_12_ini = ?
             ,      h3_12_end = ?
+            ,      fuel_l = ?
+            ,      fuel_l_per_100km = ?
+            ,      energy_kwh = ?
+            ,      energy_kwh_per_km = ?
+            ,      soc_ini = ?
+            ,      soc_end = ?
+            ,      mean_oat = ?
+            ,      ac_energy_kwh = ?
+            ,      heater_energy_kwh = ?
             WHERE  traj_id = ?
             ",
     );
@@ -43,6 +57,15 @@ pub fn update_trajectories(db: &EveDb, updates: &[TrajectoryUpdate]) -> anyhow::
             update.dt_end,
             update.h3_12_ini as i64,
             update.h3_12_end as i64,
+            update.energy.fuel_l,
+            update.energy.fuel_l_per_100km,
+            update.energy.energy_kwh,
+            update.energy.energy_kwh_per_km,
+            update.energy.soc_ini,
+            update.energy.soc_end,
+            update.energy.mean_oat,
+            update.energy.ac_energy_kwh,
+            update.energy.heater_energy_kwh,
             update.trajectory_id
         );
         tx.execute(&sql, params)?;
@@ -51,6 +74,79 @@ pub fn update_trajectories(db: &EveDb, updates: &[TrajectoryUpdate]) -> anyhow::
         .map_err(|e| anyhow!("Failed to update trajectories: {:?}", e))
 }
 
+pub fn split_trajectories(db: &EveDb, splits: &[TrajectorySplit]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql_update = text_block! {
+        "UPDATE trajectory"
+        "SET    parent_trip = trip_id"
+        ",      time_stamp_ini = ?1"
+        ",      time_stamp_end = ?2"
+        "WHERE  traj_id = ?3"
+    };
+    let sql_insert = text_block! {
+        "INSERT INTO trajectory "
+        "    (vehicle_id, trip_id, parent_trip, segment, time_stamp_ini, time_stamp_end) "
+        "SELECT vehicle_id, trip_id, trip_id, ?1, ?2, ?3 "
+        "FROM   trajectory "
+        "WHERE  traj_id = ?4;"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt_update = tx.prepare(sql_update)?;
+        let mut stmt_insert = tx.prepare(sql_insert)?;
+        for split in splits {
+            // The first segment keeps the original trajectory row
+            for (segment, (ts_ini, ts_end)) in split.bounds.iter().enumerate() {
+                if segment == 0 {
+                    stmt_update.execute(params!(ts_ini, ts_end, split.trajectory_id))?;
+                } else {
+                    stmt_insert.execute(params!(
+                        segment as i64,
+                        ts_ini,
+                        ts_end,
+                        split.trajectory_id
+                    ))?;
+                }
+            }
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to split trajectories: {:?}", e))
+}
+
+pub fn update_trajectory_stops(db: &EveDb, stops: &[TrajectoryStops]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let tx = conn.transaction()?;
+    let sql = text_block! {
+        "UPDATE trajectory"
+        "SET    stop_count = ?1"
+        ",      idle_s = ?2"
+        "WHERE  traj_id = ?3"
+    };
+
+    for stop in stops {
+        tx.execute(sql, params!(stop.stop_count, stop.idle_s, stop.trajectory_id))?;
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to update trajectory stops: {:?}", e))
+}
+
+pub fn update_trajectory_regen(db: &EveDb, regens: &[TrajectoryRegen]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let tx = conn.transaction()?;
+    let sql = text_block! {
+        "UPDATE trajectory"
+        "SET    regen_kwh = ?1"
+        "WHERE  traj_id = ?2"
+    };
+
+    for regen in regens {
+        tx.execute(sql, params!(regen.regen_kwh, regen.trajectory_id))?;
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to update trajectory regen: {:?}", e))
+}
+
 pub fn get_trajectory_ids(db: &EveDb) -> anyhow::Result<Vec<i64>> {
     let conn = db.connect()?;
     let sql = text_block! {
@@ -63,6 +159,17 @@ pub fn get_trajectory_ids(db: &EveDb) -> anyhow::Result<Vec<i64>> {
     Ok(traj_ids)
 }
 
+/// Returns the id and GPS length of every trajectory
+pub fn get_trajectory_lengths(db: &EveDb) -> anyhow::Result<Vec<(i64, Option<f64>)>> {
+    let conn = db.connect()?;
+    let sql = "SELECT traj_id, length_m FROM trajectory ORDER BY traj_id;";
+    let mut stmt = conn.prepare(sql)?;
+    let lengths = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<anyhow::Result<Vec<(i64, Option<f64>)>, Error>>()?;
+    Ok(lengths)
+}
+
 pub fn get_trajectory_points(
     db: &EveDb,
     trajectory_id: i64,
@@ -77,6 +184,7 @@ pub fn get_trajectory_points(
         ",          s.match_longitude "
         "from       signal s "
         "inner join trajectory t on s.vehicle_id = t.vehicle_id and  s.trip_id = t.trip_id "
+        "           and s.time_stamp between t.time_stamp_ini and t.time_stamp_end "
         "where      t.traj_id = ?1 "
         "order by   s.time_stamp "
     };
@@ -103,6 +211,7 @@ pub fn get_way_points(db: &EveDb, trajectory_id: i64) -> anyhow::Result<Vec<WayP
         ",          min(s.time_stamp) / 1000 as time"
         "from       signal s"
         "inner join trajectory t on s.vehicle_id = t.vehicle_id and s.trip_id = t.trip_id"
+        "           and s.time_stamp between t.time_stamp_ini and t.time_stamp_end"
         "where      t.traj_id = ?1"
         "group by   s.latitude, s.longitude"
         "order by   time;"
@@ -0,0 +1,42 @@
+use crate::db::evedb::EveDb;
+use crate::models::turn::Turn;
+use anyhow::anyhow;
+use rusqlite::params;
+use text_block_macros::text_block;
+
+/// Returns the `(traj_id, edge_id)` pairs in traversal order within each trajectory
+pub fn get_edge_sequences(db: &EveDb) -> anyhow::Result<Vec<(i64, i64)>> {
+    let conn = db.connect()?;
+    let sql = "SELECT traj_id, edge_id FROM traj_edge ORDER BY traj_id, seq;";
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+pub fn insert_turns(db: &EveDb, turns: &[Turn]) -> anyhow::Result<()> {
+    let mut conn = db.connect()?;
+    let sql = text_block! {
+        "INSERT INTO turn "
+        "    (from_edge, via_node, to_edge, angle_deg, turn_type, turn_count) "
+        "VALUES "
+        "    (?1, ?2, ?3, ?4, ?5, ?6);"
+    };
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for turn in turns {
+            stmt.execute(params!(
+                turn.from_edge,
+                turn.via_node,
+                turn.to_edge,
+                turn.angle_deg,
+                turn.turn_type.code(),
+                turn.turn_count,
+            ))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| anyhow!("Failed to insert turns: {:?}", e))
+}
@@ -1,7 +1,24 @@
 use crate::db::evedb::EveDb;
-use crate::models::vehicle::Vehicle;
+use crate::models::vehicle::{Vehicle, VehicleCategory};
 use indicatif::ProgressIterator;
-use rusqlite::params;
+use rusqlite::{Transaction, params};
+
+/// Returns the code of a category, adding unrecognized values to its lookup table so the
+/// vehicle foreign keys hold.
+fn category_code<C: VehicleCategory>(
+    tx: &Transaction<'_>,
+    category: &Option<C>,
+) -> anyhow::Result<Option<String>> {
+    let Some(category) = category else {
+        return Ok(None);
+    };
+    let sql = format!(
+        "INSERT OR IGNORE INTO {} (code, description, known) VALUES (?1, NULL, 0)",
+        C::TABLE
+    );
+    tx.execute(&sql, params!(category.code()))?;
+    Ok(Some(category.code().to_string()))
+}
 
 pub fn insert_vehicles(db: &EveDb, vehicles: Vec<Vehicle>) -> anyhow::Result<usize> {
     let sql = "
@@ -10,22 +27,34 @@ pub fn insert_vehicles(db: &EveDb, vehicles: Vec<Vehicle>) -> anyhow::Result<usi
             vehicle_type,
             vehicle_class,
             engine,
+            displacement_l,
+            cylinders,
+            turbo,
             transmission,
             drive_wheels,
-            weight) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+            weight,
+            weight_kg,
+            weight_unit) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)";
 
     let mut conn = db.connect()?;
+    conn.execute("PRAGMA foreign_keys = ON;", ())?;
     let tx = conn.transaction()?;
 
     for vehicle in vehicles.iter().progress() {
+        let engine_spec = vehicle.engine_spec.as_ref();
         let params = params!(
             vehicle.vehicle_id,
-            vehicle.vehicle_type,
-            vehicle.vehicle_class,
+            category_code(&tx, &vehicle.vehicle_type)?,
+            category_code(&tx, &vehicle.vehicle_class)?,
             vehicle.engine,
-            vehicle.transmission,
-            vehicle.drive_wheels,
-            vehicle.weight
+            engine_spec.and_then(|e| e.displacement_l),
+            engine_spec.and_then(|e| e.cylinders),
+            engine_spec.map(|e| e.turbo),
+            category_code(&tx, &vehicle.transmission)?,
+            category_code(&tx, &vehicle.drive_wheels)?,
+            vehicle.weight,
+            vehicle.weight_kg,
+            vehicle.weight_unit.map(|u| u.code())
         );
         tx.execute(sql, params)?;
     }
@@ -1,10 +1,25 @@
 use crate::db::api::SqliteDb;
 use crate::db::ddl;
 use crate::db::dml;
+use crate::etl::filters::FilterReport;
+use crate::models::aggregate::{H3Aggregate, H3Metric, H3SignalStats};
+use crate::models::charge::{ChargeEvent, TripSoc};
+use crate::models::edge::NetworkEdge;
+use crate::models::event::{EventKind, EventSignal, PassageEvent};
+use crate::models::kinematics::SignalKinematics;
 use crate::models::node::Node;
-use crate::models::signal::CsvSignal;
-use crate::models::trajectory::{TrajectoryPoint, TrajectoryUpdate, WayPoint};
+use crate::models::od::{OdCell, OdTrip};
+use crate::models::osm::OsmWay;
+use crate::models::quality::MatchQuality;
+use crate::models::signal::{CsvSignal, EnergySignal, TrajectorySignal};
+use crate::models::stop::StopEvent;
+use crate::models::trajectory::{
+    TrajectoryPoint, TrajectoryRegen, TrajectorySplit, TrajectoryStops, TrajectoryUpdate,
+    WayPoint,
+};
+use crate::models::turn::Turn;
 use crate::models::vehicle::Vehicle;
+use crate::tools::TimeBase;
 use anyhow::Result;
 use csv::DeserializeRecordsIter;
 use rusqlite::Connection;
@@ -28,6 +43,16 @@ impl EveDb {
         Ok(conn)
     }
 
+    pub fn table_exists(&self, table: &str) -> Result<bool> {
+        let conn = self.connect()?;
+        let count: i64 = conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type='table' AND name=?1",
+            [table],
+            |r| r.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
     pub fn create_vehicle_table(&self) -> Result<usize> {
         ddl::vehicle::create_table(self)
     }
@@ -36,11 +61,17 @@ impl EveDb {
         ddl::signal::create_table(self)
     }
 
+    pub fn create_focus_point_table(&self) -> Result<usize> {
+        ddl::signal::create_focus_point_table(self)
+    }
+
     pub fn insert_signals(
         &self,
         signals: DeserializeRecordsIter<'_, &[u8], CsvSignal>,
+        time_base: &TimeBase,
+        local_time: bool,
     ) -> Result<usize> {
-        dml::signal::insert_signals(self, signals)
+        dml::signal::insert_signals(self, signals, time_base, local_time)
     }
 
     pub fn create_signal_indexes(&self) -> Result<usize> {
@@ -63,6 +94,10 @@ impl EveDb {
         dml::trajectory::insert_trajectories(self)
     }
 
+    pub fn split_trajectories(&self, splits: &[TrajectorySplit]) -> Result<()> {
+        dml::trajectory::split_trajectories(self, splits)
+    }
+
     pub fn update_trajectories(&self, updates: &[TrajectoryUpdate]) -> Result<()> {
         dml::trajectory::update_trajectories(self, updates)
     }
@@ -119,18 +154,254 @@ impl EveDb {
         dml::node::insert_match_error(self, trajectory_id, match_error)
     }
 
-    pub fn insert_nodes(&self, traj_id: i64, nodes: &mut Vec<Node>) -> Result<()> {
+    pub fn insert_nodes(&self, traj_id: i64, nodes: &mut [Node]) -> Result<()> {
         dml::node::insert_nodes(self, traj_id, nodes)
     }
     
-    pub fn insert_edges(&self, traj_id: i64, nodes: &[Node]) -> Result<()> {
-        dml::edge::insert_edges(self, traj_id, nodes)
+    pub fn insert_edges(&self, traj_id: i64, nodes: &[Node], times_s: &[Option<f64>]) -> Result<()> {
+        dml::edge::insert_edges(self, traj_id, nodes, times_s)
+    }
+
+    pub fn get_nodes(&self) -> Result<Vec<Node>> {
+        dml::node::get_nodes(self)
+    }
+
+    pub fn get_network_edges(&self) -> Result<Vec<NetworkEdge>> {
+        dml::edge::get_network_edges(self)
+    }
+
+    pub fn repair_network(
+        &self,
+        merges: &[(i64, i64)],
+        edges: &[NetworkEdge],
+    ) -> Result<Vec<&'static str>> {
+        dml::edge::repair_network(self, merges, edges)
+    }
+
+    pub fn create_match_metadata_table(&self) -> Result<usize> {
+        ddl::matching::create_metadata_table(self)
+    }
+
+    pub fn create_match_filter_table(&self) -> Result<usize> {
+        ddl::matching::create_filter_table(self)
+    }
+
+    pub fn insert_match_metadata(
+        &self,
+        traj_id: i64,
+        point_count: usize,
+        filtered_count: usize,
+        filter_settings: &str,
+        search_radius_m: f64,
+        reports: &[FilterReport],
+    ) -> Result<()> {
+        dml::matching::insert_match_metadata(
+            self,
+            traj_id,
+            point_count,
+            filtered_count,
+            filter_settings,
+            search_radius_m,
+            reports,
+        )
+    }
+
+    pub fn get_search_radius(&self, traj_id: i64) -> Result<Option<f64>> {
+        dml::matching::get_search_radius(self, traj_id)
+    }
+
+    pub fn create_od_table(&self) -> Result<usize> {
+        ddl::od::create_table(self)
+    }
+
+    pub fn create_od_indexes(&self) -> Result<usize> {
+        ddl::od::create_indexes(self)
+    }
+
+    pub fn get_od_trips(&self) -> Result<Vec<OdTrip>> {
+        dml::od::get_od_trips(self)
+    }
+
+    pub fn insert_od_cells(&self, cells: &[OdCell]) -> Result<()> {
+        dml::od::insert_od_cells(self, cells)
+    }
+
+    pub fn get_trajectory_signals(&self, trajectory_id: i64) -> Result<Vec<TrajectorySignal>> {
+        dml::signal::get_trajectory_signals(self, trajectory_id)
+    }
+
+    pub fn get_energy_signals(&self, trajectory_id: i64) -> Result<Vec<EnergySignal>> {
+        dml::signal::get_energy_signals(self, trajectory_id)
+    }
+
+    pub fn create_stop_table(&self) -> Result<usize> {
+        ddl::stop::create_table(self)
+    }
+
+    pub fn create_stop_indexes(&self) -> Result<usize> {
+        ddl::stop::create_indexes(self)
+    }
+
+    pub fn insert_stop_events(&self, stops: &[StopEvent]) -> Result<()> {
+        dml::stop::insert_stop_events(self, stops)
+    }
+
+    pub fn update_trajectory_stops(&self, stops: &[TrajectoryStops]) -> Result<()> {
+        dml::trajectory::update_trajectory_stops(self, stops)
+    }
+
+    pub fn create_charge_table(&self) -> Result<usize> {
+        ddl::charge::create_table(self)
+    }
+
+    pub fn create_charge_indexes(&self) -> Result<usize> {
+        ddl::charge::create_indexes(self)
+    }
+
+    pub fn get_trip_socs(&self) -> Result<Vec<TripSoc>> {
+        dml::charge::get_trip_socs(self)
+    }
+
+    pub fn insert_charge_events(&self, charges: &[ChargeEvent]) -> Result<()> {
+        dml::charge::insert_charge_events(self, charges)
+    }
+
+    pub fn update_trajectory_regen(&self, regens: &[TrajectoryRegen]) -> Result<()> {
+        dml::trajectory::update_trajectory_regen(self, regens)
+    }
+
+    pub fn create_event_table(&self, kind: EventKind) -> Result<usize> {
+        ddl::event::create_table(self, kind)
+    }
+
+    pub fn create_event_indexes(&self, kind: EventKind) -> Result<usize> {
+        ddl::event::create_indexes(self, kind)
+    }
+
+    pub fn get_event_signals(&self, trajectory_id: i64) -> Result<Vec<EventSignal>> {
+        dml::event::get_event_signals(self, trajectory_id)
+    }
+
+    pub fn insert_passage_events(&self, kind: EventKind, events: &[PassageEvent]) -> Result<()> {
+        dml::event::insert_passage_events(self, kind, events)
+    }
+
+    pub fn create_match_quality_table(&self) -> Result<usize> {
+        ddl::matching::create_quality_table(self)
+    }
+
+    pub fn get_trajectory_lengths(&self) -> Result<Vec<(i64, Option<f64>)>> {
+        dml::trajectory::get_trajectory_lengths(self)
+    }
+
+    pub fn get_matched_shape(&self, trajectory_id: i64) -> Result<Vec<Node>> {
+        dml::node::get_matched_shape(self, trajectory_id)
+    }
+
+    pub fn insert_match_quality(&self, rows: &[MatchQuality]) -> Result<()> {
+        dml::matching::insert_match_quality(self, rows)
+    }
+
+    pub fn flag_match_outliers(
+        &self,
+        max_mean_distance_m: f64,
+        max_beyond_share: f64,
+        min_length_ratio: f64,
+        max_length_ratio: f64,
+    ) -> Result<Vec<i64>> {
+        dml::matching::flag_match_outliers(
+            self,
+            max_mean_distance_m,
+            max_beyond_share,
+            min_length_ratio,
+            max_length_ratio,
+        )
+    }
+
+    pub fn create_turn_table(&self) -> Result<usize> {
+        ddl::turn::create_table(self)
+    }
+
+    pub fn create_turn_indexes(&self) -> Result<usize> {
+        ddl::turn::create_indexes(self)
+    }
+
+    pub fn get_edge_sequences(&self) -> Result<Vec<(i64, i64)>> {
+        dml::turn::get_edge_sequences(self)
+    }
+
+    pub fn insert_turns(&self, turns: &[Turn]) -> Result<()> {
+        dml::turn::insert_turns(self, turns)
+    }
+
+    pub fn create_osm_tables(&self) -> Result<usize> {
+        ddl::osm::create_tables(self)
+    }
+
+    pub fn create_osm_indexes(&self) -> Result<usize> {
+        ddl::osm::create_indexes(self)
+    }
+
+    pub fn create_edge_osm_table(&self) -> Result<usize> {
+        ddl::osm::create_edge_osm_table(self)
+    }
+
+    pub fn get_signal_bounds(&self) -> Result<Option<(f64, f64, f64, f64)>> {
+        dml::osm::get_signal_bounds(self)
+    }
+
+    pub fn insert_osm_network(&self, nodes: &[Node], ways: &[OsmWay]) -> Result<()> {
+        dml::osm::insert_osm_network(self, nodes, ways)
+    }
+
+    pub fn get_osm_nodes(&self) -> Result<Vec<Node>> {
+        dml::osm::get_osm_nodes(self)
+    }
+
+    pub fn get_osm_ways(&self) -> Result<Vec<OsmWay>> {
+        dml::osm::get_osm_ways(self)
+    }
+
+    pub fn insert_edge_osm(&self, rows: &[(i64, i64)]) -> Result<()> {
+        dml::osm::insert_edge_osm(self, rows)
+    }
+
+    pub fn create_kinematics_table(&self) -> Result<usize> {
+        ddl::kinematics::create_table(self)
+    }
+
+    pub fn create_kinematics_indexes(&self) -> Result<usize> {
+        ddl::kinematics::create_indexes(self)
+    }
+
+    pub fn insert_signal_kinematics(&self, rows: &[SignalKinematics]) -> Result<()> {
+        dml::kinematics::insert_signal_kinematics(self, rows)
+    }
+
+    pub fn create_h3_aggregate_table(&self, resolution: u8, metrics: &[H3Metric]) -> Result<usize> {
+        ddl::aggregate::create_h3_table(self, resolution, metrics)
+    }
+
+    pub fn get_h3_signal_stats(&self) -> Result<Vec<H3SignalStats>> {
+        dml::aggregate::get_h3_signal_stats(self)
+    }
+
+    pub fn insert_h3_aggregates(
+        &self,
+        resolution: u8,
+        metrics: &[H3Metric],
+        cells: &[H3Aggregate],
+    ) -> Result<()> {
+        dml::aggregate::insert_h3_aggregates(self, resolution, metrics, cells)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::vehicle::{
+        DriveWheels, EngineSpec, Transmission, VehicleClass, VehicleType, WeightUnit,
+    };
     use std::fs;
 
     #[test]
@@ -147,12 +418,19 @@ mod tests {
 
         let vehicle = Vehicle {
             vehicle_id: 1,
-            vehicle_type: Some("ICE".to_string()),
-            vehicle_class: Some("Sedan".to_string()),
+            vehicle_type: Some(VehicleType::Ice),
+            vehicle_class: Some(VehicleClass::Unknown("Coupe".to_string())),
             engine: Some("V6".to_string()),
-            transmission: Some("Auto".to_string()),
-            drive_wheels: Some("FWD".to_string()),
+            engine_spec: Some(EngineSpec {
+                displacement_l: None,
+                cylinders: Some(6),
+                turbo: false,
+            }),
+            transmission: Some(Transmission::Automatic),
+            drive_wheels: Some(DriveWheels::Fwd),
             weight: Some(1500),
+            weight_kg: Some(1500.0),
+            weight_unit: Some(WeightUnit::Kilogram),
         };
         db.insert_vehicles(vec![vehicle]).unwrap();
 
@@ -163,6 +441,26 @@ mod tests {
             .unwrap();
         assert_eq!(count, 1);
 
+        // Unrecognized categories are added to their lookup table
+        let known: i64 = conn
+            .query_row(
+                "SELECT known FROM ref_vehicle_class WHERE code = 'Coupe'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(known, 0);
+
+        // The foreign keys reject codes missing from the lookup tables
+        conn.execute("PRAGMA foreign_keys = ON;", ()).unwrap();
+        assert!(
+            conn.execute(
+                "INSERT INTO vehicle (vehicle_id, vehicle_type) VALUES (2, 'Ice')",
+                ()
+            )
+            .is_err()
+        );
+
         fs::remove_file(db_path).unwrap();
     }
 
@@ -237,4 +535,275 @@ mod tests {
 
         fs::remove_file(db_path).unwrap();
     }
+
+    #[test]
+    fn test_matched_sequence_with_loop() {
+        let db_path = "test_sequence.db";
+        if std::path::Path::new(db_path).exists() {
+            fs::remove_file(db_path).unwrap();
+        }
+        let db = EveDb::new(db_path);
+
+        db.create_node_table().unwrap();
+        db.create_edge_table().unwrap();
+        db.create_trajectory_table().unwrap();
+        db.create_traj_node_table().unwrap();
+        db.create_traj_edge_table().unwrap();
+
+        let node = |latitude: f64| {
+            Node::builder()
+                .id(0)
+                .latitude(latitude)
+                .longitude(-83.7)
+                .altitude(0.0)
+                .h3_12(0)
+                .build()
+        };
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO trajectory (traj_id, vehicle_id, trip_id) VALUES (1, 1, 1), (2, 1, 2)",
+            [],
+        )
+        .unwrap();
+
+        let mut nodes = vec![node(42.0), node(42.001)];
+        db.insert_nodes(1, &mut nodes).unwrap();
+
+        // Back and forth over the same street, then once more
+        let (a, b) = (nodes[0], nodes[1]);
+        let mut path = vec![a, b, a, b];
+        db.insert_nodes(2, &mut path).unwrap();
+        db.insert_edges(2, &path, &[Some(10.0), None, Some(0.0)]).unwrap();
+
+        let shape: Vec<i64> = db.get_matched_shape(2).unwrap().iter().map(|n| n.id).collect();
+        assert_eq!(shape, vec![a.id, b.id, a.id, b.id]);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT e.node_ini, e.node_end, te.shape_index, te.distance_m \
+                 FROM traj_edge te JOIN edge e ON te.edge_id = e.edge_id \
+                 WHERE te.traj_id = 2 ORDER BY te.seq",
+            )
+            .unwrap();
+        let edges: Vec<(i64, i64, i64, f64)> = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let length_m = a.distance_to(&b);
+        assert_eq!(edges.len(), 3);
+        assert_eq!((edges[2].0, edges[2].1, edges[2].2), (a.id, b.id, 2));
+        assert!((edges[2].3 - 2.0 * length_m).abs() < 1e-9);
+
+        // The repeated movement reuses its edge
+        let edge_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM edge", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(edge_count, 2);
+
+        let last_m: f64 = conn
+            .query_row(
+                "SELECT distance_m FROM traj_node WHERE traj_id = 2 AND seq = 3",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!((last_m - 3.0 * length_m).abs() < 1e-9);
+
+        // Only the timed traversal of a -> b counts towards its speed
+        let network = db.get_network_edges().unwrap();
+        let forward = network.iter().find(|e| e.node_ini == a.id).unwrap();
+        let backward = network.iter().find(|e| e.node_ini == b.id).unwrap();
+        assert_eq!(forward.traversals, 2);
+        assert!((forward.mean_speed_kmh.unwrap() - length_m / 10.0 * 3.6).abs() < 1e-9);
+        assert_eq!(backward.mean_speed_kmh, None);
+
+        fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn test_repair_renumbers_paths() {
+        let db_path = "test_repair.db";
+        if std::path::Path::new(db_path).exists() {
+            fs::remove_file(db_path).unwrap();
+        }
+        let db = EveDb::new(db_path);
+
+        db.create_node_table().unwrap();
+        db.create_edge_table().unwrap();
+        db.create_trajectory_table().unwrap();
+        db.create_traj_node_table().unwrap();
+        db.create_traj_edge_table().unwrap();
+
+        let node = |latitude: f64| {
+            Node::builder()
+                .id(0)
+                .latitude(latitude)
+                .longitude(-83.7)
+                .altitude(0.0)
+                .h3_12(0)
+                .build()
+        };
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO trajectory (traj_id, vehicle_id, trip_id) VALUES (1, 1, 1)",
+            [],
+        )
+        .unwrap();
+
+        // c is half a metre past b and merges into it
+        let mut path = vec![node(42.0), node(42.001), node(42.0010045), node(42.002)];
+        db.insert_nodes(1, &mut path).unwrap();
+        db.insert_edges(1, &path, &[]).unwrap();
+        let (a, b, c, d) = (path[0], path[1], path[2], path[3]);
+
+        let network = db.get_network_edges().unwrap();
+        let cd = network.iter().find(|e| e.node_ini == c.id).unwrap();
+        let bd = NetworkEdge {
+            node_ini: b.id,
+            length_m: b.distance_to(&d),
+            ..cd.clone()
+        };
+        let bc = network.iter().find(|e| e.node_end == c.id).unwrap();
+        let bb = NetworkEdge {
+            node_end: b.id,
+            length_m: 0.0,
+            ..bc.clone()
+        };
+        db.repair_network(&[(c.id, b.id)], &[bb, bd]).unwrap();
+
+        let nodes: Vec<(i64, i64, f64)> = conn
+            .prepare("SELECT node_id, seq, distance_m FROM traj_node WHERE traj_id = 1 ORDER BY seq")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let ids: Vec<(i64, i64)> = nodes.iter().map(|n| (n.0, n.1)).collect();
+        assert_eq!(ids, vec![(a.id, 0), (b.id, 1), (d.id, 2)]);
+        let expected_m = a.distance_to(&b) + b.distance_to(&d);
+        assert!((nodes[2].2 - expected_m).abs() < 1e-9);
+
+        let edges: Vec<(i64, i64, f64)> = conn
+            .prepare(
+                "SELECT e.node_ini, te.seq, te.distance_m \
+                 FROM traj_edge te JOIN edge e ON te.edge_id = e.edge_id \
+                 WHERE te.traj_id = 1 ORDER BY te.seq",
+            )
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(edges.len(), 2);
+        assert_eq!((edges[1].0, edges[1].1), (b.id, 1));
+        assert!((edges[1].2 - nodes[1].2).abs() < 1e-9);
+
+        fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn test_repair_drops_stale_turns() {
+        let db_path = "test_repair_turns.db";
+        if std::path::Path::new(db_path).exists() {
+            fs::remove_file(db_path).unwrap();
+        }
+        let db = EveDb::new(db_path);
+
+        db.create_node_table().unwrap();
+        db.create_edge_table().unwrap();
+        db.create_trajectory_table().unwrap();
+        db.create_traj_node_table().unwrap();
+        db.create_traj_edge_table().unwrap();
+        db.create_turn_table().unwrap();
+        db.create_stop_table().unwrap();
+
+        let node = |latitude: f64| {
+            Node::builder()
+                .id(0)
+                .latitude(latitude)
+                .longitude(-83.7)
+                .altitude(0.0)
+                .h3_12(0)
+                .build()
+        };
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO trajectory (traj_id, vehicle_id, trip_id) VALUES (1, 1, 1)",
+            [],
+        )
+        .unwrap();
+
+        // c is half a metre past b and merges into it, removing the edge b -> c
+        let mut path = vec![node(42.0), node(42.001), node(42.0010045)];
+        db.insert_nodes(1, &mut path).unwrap();
+        db.insert_edges(1, &path, &[]).unwrap();
+        let (b, c) = (path[1], path[2]);
+        let network = db.get_network_edges().unwrap();
+        let ab = network.iter().find(|e| e.node_end == b.id).unwrap();
+        let bc = network.iter().find(|e| e.node_ini == b.id).unwrap();
+        conn.execute(
+            "INSERT INTO turn VALUES (?1, ?2, ?3, 0.0, 'straight', 1)",
+            rusqlite::params!(ab.id, b.id, bc.id),
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO stop_event (traj_id, latitude, longitude, h3_12, time_stamp_ini, \
+             time_stamp_end, duration_s, node_id) VALUES (1, 42.0010045, -83.7, 0, 0, 5000, 5.0, ?1)",
+            rusqlite::params!(c.id),
+        )
+        .unwrap();
+
+        let bb = NetworkEdge {
+            node_end: b.id,
+            length_m: 0.0,
+            ..bc.clone()
+        };
+        let dropped = db.repair_network(&[(c.id, b.id)], &[bb]).unwrap();
+        assert_eq!(dropped, vec!["turn"]);
+        assert!(!db.table_exists("turn").unwrap());
+
+        let stop_node: i64 = conn
+            .query_row("SELECT node_id FROM stop_event", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(stop_node, b.id);
+
+        fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn test_osm_network_round_trip() {
+        let db_path = "test_osm.db";
+        if std::path::Path::new(db_path).exists() {
+            fs::remove_file(db_path).unwrap();
+        }
+        let db = EveDb::new(db_path);
+        db.create_osm_tables().unwrap();
+        db.create_osm_indexes().unwrap();
+
+        let node = |id: i64, latitude: f64| Node {
+            id,
+            latitude,
+            longitude: -83.7,
+            altitude: 0.0,
+            h3_12: 0,
+        };
+        let way = |id: i64, nodes: Vec<i64>| OsmWay {
+            id,
+            highway: "residential".to_string(),
+            name: Some("Main Street".to_string()),
+            oneway: id == 20,
+            max_speed_kmh: None,
+            length_m: 111.2,
+            nodes,
+        };
+        let nodes = vec![node(1, 42.0), node(2, 42.001), node(3, 42.002)];
+        let ways = vec![way(10, vec![1, 2]), way(20, vec![3, 2, 1])];
+        db.insert_osm_network(&nodes, &ways).unwrap();
+
+        assert_eq!(db.get_osm_nodes().unwrap(), nodes);
+        assert_eq!(db.get_osm_ways().unwrap(), ways);
+
+        fs::remove_file(db_path).unwrap();
+    }
 }
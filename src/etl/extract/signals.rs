@@ -1,6 +1,7 @@
 use crate::cli::Cli;
 use crate::db::evedb::EveDb;
 use crate::models::signal::CsvSignal;
+use crate::tools::TimeBase;
 use anyhow::Result;
 use std::fs;
 use std::io::Read;
@@ -27,13 +28,23 @@ fn get_signal_data(cli: &Cli, data_filename: &str) -> Result<String> {
     Ok(csv)
 }
 
-pub fn insert_signals(cli: &crate::cli::Cli, data_file: &str) -> Result<usize> {
+pub fn insert_signals(
+    cli: &crate::cli::Cli,
+    data_file: &str,
+    time_base: &TimeBase,
+    local_time: bool,
+) -> Result<usize> {
     let csv = get_signal_data(cli, data_file)?;
     let db = EveDb::new(&cli.db_path);
-    insert_signals_from_csv(&db, &csv)
+    insert_signals_from_csv(&db, &csv, time_base, local_time)
 }
 
-pub fn insert_signals_from_csv(db: &EveDb, csv_content: &str) -> Result<usize> {
+pub fn insert_signals_from_csv(
+    db: &EveDb,
+    csv_content: &str,
+    time_base: &TimeBase,
+    local_time: bool,
+) -> Result<usize> {
     // Replace "nan" and ';' with null
     let mut csv = csv_content.replace("nan", "");
     csv = csv.replace(";", "");
@@ -42,7 +53,7 @@ pub fn insert_signals_from_csv(db: &EveDb, csv_content: &str) -> Result<usize> {
 
     let iterator = reader.deserialize::<CsvSignal>();
 
-    db.insert_signals(iterator)
+    db.insert_signals(iterator, time_base, local_time)
 }
 
 #[cfg(test)]
@@ -59,13 +70,43 @@ mod tests {
         }
         let db = EveDb::new(db_path);
         db.create_signal_table().unwrap();
+        db.create_focus_point_table().unwrap();
 
         let csv_content = "DayNum,VehId,Trip,Timestamp(ms),Latitude[deg],Longitude[deg],Vehicle Speed[km/h],MAF[g/sec],Engine RPM[RPM],Absolute Load[%],OAT[DegC],Fuel Rate[L/hr],Air Conditioning Power[kW],Air Conditioning Power[Watts],Heater Power[Watts],HV Battery Current[A],HV Battery SOC[%],HV Battery Voltage[V],Short Term Fuel Trim Bank 1[%],Short Term Fuel Trim Bank 2[%],Long Term Fuel Trim Bank 1[%],Long Term Fuel Trim Bank 2[%],Elevation Raw[m],Elevation Smoothed[m],Gradient,Energy Consumption[,Matchted Latitude[deg],Matched Longitude[deg],Match Type,Class of Speed Limit,Speed Limit[km/h],Speed Limit Direction[km/h],Intersection,Bus Stops,Focus Points
-1,10,100,1000,42.1,-83.1,60.0,nan,2000,50,20,1.5,0.5,500,0,10,80,350,0,0,0,0,200,200,0,0.1,42.1001,-83.1001,1,1,50,50,0,0,
+1.5,10,100,1000,42.1,-83.1,60.0,nan,2000,50,20,1.5,0.5,500,0,10,80,350,0,0,0,0,200,200,0,0.1,42.1001,-83.1001,1,1,\"[40, 56]\",50,0,0,\"Traffic Signals, Stop, stop\"
 ";
-        let result = insert_signals_from_csv(&db, csv_content).unwrap();
+        let result = insert_signals_from_csv(&db, csv_content, &TimeBase::default(), true).unwrap();
         assert_eq!(result, 1);
 
+        // Day 1.5 is noon of the base date, one second into the trip
+        let conn = db.connect().unwrap();
+        let (day_num, ts_utc, ts_local): (f64, String, String) = conn
+            .query_row("SELECT day_num, ts_utc, ts_local FROM signal", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(day_num, 1.5);
+        assert_eq!(ts_utc, "2017-11-01T16:00:01.000Z");
+        assert_eq!(ts_local, "2017-11-01T12:00:01.000-04:00");
+
+        let (speed_limit, direction, backward): (f64, String, f64) = conn
+            .query_row(
+                "SELECT speed_limit_kmh, speed_limit_dir, speed_limit_bwd_kmh FROM signal",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((speed_limit, direction.as_str(), backward), (40.0, "split", 56.0));
+
+        let point_types: Vec<String> = conn
+            .prepare("SELECT point_type FROM focus_point ORDER BY point_type")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(point_types, vec!["stop", "traffic_signals"]);
+
         fs::remove_file(db_path).unwrap();
     }
 }
@@ -1,4 +1,7 @@
-use crate::models::vehicle::Vehicle;
+use crate::models::vehicle::{
+    DriveWheels, EngineSpec, Transmission, Vehicle, VehicleCategory, VehicleClass, VehicleType,
+    WeightUnit,
+};
 use anyhow::Result;
 use calamine::{Data, DataType, Range, Reader, Xlsx, open_workbook};
 
@@ -16,20 +19,100 @@ fn no_data_str(data: &Option<String>) -> Option<String> {
     }
 }
 
+fn no_data_category<C: VehicleCategory>(data: &Option<String>) -> Option<C> {
+    no_data_str(data).map(|text| C::parse(&text))
+}
+
+/// Unit of weights given without one. VED reports generalized weights in pounds.
+const DEFAULT_WEIGHT_UNIT: WeightUnit = WeightUnit::Pound;
+
+/// Parses engine descriptions such as "4-GAS 2.0L", "6-GAS/ELECTRIC 3.5L" or "V6 2998CC
+/// TURBO". Returns `None` when neither the cylinders nor the displacement are found.
+fn parse_engine(engine: &str) -> Option<EngineSpec> {
+    let upper = engine.to_uppercase();
+    let tokens: Vec<&str> = upper.split_whitespace().collect();
+    let mut spec = EngineSpec {
+        turbo: upper.contains("TURBO") || upper.contains("T/C") || tokens.contains(&"TC"),
+        ..EngineSpec::default()
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).copied();
+        // A bare number followed by "L" is a displacement, not a cylinder count
+        if spec.cylinders.is_none() && next != Some("L") {
+            let count = match token.split_once('-') {
+                Some((count, _)) => count,
+                None => token.trim_start_matches(['V', 'I', 'L', 'H']),
+            };
+            spec.cylinders = count.parse::<i64>().ok().filter(|c| (1..=16).contains(c));
+            if spec.cylinders.is_some() {
+                continue;
+            }
+        }
+        if spec.displacement_l.is_none() {
+            spec.displacement_l = if let Some(litres) = token.strip_suffix('L') {
+                litres.parse::<f64>().ok()
+            } else if let Some(cc) = token.strip_suffix("CC") {
+                cc.parse::<f64>().ok().map(|cc| cc / 1000.0)
+            } else if next == Some("L") {
+                token.parse::<f64>().ok()
+            } else {
+                None
+            };
+        }
+    }
+
+    if spec.cylinders.is_none() && spec.displacement_l.is_none() {
+        None
+    } else {
+        Some(spec)
+    }
+}
+
+/// Parses a weight with an optional "kg" or "lb" unit into kilograms and the source unit.
+fn parse_weight(weight: &str) -> Option<(f64, WeightUnit)> {
+    let lower = weight.trim().to_lowercase();
+    let (value, unit) = if let Some(value) = lower.strip_suffix("kg") {
+        (value, WeightUnit::Kilogram)
+    } else if let Some(value) = lower
+        .strip_suffix("lbs")
+        .or_else(|| lower.strip_suffix("lb"))
+    {
+        (value, WeightUnit::Pound)
+    } else {
+        (lower.as_str(), DEFAULT_WEIGHT_UNIT)
+    };
+    let value = value.trim().replace(',', "").parse::<f64>().ok()?;
+    Some((unit.to_kg(value), unit))
+}
+
 fn read_vehicles_from_range(range: Range<Data>) -> Result<Vec<Vehicle>> {
     let mut vehicles: Vec<Vehicle> = Vec::new();
 
     for row in range.rows().skip(1) {
+        let engine = no_data_str(&row[3].as_string());
+        let engine_spec = engine.as_deref().and_then(parse_engine);
+        if let (Some(engine), None) = (&engine, &engine_spec) {
+            eprintln!("Failed to parse engine '{}' of vehicle {}", engine, row[0]);
+        }
+
+        let weight_text = no_data_str(&row[6].as_string());
+        let weight_kg = weight_text.as_deref().and_then(parse_weight);
+        if let (Some(weight), None) = (&weight_text, &weight_kg) {
+            eprintln!("Failed to parse weight '{}' of vehicle {}", weight, row[0]);
+        }
+
         let vehicle: Vehicle = Vehicle {
             vehicle_id: row[0].as_i64().ok_or(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Vehicle ID cannot be parsed",
             ))?,
-            vehicle_type: no_data_str(&row[1].as_string()),
-            vehicle_class: no_data_str(&row[2].as_string()),
-            engine: no_data_str(&row[3].as_string()),
-            transmission: no_data_str(&row[4].as_string()),
-            drive_wheels: no_data_str(&row[5].as_string()),
+            vehicle_type: no_data_category::<VehicleType>(&row[1].as_string()),
+            vehicle_class: no_data_category::<VehicleClass>(&row[2].as_string()),
+            engine,
+            engine_spec,
+            transmission: no_data_category::<Transmission>(&row[4].as_string()),
+            drive_wheels: no_data_category::<DriveWheels>(&row[5].as_string()),
             weight: if let Some(s) = row[6].as_string() {
                 if s == "NO DATA" {
                     None
@@ -39,6 +122,8 @@ fn read_vehicles_from_range(range: Range<Data>) -> Result<Vec<Vehicle>> {
             } else {
                 row[6].as_i64()
             },
+            weight_kg: weight_kg.map(|(kg, _)| kg),
+            weight_unit: weight_kg.map(|(_, unit)| unit),
         };
         vehicles.push(vehicle);
     }
@@ -76,6 +161,62 @@ mod tests {
         assert_eq!(no_data_str(&None), None);
     }
 
+    #[test]
+    fn test_parse_engine() {
+        assert_eq!(
+            parse_engine("4-GAS/ELECTRIC 1.8L"),
+            Some(EngineSpec {
+                displacement_l: Some(1.8),
+                cylinders: Some(4),
+                turbo: false,
+            })
+        );
+        assert_eq!(
+            parse_engine("4-GAS 2.0L T/C"),
+            Some(EngineSpec {
+                displacement_l: Some(2.0),
+                cylinders: Some(4),
+                turbo: true,
+            })
+        );
+        assert_eq!(
+            parse_engine("V6 2998cc Turbo"),
+            Some(EngineSpec {
+                displacement_l: Some(2.998),
+                cylinders: Some(6),
+                turbo: true,
+            })
+        );
+        assert_eq!(parse_engine("3.5 L").unwrap().displacement_l, Some(3.5));
+        assert_eq!(
+            parse_engine("2 L"),
+            Some(EngineSpec {
+                displacement_l: Some(2.0),
+                cylinders: None,
+                turbo: false,
+            })
+        );
+        assert_eq!(
+            parse_engine("4-GAS 2 L"),
+            Some(EngineSpec {
+                displacement_l: Some(2.0),
+                cylinders: Some(4),
+                turbo: false,
+            })
+        );
+        assert_eq!(parse_engine("ELECTRIC"), None);
+    }
+
+    #[test]
+    fn test_parse_weight() {
+        assert_eq!(parse_weight("1500 kg"), Some((1500.0, WeightUnit::Kilogram)));
+        let (kg, unit) = parse_weight("3,500 lbs").unwrap();
+        assert!((kg - 1587.57).abs() < 0.01);
+        assert_eq!(unit, WeightUnit::Pound);
+        assert_eq!(parse_weight("4000").unwrap().1, WeightUnit::Pound);
+        assert_eq!(parse_weight("heavy"), None);
+    }
+
     #[test]
     fn test_read_vehicles_from_range() {
         let mut range = Range::new((0, 0), (1, 6));
@@ -98,9 +239,14 @@ mod tests {
         let result = read_vehicles_from_range(range).unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].vehicle_id, 101);
-        assert_eq!(result[0].vehicle_type, Some("ICE".to_string()));
+        assert_eq!(result[0].vehicle_type, Some(VehicleType::Ice));
         assert_eq!(result[0].vehicle_class, None);
+        assert_eq!(result[0].transmission, Some(Transmission::Automatic));
+        assert_eq!(result[0].drive_wheels, Some(DriveWheels::Fwd));
         assert_eq!(result[0].engine, Some("V8".to_string()));
+        assert_eq!(result[0].engine_spec.as_ref().unwrap().cylinders, Some(8));
         assert_eq!(result[0].weight, Some(1500));
+        assert_eq!(result[0].weight_unit, Some(WeightUnit::Pound));
+        assert!((result[0].weight_kg.unwrap() - 680.39).abs() < 0.01);
     }
 }
@@ -0,0 +1,274 @@
+use crate::models::trajectory::WayPoint;
+use geo::{Distance, Haversine, Point};
+use serde_json::{Map, Value, json};
+
+/// A pre-matching filter that removes way points before they are sent to the map-matcher
+pub trait WayPointFilter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn settings(&self) -> Value;
+
+    fn filter(&self, points: Vec<WayPoint>) -> Vec<WayPoint>;
+}
+
+fn distance_m(a: &WayPoint, b: &WayPoint) -> f64 {
+    Haversine.distance(
+        Point::new(a.longitude, a.latitude),
+        Point::new(b.longitude, b.latitude),
+    )
+}
+
+/// Removes points implying a speed above `max_speed_kmh` both from the last kept point and
+/// to the next point, so that a single outlier cannot make the rest of the trace look
+/// implausible. The first and last points, which have only one neighbour, are checked against
+/// the two nearest points on their side instead.
+/// Way point times have a one-second resolution, so shorter intervals count as one second.
+pub struct SpeedFilter {
+    pub max_speed_kmh: f64,
+}
+
+impl SpeedFilter {
+    fn implausible(&self, a: &WayPoint, b: &WayPoint) -> bool {
+        let dt_s = (b.time - a.time).abs().max(1) as f64;
+        distance_m(a, b) / dt_s * 3.6 > self.max_speed_kmh
+    }
+}
+
+impl WayPointFilter for SpeedFilter {
+    fn name(&self) -> &'static str {
+        "speed"
+    }
+
+    fn settings(&self) -> Value {
+        json!({ "max_speed_kmh": self.max_speed_kmh })
+    }
+
+    fn filter(&self, points: Vec<WayPoint>) -> Vec<WayPoint> {
+        let mut kept: Vec<WayPoint> = Vec::with_capacity(points.len());
+        for (i, point) in points.iter().enumerate() {
+            let before = kept.last().or_else(|| points.get(i + 2));
+            let after = points
+                .get(i + 1)
+                .or_else(|| kept.len().checked_sub(2).map(|j| &kept[j]));
+            if let (Some(before), Some(after)) = (before, after)
+                && self.implausible(before, point)
+                && self.implausible(point, after)
+            {
+                continue;
+            }
+            kept.push(point.clone());
+        }
+        kept
+    }
+}
+
+/// Collapses consecutive points with the same coordinates, up to `precision` decimal places
+pub struct DuplicateFilter {
+    pub precision: u32,
+}
+
+impl WayPointFilter for DuplicateFilter {
+    fn name(&self) -> &'static str {
+        "duplicate"
+    }
+
+    fn settings(&self) -> Value {
+        json!({ "precision": self.precision })
+    }
+
+    fn filter(&self, points: Vec<WayPoint>) -> Vec<WayPoint> {
+        let scale = 10f64.powi(self.precision as i32);
+        let key = |p: &WayPoint| {
+            (
+                (p.latitude * scale).round() as i64,
+                (p.longitude * scale).round() as i64,
+            )
+        };
+        let mut kept: Vec<WayPoint> = Vec::with_capacity(points.len());
+        for point in points {
+            if kept.last().is_some_and(|last| key(last) == key(&point)) {
+                continue;
+            }
+            kept.push(point);
+        }
+        kept
+    }
+}
+
+/// Thins the trace so that consecutive points are at least `min_distance_m` apart.
+/// The last point is always kept to preserve the trip destination.
+pub struct DistanceFilter {
+    pub min_distance_m: f64,
+}
+
+impl WayPointFilter for DistanceFilter {
+    fn name(&self) -> &'static str {
+        "distance"
+    }
+
+    fn settings(&self) -> Value {
+        json!({ "min_distance_m": self.min_distance_m })
+    }
+
+    fn filter(&self, points: Vec<WayPoint>) -> Vec<WayPoint> {
+        let count = points.len();
+        let mut kept: Vec<WayPoint> = Vec::with_capacity(count);
+        for (i, point) in points.into_iter().enumerate() {
+            let is_last = i + 1 == count;
+            if let Some(last) = kept.last()
+                && !is_last
+                && distance_m(last, &point) < self.min_distance_m
+            {
+                continue;
+            }
+            kept.push(point);
+        }
+        kept
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterReport {
+    pub filter: &'static str,
+    pub removed: usize,
+}
+
+#[derive(Default)]
+pub struct FilterPipeline {
+    filters: Vec<Box<dyn WayPointFilter>>,
+}
+
+impl FilterPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, filter: impl WayPointFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn settings(&self) -> Value {
+        let settings: Map<String, Value> = self
+            .filters
+            .iter()
+            .map(|f| (f.name().to_string(), f.settings()))
+            .collect();
+        Value::Object(settings)
+    }
+
+    /// Runs the filters in order, reporting how many points each one removed
+    pub fn apply(&self, points: Vec<WayPoint>) -> (Vec<WayPoint>, Vec<FilterReport>) {
+        let mut points = points;
+        let mut reports: Vec<FilterReport> = Vec::with_capacity(self.filters.len());
+        for filter in &self.filters {
+            let before = points.len();
+            points = filter.filter(points);
+            reports.push(FilterReport {
+                filter: filter.name(),
+                removed: before - points.len(),
+            });
+        }
+        (points, reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wp(time: i64, latitude: f64, longitude: f64) -> WayPoint {
+        WayPoint {
+            time,
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn test_speed_filter_removes_jumps() {
+        // ~111 m per 0.001 deg of latitude
+        let points = vec![
+            wp(0, 42.000, -83.0),
+            wp(10, 42.001, -83.0),
+            wp(11, 42.100, -83.0), // ~11 km in 1 s
+            wp(20, 42.002, -83.0),
+        ];
+        let kept = SpeedFilter { max_speed_kmh: 200.0 }.filter(points);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[2].time, 20);
+    }
+
+    #[test]
+    fn test_speed_filter_first_and_last_outliers() {
+        let points = vec![
+            wp(0, 42.100, -83.0), // ~11 km from the rest
+            wp(10, 42.000, -83.0),
+            wp(20, 42.001, -83.0),
+            wp(30, 42.002, -83.0),
+            wp(31, 42.200, -83.0), // ~22 km in 1 s
+        ];
+        let kept = SpeedFilter { max_speed_kmh: 200.0 }.filter(points);
+        let times: Vec<i64> = kept.iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![10, 20, 30]);
+
+        // An outlier second point does not take the first one with it
+        let points = vec![
+            wp(0, 42.000, -83.0),
+            wp(1, 42.100, -83.0),
+            wp(10, 42.001, -83.0),
+        ];
+        let kept = SpeedFilter { max_speed_kmh: 200.0 }.filter(points);
+        let times: Vec<i64> = kept.iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![0, 10]);
+    }
+
+    #[test]
+    fn test_duplicate_filter() {
+        let points = vec![
+            wp(0, 42.0, -83.0),
+            wp(1, 42.0000001, -83.0),
+            wp(2, 42.001, -83.0),
+            wp(3, 42.0, -83.0),
+        ];
+        let kept = DuplicateFilter { precision: 6 }.filter(points);
+        assert_eq!(kept.len(), 3);
+    }
+
+    #[test]
+    fn test_distance_filter_keeps_last_point() {
+        let points = vec![
+            wp(0, 42.0, -83.0),
+            wp(1, 42.00001, -83.0),
+            wp(2, 42.001, -83.0),
+            wp(3, 42.00101, -83.0),
+        ];
+        let kept = DistanceFilter { min_distance_m: 5.0 }.filter(points);
+        let times: Vec<i64> = kept.iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_pipeline_reports() {
+        let pipeline = FilterPipeline::new()
+            .with(DuplicateFilter { precision: 6 })
+            .with(SpeedFilter { max_speed_kmh: 200.0 });
+        let points = vec![
+            wp(0, 42.0, -83.0),
+            wp(1, 42.0, -83.0),
+            wp(2, 43.0, -83.0),
+            wp(10, 42.001, -83.0),
+        ];
+        let (kept, reports) = pipeline.apply(points);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(
+            reports,
+            vec![
+                FilterReport { filter: "duplicate", removed: 1 },
+                FilterReport { filter: "speed", removed: 1 },
+            ]
+        );
+        assert_eq!(pipeline.settings()["speed"]["max_speed_kmh"], json!(200.0));
+    }
+}
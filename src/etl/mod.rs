@@ -1,2 +1,3 @@
 pub mod converters;
 pub mod extract;
+pub mod filters;
@@ -0,0 +1,188 @@
+use crate::cli::GraphFormat;
+use crate::models::edge::NetworkEdge;
+use crate::models::node::Node;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+fn graphml(nodes: &[Node], edges: &[NetworkEdge]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    for (id, domain, name, kind) in [
+        ("d0", "node", "latitude", "double"),
+        ("d1", "node", "longitude", "double"),
+        ("d2", "node", "altitude", "double"),
+        ("d3", "node", "h3", "string"),
+        ("d4", "edge", "edge_id", "long"),
+        ("d5", "edge", "length_m", "double"),
+        ("d6", "edge", "bearing_deg", "double"),
+        ("d7", "edge", "traversals", "long"),
+    ] {
+        let _ = writeln!(
+            xml,
+            "  <key id=\"{id}\" for=\"{domain}\" attr.name=\"{name}\" attr.type=\"{kind}\"/>"
+        );
+    }
+    xml.push_str("  <graph id=\"road_network\" edgedefault=\"directed\">\n");
+    for node in nodes {
+        let _ = writeln!(
+            xml,
+            "    <node id=\"{}\"><data key=\"d0\">{}</data><data key=\"d1\">{}</data>\
+             <data key=\"d2\">{}</data><data key=\"d3\">{:x}</data></node>",
+            node.id, node.latitude, node.longitude, node.altitude, node.h3_12
+        );
+    }
+    for edge in edges {
+        let _ = writeln!(
+            xml,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"><data key=\"d4\">{}</data>\
+             <data key=\"d5\">{}</data><data key=\"d6\">{}</data><data key=\"d7\">{}</data></edge>",
+            edge.id,
+            edge.node_ini,
+            edge.node_end,
+            edge.id,
+            edge.length_m,
+            edge.bearing_deg,
+            edge.traversals
+        );
+    }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+/// Space-delimited edge list with a node coordinates CSV written next to it. The columns
+/// after the node pair are bare values, so NetworkX needs them named:
+/// `networkx.read_edgelist(path, create_using=networkx.DiGraph, nodetype=int,
+/// data=[("edge_id", int), ("length_m", float), ("bearing_deg", float), ("traversals", int)])`
+fn write_edge_list(path: &str, nodes: &[Node], edges: &[NetworkEdge]) -> Result<()> {
+    let mut text = String::from("# node_ini node_end edge_id length_m bearing_deg traversals\n");
+    for edge in edges {
+        let _ = writeln!(
+            text,
+            "{} {} {} {} {} {}",
+            edge.node_ini, edge.node_end, edge.id, edge.length_m, edge.bearing_deg, edge.traversals
+        );
+    }
+    std::fs::write(path, text)?;
+
+    let mut writer = csv::Writer::from_path(Path::new(path).with_extension("nodes.csv"))?;
+    writer.write_record(["node_id", "latitude", "longitude", "altitude", "h3"])?;
+    for node in nodes {
+        writer.write_record([
+            node.id.to_string(),
+            node.latitude.to_string(),
+            node.longitude.to_string(),
+            node.altitude.to_string(),
+            format!("{:x}", node.h3_12),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Compressed sparse row adjacency where row and column `i` stand for `nodes[i]`, so
+/// that `scipy.sparse.csr_matrix((length_m, indices, indptr))` rebuilds the graph
+fn csr(nodes: &[Node], edges: &[NetworkEdge]) -> Result<serde_json::Value> {
+    let index: HashMap<i64, usize> = nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+    let position = |node_id: i64| {
+        index
+            .get(&node_id)
+            .copied()
+            .ok_or_else(|| anyhow!("Edge references unknown node {}", node_id))
+    };
+
+    let mut sorted: Vec<(usize, usize, &NetworkEdge)> = edges
+        .iter()
+        .map(|e| Ok((position(e.node_ini)?, position(e.node_end)?, e)))
+        .collect::<Result<_>>()?;
+    sorted.sort_by_key(|&(row, col, e)| (row, col, e.id));
+
+    let mut indptr = vec![0usize; nodes.len() + 1];
+    for &(row, _, _) in &sorted {
+        indptr[row + 1] += 1;
+    }
+    for i in 0..nodes.len() {
+        indptr[i + 1] += indptr[i];
+    }
+
+    Ok(json!({
+        "nodes": nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+        "latitude": nodes.iter().map(|n| n.latitude).collect::<Vec<_>>(),
+        "longitude": nodes.iter().map(|n| n.longitude).collect::<Vec<_>>(),
+        "h3": nodes.iter().map(|n| format!("{:x}", n.h3_12)).collect::<Vec<_>>(),
+        "indptr": indptr,
+        "indices": sorted.iter().map(|&(_, col, _)| col).collect::<Vec<_>>(),
+        "edge_id": sorted.iter().map(|(_, _, e)| e.id).collect::<Vec<_>>(),
+        "length_m": sorted.iter().map(|(_, _, e)| e.length_m).collect::<Vec<_>>(),
+        "bearing_deg": sorted.iter().map(|(_, _, e)| e.bearing_deg).collect::<Vec<_>>(),
+        "traversals": sorted.iter().map(|(_, _, e)| e.traversals).collect::<Vec<_>>(),
+    }))
+}
+
+pub fn export_graph(
+    path: &str,
+    format: GraphFormat,
+    nodes: &[Node],
+    edges: &[NetworkEdge],
+) -> Result<()> {
+    match format {
+        GraphFormat::Graphml => std::fs::write(path, graphml(nodes, edges))?,
+        GraphFormat::Edgelist => write_edge_list(path, nodes, edges)?,
+        GraphFormat::Csr => std::fs::write(path, serde_json::to_string(&csr(nodes, edges)?)?)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64) -> Node {
+        Node {
+            id,
+            latitude: 42.0 + id as f64 * 0.001,
+            longitude: -83.7,
+            altitude: 0.0,
+            h3_12: 0x8c2ab2d2a5a5dff,
+        }
+    }
+
+    fn edge(id: i64, node_ini: i64, node_end: i64) -> NetworkEdge {
+        NetworkEdge {
+            id,
+            node_ini,
+            node_end,
+            length_m: 10.0 * id as f64,
+            bearing_deg: 90.0,
+            traversals: id,
+            mean_speed_kmh: None,
+        }
+    }
+
+    #[test]
+    fn test_graphml() {
+        let xml = graphml(&[node(1), node(2)], &[edge(5, 1, 2)]);
+
+        assert!(xml.contains("edgedefault=\"directed\""));
+        assert!(xml.contains("<node id=\"2\">"));
+        assert!(xml.contains("<data key=\"d3\">8c2ab2d2a5a5dff</data>"));
+        assert!(xml.contains("<edge id=\"e5\" source=\"1\" target=\"2\">"));
+        assert!(xml.contains("<data key=\"d7\">5</data>"));
+    }
+
+    #[test]
+    fn test_csr() {
+        let nodes = vec![node(10), node(20), node(30)];
+        let edges = vec![edge(3, 30, 10), edge(1, 10, 30), edge(2, 10, 20)];
+        let graph = csr(&nodes, &edges).unwrap();
+
+        assert_eq!(graph["indptr"], json!([0, 2, 2, 3]));
+        assert_eq!(graph["indices"], json!([1, 2, 0]));
+        assert_eq!(graph["edge_id"], json!([2, 1, 3]));
+        assert_eq!(graph["length_m"], json!([20.0, 10.0, 30.0]));
+        assert!(csr(&nodes, &[edge(4, 10, 40)]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::models::aggregate::{H3Aggregate, H3Metric};
+use anyhow::{Result, anyhow};
+use h3o::{CellIndex, LatLng};
+use rusqlite::types::Value;
+use serde_json::{Map, json};
+
+fn cell_polygon(h3: u64) -> Result<Vec<Vec<f64>>> {
+    let cell = CellIndex::try_from(h3).map_err(|e| anyhow!("Invalid H3 index {}: {}", h3, e))?;
+    let mut ring: Vec<Vec<f64>> = cell
+        .boundary()
+        .iter()
+        .map(|ll: &LatLng| vec![ll.lng(), ll.lat()])
+        .collect();
+
+    // GeoJSON rings must be closed
+    if let Some(first) = ring.first().cloned() {
+        ring.push(first);
+    }
+    Ok(ring)
+}
+
+fn metric_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Integer(v) => json!(v),
+        Value::Real(v) => json!(v),
+        _ => serde_json::Value::Null,
+    }
+}
+
+pub fn h3_geojson(metrics: &[H3Metric], cells: &[H3Aggregate]) -> Result<serde_json::Value> {
+    let mut features = Vec::with_capacity(cells.len());
+    for cell in cells {
+        let mut properties = Map::new();
+        properties.insert("h3".to_string(), json!(format!("{:x}", cell.h3)));
+        for metric in metrics {
+            properties.insert(
+                metric.column_name().to_string(),
+                metric_json(cell.metric_value(*metric)),
+            );
+        }
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [cell_polygon(cell.h3)?],
+            },
+            "properties": properties,
+        }));
+    }
+    Ok(json!({
+        "type": "FeatureCollection",
+        "features": features,
+    }))
+}
+
+pub fn export_h3_geojson(path: &str, metrics: &[H3Metric], cells: &[H3Aggregate]) -> Result<()> {
+    let geojson = h3_geojson(metrics, cells)?;
+    std::fs::write(path, serde_json::to_string(&geojson)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{h3_to_parent, lat_lng_to_h3_12};
+
+    #[test]
+    fn test_h3_geojson() {
+        let h3 = h3_to_parent(lat_lng_to_h3_12(42.2808, -83.7430), 9).unwrap();
+        let cells = vec![H3Aggregate {
+            h3,
+            point_count: 10,
+            mean_speed: Some(42.0),
+            mean_energy_consumption: None,
+            mean_fuel_rate: None,
+            vehicle_count: 3,
+        }];
+        let geojson = h3_geojson(&[H3Metric::Count, H3Metric::Energy], &cells).unwrap();
+
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["properties"]["h3"], json!(format!("{:x}", h3)));
+        assert_eq!(feature["properties"]["point_count"], json!(10));
+        assert!(feature["properties"]["mean_energy_consumption"].is_null());
+        assert!(feature["properties"].get("mean_speed").is_none());
+
+        let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 7);
+        assert_eq!(ring.first(), ring.last());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod graph;
+pub mod h3;
+pub mod od;
+pub mod route;
@@ -0,0 +1,189 @@
+use crate::cli::ExportFormat;
+use crate::models::od::OdCell;
+use anyhow::{Result, anyhow};
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Serialize;
+use std::fs::File;
+use std::sync::Arc;
+use text_block_macros::text_block;
+
+/// Exported row, with the H3 cells written as hexadecimal strings
+#[derive(Serialize)]
+struct OdRecord<'a> {
+    h3_ini: String,
+    h3_end: String,
+    resolution: u8,
+    vehicle_type: Option<&'a str>,
+    hour: u32,
+    weekday: u32,
+    trip_count: i64,
+    median_duration_s: f64,
+    median_length_m: f64,
+}
+
+impl<'a> From<&'a OdCell> for OdRecord<'a> {
+    fn from(cell: &'a OdCell) -> Self {
+        OdRecord {
+            h3_ini: format!("{:x}", cell.h3_ini),
+            h3_end: format!("{:x}", cell.h3_end),
+            resolution: cell.resolution,
+            vehicle_type: cell.vehicle_type.as_deref(),
+            hour: cell.hour,
+            weekday: cell.weekday,
+            trip_count: cell.trip_count,
+            median_duration_s: cell.median_duration_s,
+            median_length_m: cell.median_length_m,
+        }
+    }
+}
+
+fn write_csv(path: &str, cells: &[OdCell]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for cell in cells {
+        writer.serialize(OdRecord::from(cell))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_parquet(path: &str, cells: &[OdCell]) -> Result<()> {
+    let schema = text_block! {
+        "message od_matrix {"
+        "    REQUIRED BYTE_ARRAY h3_ini (UTF8);"
+        "    REQUIRED BYTE_ARRAY h3_end (UTF8);"
+        "    REQUIRED INT32 resolution;"
+        "    OPTIONAL BYTE_ARRAY vehicle_type (UTF8);"
+        "    REQUIRED INT32 hour;"
+        "    REQUIRED INT32 weekday;"
+        "    REQUIRED INT64 trip_count;"
+        "    REQUIRED DOUBLE median_duration_s;"
+        "    REQUIRED DOUBLE median_length_m;"
+        "}"
+    };
+    let schema = Arc::new(parse_message_type(schema)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(File::create(path)?, schema, props)?;
+
+    let records: Vec<OdRecord> = cells.iter().map(OdRecord::from).collect();
+    let h3_ini: Vec<ByteArray> = records.iter().map(|r| r.h3_ini.as_str().into()).collect();
+    let h3_end: Vec<ByteArray> = records.iter().map(|r| r.h3_end.as_str().into()).collect();
+    let resolution: Vec<i32> = records.iter().map(|r| r.resolution as i32).collect();
+    let vehicle_type: Vec<ByteArray> = records
+        .iter()
+        .filter_map(|r| r.vehicle_type.map(ByteArray::from))
+        .collect();
+    let vehicle_type_def: Vec<i16> = records
+        .iter()
+        .map(|r| r.vehicle_type.is_some() as i16)
+        .collect();
+    let hour: Vec<i32> = records.iter().map(|r| r.hour as i32).collect();
+    let weekday: Vec<i32> = records.iter().map(|r| r.weekday as i32).collect();
+    let trip_count: Vec<i64> = records.iter().map(|r| r.trip_count).collect();
+    let duration: Vec<f64> = records.iter().map(|r| r.median_duration_s).collect();
+    let length: Vec<f64> = records.iter().map(|r| r.median_length_m).collect();
+
+    let mut row_group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        match index {
+            0 => column.typed::<ByteArrayType>().write_batch(&h3_ini, None, None)?,
+            1 => column.typed::<ByteArrayType>().write_batch(&h3_end, None, None)?,
+            2 => column.typed::<Int32Type>().write_batch(&resolution, None, None)?,
+            3 => column.typed::<ByteArrayType>().write_batch(
+                &vehicle_type,
+                Some(&vehicle_type_def),
+                None,
+            )?,
+            4 => column.typed::<Int32Type>().write_batch(&hour, None, None)?,
+            5 => column.typed::<Int32Type>().write_batch(&weekday, None, None)?,
+            6 => column.typed::<Int64Type>().write_batch(&trip_count, None, None)?,
+            7 => column.typed::<DoubleType>().write_batch(&duration, None, None)?,
+            8 => column.typed::<DoubleType>().write_batch(&length, None, None)?,
+            _ => return Err(anyhow!("Unexpected od_matrix column {}", index)),
+        };
+        column.close()?;
+        index += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+pub fn export_od_cells(path: &str, format: ExportFormat, cells: &[OdCell]) -> Result<()> {
+    match format {
+        ExportFormat::Csv => write_csv(path, cells),
+        ExportFormat::Parquet => write_parquet(path, cells),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn sample_cells() -> Vec<OdCell> {
+        vec![
+            OdCell {
+                h3_ini: 0x882ab25a0bfffff,
+                h3_end: 0x882ab25a1bfffff,
+                resolution: 8,
+                vehicle_type: Some("EV".to_string()),
+                hour: 8,
+                weekday: 1,
+                trip_count: 3,
+                median_duration_s: 600.0,
+                median_length_m: 4200.0,
+            },
+            OdCell {
+                h3_ini: 0x882ab25a0bfffff,
+                h3_end: 0x882ab25a0bfffff,
+                resolution: 8,
+                vehicle_type: None,
+                hour: 17,
+                weekday: 4,
+                trip_count: 1,
+                median_duration_s: 120.0,
+                median_length_m: 800.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_export_od_cells_csv() {
+        let path = "test_od_export.csv";
+        export_od_cells(path, ExportFormat::Csv, &sample_cells()).unwrap();
+
+        let content = fs::read_to_string(path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("h3_ini,h3_end,resolution,vehicle_type"));
+        assert!(lines[1].starts_with("882ab25a0bfffff,882ab25a1bfffff,8,EV,8,1,3"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_od_cells_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let path = "test_od_export.parquet";
+        export_od_cells(path, ExportFormat::Parquet, &sample_cells()).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(
+            reader.metadata().file_metadata().schema_descr().num_columns(),
+            9
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use crate::routing::Route;
+use anyhow::Result;
+use serde_json::json;
+
+pub fn route_geojson(route: &Route) -> serde_json::Value {
+    let coordinates: Vec<Vec<f64>> = route
+        .nodes
+        .iter()
+        .map(|n| vec![n.longitude, n.latitude])
+        .collect();
+    json!({
+        "type": "FeatureCollection",
+        "features": [{
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": coordinates,
+            },
+            "properties": {
+                "node_ids": route.nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+                "edge_ids": route.edge_ids,
+                "length_m": route.length_m,
+                "time_s": route.time_s,
+            },
+        }],
+    })
+}
+
+pub fn export_route_geojson(path: &str, route: &Route) -> Result<()> {
+    std::fs::write(path, serde_json::to_string(&route_geojson(route))?)?;
+    Ok(())
+}
@@ -1,6 +1,10 @@
+pub mod cache;
 pub mod cli;
 pub mod commands;
 pub mod db;
 pub mod etl;
+pub mod export;
+pub mod matcher;
 pub mod models;
+pub mod routing;
 pub mod tools;
@@ -1,24 +1,69 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use evedb::cli::{Cli, Commands};
+use evedb::commands::aggregate::run_aggregate;
 use evedb::commands::build::build_database;
 use evedb::commands::builders::node::build_nodes;
 use evedb::commands::clean::clean_data;
 use evedb::commands::clone::clone_data;
+use evedb::commands::config::run_config;
+use evedb::commands::export::run_export;
 use evedb::commands::interactive::interactive;
-use evedb::models::config::Config;
+use evedb::commands::network::run_network;
+use evedb::commands::route::run_route;
+use evedb::commands::stage::run_stage;
+use evedb::commands::validate::validate;
+use evedb::models::config::{Config, PROJECT_FILE};
+use std::path::Path;
 
 #[tokio::main]
 async fn main() {
-    let mut cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    cli.load_config(&Config::load());
+    if cli.profile.is_none() {
+        cli.profile = std::env::var("EVEDB_PROFILE").ok();
+    }
+    if Path::new("./evedb.json").exists() {
+        eprintln!("evedb.json is no longer read, move its settings to evedb.toml");
+    }
+    // The config command reports load errors itself, so that a broken file can be fixed
+    let env = |name: &str| std::env::var(name).ok();
+    match Config::load(Path::new(PROJECT_FILE), cli.profile.as_deref(), env) {
+        Ok(config) => cli.load_config(&config, &matches),
+        Err(_) if matches!(cli.command, Some(Commands::Config(_))) => {}
+        Err(e) => {
+            eprintln!("Invalid configuration: {:#}", e);
+            std::process::exit(1);
+        }
+    }
 
     match &cli.command {
         Some(Commands::Build(args)) => {
             build_database(&cli, args).await;
         }
-        Some(Commands::Match) => {
-            build_nodes(&cli).await;
+        Some(Commands::Match(args)) => {
+            build_nodes(&cli, args).await;
+        }
+        Some(Commands::Stage(args)) => {
+            run_stage(&cli, args);
+        }
+        Some(Commands::Aggregate(args)) => {
+            run_aggregate(&cli, args);
+        }
+        Some(Commands::Export(args)) => {
+            run_export(&cli, args);
+        }
+        Some(Commands::Network(args)) => {
+            run_network(&cli, args);
+        }
+        Some(Commands::Validate(args)) => {
+            validate(&cli, args);
+        }
+        Some(Commands::Route(args)) => {
+            run_route(&cli, args);
+        }
+        Some(Commands::Config(args)) => {
+            run_config(&cli, args);
         }
         Some(Commands::Clean) => {
             clean_data(&cli);
@@ -33,7 +78,4 @@ async fn main() {
             interactive(&mut cli).await;
         }
     }
-
-    // Obtain the config and save it
-    cli.get_config().save();
 }
@@ -0,0 +1,386 @@
+use crate::matcher::{MapMatcher, MatchedTrip, SEARCH_RADIUS_M};
+use crate::models::edge::NetworkEdge;
+use crate::models::node::Node;
+use crate::models::trajectory::WayPoint;
+use crate::routing::RoadGraph;
+use anyhow::{Result, anyhow};
+use geo::{Distance, Haversine, Point};
+use std::collections::{HashMap, HashSet};
+
+/// Mean Earth radius (m), matching the one used by the Haversine distances
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Side (degrees) of the grid cells indexing the segments, about the search radius
+const CELL_DEG: f64 = 0.001;
+
+/// Number of nearest segments kept as candidates for each GPS point
+const MAX_CANDIDATES: usize = 8;
+
+/// Transitions whose route is longer than this multiple of the distance between the
+/// GPS points, plus twice the search radius, are impossible
+const MAX_DETOUR: f64 = 3.0;
+
+struct Segment {
+    node_ini: i64,
+    node_end: i64,
+    length_m: f64,
+}
+
+/// Projection of a GPS point onto a segment, `fraction` of the way along it
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    segment: usize,
+    fraction: f64,
+    distance_m: f64,
+}
+
+fn cell(latitude: f64, longitude: f64) -> (i64, i64) {
+    (
+        (latitude / CELL_DEG).floor() as i64,
+        (longitude / CELL_DEG).floor() as i64,
+    )
+}
+
+/// Hidden Markov model map-matcher after Newson and Krumm (2009), running on a local
+/// road network. The hidden states are the projections of each GPS point onto nearby
+/// segments; emissions are Gaussian in the distance to the segment and transitions
+/// exponential in the difference between the route and straight-line distances.
+pub struct HmmMatcher {
+    graph: RoadGraph,
+    segments: Vec<Segment>,
+    grid: HashMap<(i64, i64), Vec<usize>>,
+    sigma_m: f64,
+    beta_m: f64,
+}
+
+impl HmmMatcher {
+    /// `sigma_m` is the standard deviation of the GPS noise and `beta_m` the scale of
+    /// the tolerated difference between route and straight-line distances
+    pub fn new(nodes: Vec<Node>, edges: &[NetworkEdge], sigma_m: f64, beta_m: f64) -> HmmMatcher {
+        let positions: HashMap<i64, &Node> = nodes.iter().map(|n| (n.id, n)).collect();
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+        for edge in edges {
+            let (Some(a), Some(b)) = (positions.get(&edge.node_ini), positions.get(&edge.node_end))
+            else {
+                continue;
+            };
+            let (lat0, lon0) = cell(a.latitude.min(b.latitude), a.longitude.min(b.longitude));
+            let (lat1, lon1) = cell(a.latitude.max(b.latitude), a.longitude.max(b.longitude));
+            for i in lat0..=lat1 {
+                for j in lon0..=lon1 {
+                    grid.entry((i, j)).or_default().push(segments.len());
+                }
+            }
+            segments.push(Segment {
+                node_ini: edge.node_ini,
+                node_end: edge.node_end,
+                length_m: edge.length_m,
+            });
+        }
+
+        HmmMatcher {
+            graph: RoadGraph::new(nodes, edges),
+            segments,
+            grid,
+            sigma_m,
+            beta_m,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Distance (m) from the point to the segment and the fraction of the segment at
+    /// the projection, on a local plane around the point
+    fn project(&self, latitude: f64, longitude: f64, segment: &Segment) -> Option<(f64, f64)> {
+        let scale_x = latitude.to_radians().cos() * EARTH_RADIUS_M;
+        let xy = |n: &Node| {
+            (
+                (n.longitude - longitude).to_radians() * scale_x,
+                (n.latitude - latitude).to_radians() * EARTH_RADIUS_M,
+            )
+        };
+        let (ax, ay) = xy(self.graph.node(segment.node_ini)?);
+        let (bx, by) = xy(self.graph.node(segment.node_end)?);
+        let (dx, dy) = (bx - ax, by - ay);
+        let length2 = dx * dx + dy * dy;
+        let t = if length2 > 0.0 {
+            (-(ax * dx + ay * dy) / length2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Some(((ax + t * dx).hypot(ay + t * dy), t))
+    }
+
+    /// The nearest segments within the search radius of the point
+    fn candidates(&self, latitude: f64, longitude: f64) -> Vec<Candidate> {
+        let dlat = (SEARCH_RADIUS_M / EARTH_RADIUS_M).to_degrees();
+        let dlon = dlat / latitude.to_radians().cos();
+        let (lat0, lon0) = cell(latitude - dlat, longitude - dlon);
+        let (lat1, lon1) = cell(latitude + dlat, longitude + dlon);
+
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for i in lat0..=lat1 {
+            for j in lon0..=lon1 {
+                for &segment in self.grid.get(&(i, j)).into_iter().flatten() {
+                    if !seen.insert(segment) {
+                        continue;
+                    }
+                    if let Some((distance_m, fraction)) =
+                        self.project(latitude, longitude, &self.segments[segment])
+                        && distance_m <= SEARCH_RADIUS_M
+                    {
+                        candidates.push(Candidate {
+                            segment,
+                            fraction,
+                            distance_m,
+                        });
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.distance_m.total_cmp(&b.distance_m));
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+
+    fn emission(&self, candidate: &Candidate) -> f64 {
+        -0.5 * (candidate.distance_m / self.sigma_m).powi(2)
+    }
+
+    /// Route distance between two candidates given the paths from the end of the
+    /// first one's segment. Moves along a single segment are taken as they come, so
+    /// that GPS noise does not turn into detours around the block.
+    fn route_distance(
+        &self,
+        from: &Candidate,
+        to: &Candidate,
+        paths: &HashMap<i64, (f64, i64)>,
+    ) -> Option<f64> {
+        let (a, b) = (&self.segments[from.segment], &self.segments[to.segment]);
+        if from.segment == to.segment {
+            return Some((to.fraction - from.fraction).abs() * a.length_m);
+        }
+        let &(between_m, _) = paths.get(&b.node_ini)?;
+        Some((1.0 - from.fraction) * a.length_m + between_m + to.fraction * b.length_m)
+    }
+
+    /// Nodes visited after the end of the first candidate's segment up to the end of
+    /// the second one's
+    fn path_between(
+        &self,
+        from: &Candidate,
+        to: &Candidate,
+        paths: &HashMap<i64, (f64, i64)>,
+    ) -> Vec<i64> {
+        if from.segment == to.segment {
+            return vec![];
+        }
+        let (a, b) = (&self.segments[from.segment], &self.segments[to.segment]);
+        let mut nodes = vec![b.node_end];
+        let mut current = b.node_ini;
+        while current != a.node_end {
+            nodes.push(current);
+            current = paths[&current].1;
+        }
+        nodes.reverse();
+        nodes
+    }
+
+    /// Runs the Viterbi algorithm over the points given as `(lat, lon)` and returns
+    /// the shape of the most likely path. Points without a segment within the search
+    /// radius are skipped; a gap that no route bridges is an error.
+    pub fn match_points(&self, points: &[(f64, f64)]) -> Result<Vec<[f64; 2]>> {
+        let steps: Vec<(usize, Vec<Candidate>)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, &(lat, lon))| (i, self.candidates(lat, lon)))
+            .filter(|(_, candidates)| !candidates.is_empty())
+            .collect();
+        if steps.is_empty() {
+            return Err(anyhow!(
+                "No road segment within {} m of the GPS points",
+                SEARCH_RADIUS_M
+            ));
+        }
+
+        let mut scores: Vec<f64> = steps[0].1.iter().map(|c| self.emission(c)).collect();
+        // For every later step and candidate, the best previous candidate and the nodes
+        // between the two
+        let mut back: Vec<Vec<(usize, Vec<i64>)>> = Vec::new();
+
+        for pair in steps.windows(2) {
+            let ((i, previous), (j, next)) = (&pair[0], &pair[1]);
+            let straight_m = Haversine.distance(
+                Point::new(points[*i].1, points[*i].0),
+                Point::new(points[*j].1, points[*j].0),
+            );
+            let limit_m = MAX_DETOUR * straight_m + 2.0 * SEARCH_RADIUS_M;
+
+            let mut next_scores = vec![f64::NEG_INFINITY; next.len()];
+            let mut next_back: Vec<(usize, Vec<i64>)> = vec![(0, vec![]); next.len()];
+            for (a, from) in previous.iter().enumerate() {
+                if scores[a] == f64::NEG_INFINITY {
+                    continue;
+                }
+                let paths = self
+                    .graph
+                    .paths_within(self.segments[from.segment].node_end, limit_m);
+                for (b, to) in next.iter().enumerate() {
+                    let Some(route_m) = self.route_distance(from, to, &paths) else {
+                        continue;
+                    };
+                    if route_m > limit_m {
+                        continue;
+                    }
+                    let score =
+                        scores[a] - (route_m - straight_m).abs() / self.beta_m + self.emission(to);
+                    if score > next_scores[b] {
+                        next_scores[b] = score;
+                        next_back[b] = (a, self.path_between(from, to, &paths));
+                    }
+                }
+            }
+
+            if next_scores.iter().all(|&s| s == f64::NEG_INFINITY) {
+                return Err(anyhow!("No route between GPS points {} and {}", i, j));
+            }
+            scores = next_scores;
+            back.push(next_back);
+        }
+
+        let mut best = (0..scores.len())
+            .max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+            .unwrap_or(0);
+        let mut paths: Vec<Vec<i64>> = Vec::new();
+        for step in back.iter().rev() {
+            let (previous, path) = &step[best];
+            paths.push(path.clone());
+            best = *previous;
+        }
+
+        let first = &self.segments[steps[0].1[best].segment];
+        let mut node_ids = vec![first.node_ini, first.node_end];
+        node_ids.extend(paths.into_iter().rev().flatten());
+        node_ids.dedup();
+
+        Ok(node_ids
+            .iter()
+            .filter_map(|&id| self.graph.node(id))
+            .map(|n| [n.latitude, n.longitude])
+            .collect())
+    }
+}
+
+impl MapMatcher for HmmMatcher {
+    async fn map_match(&self, points: &[WayPoint]) -> Result<MatchedTrip> {
+        let points: Vec<(f64, f64)> = points.iter().map(|p| (p.latitude, p.longitude)).collect();
+        Ok(MatchedTrip {
+            shape: self.match_points(&points)?,
+            warnings: None,
+        })
+    }
+
+    fn search_radius_m(&self) -> f64 {
+        SEARCH_RADIUS_M
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, latitude: f64, longitude: f64) -> Node {
+        Node {
+            id,
+            latitude,
+            longitude,
+            altitude: 0.0,
+            h3_12: 0,
+        }
+    }
+
+    fn edge(id: i64, from: &Node, to: &Node) -> NetworkEdge {
+        NetworkEdge {
+            id,
+            node_ini: from.id,
+            node_end: to.id,
+            length_m: from.distance_to(to),
+            bearing_deg: from.bearing_to(to),
+            traversals: 1,
+            mean_speed_kmh: None,
+        }
+    }
+
+    /// Two parallel one-way streets 1 -> 2 -> 3 and 4 -> 5 -> 6, 33 m apart, joined by
+    /// a link 2 -> 5, plus a far street 7 -> 8
+    fn matcher() -> HmmMatcher {
+        let nodes = vec![
+            node(1, 42.0000, -83.7000),
+            node(2, 42.0000, -83.6990),
+            node(3, 42.0000, -83.6980),
+            node(4, 42.0003, -83.7000),
+            node(5, 42.0003, -83.6990),
+            node(6, 42.0003, -83.6980),
+            node(7, 42.0200, -83.7000),
+            node(8, 42.0200, -83.6990),
+        ];
+        let n = |id: i64| nodes[id as usize - 1];
+        let edges = vec![
+            edge(12, &n(1), &n(2)),
+            edge(23, &n(2), &n(3)),
+            edge(45, &n(4), &n(5)),
+            edge(56, &n(5), &n(6)),
+            edge(25, &n(2), &n(5)),
+            edge(78, &n(7), &n(8)),
+        ];
+        HmmMatcher::new(nodes, &edges, 5.0, 10.0)
+    }
+
+    #[test]
+    fn test_match_points_follows_route() {
+        let matcher = matcher();
+        // East along the south street, closer to the north one past the link
+        let points = vec![
+            (42.00002, -83.6997),
+            (42.00005, -83.6993),
+            (42.00016, -83.6989),
+            (42.00022, -83.6985),
+            (42.00025, -83.6982),
+        ];
+        let shape = matcher.match_points(&points).unwrap();
+        assert_eq!(
+            shape,
+            vec![
+                [42.0000, -83.7000],
+                [42.0000, -83.6990],
+                [42.0003, -83.6990],
+                [42.0003, -83.6980],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_points_skips_and_breaks() {
+        let matcher = matcher();
+        // The middle point is beyond the search radius of every segment
+        let shape = matcher
+            .match_points(&[
+                (42.00001, -83.6998),
+                (42.01, -83.6995),
+                (42.00001, -83.6993),
+            ])
+            .unwrap();
+        assert_eq!(shape, vec![[42.0000, -83.7000], [42.0000, -83.6990]]);
+
+        let error = matcher
+            .match_points(&[(42.00001, -83.6998), (42.02001, -83.6995)])
+            .unwrap_err();
+        assert!(error.to_string().contains("No route"));
+        assert!(matcher.match_points(&[(42.01, -83.6995)]).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+pub mod hmm;
+pub mod pool;
+pub mod valhalla;
+
+use crate::models::trajectory::WayPoint;
+use anyhow::Result;
+
+/// Search radius (m) around each GPS point for candidate road segments
+pub const SEARCH_RADIUS_M: f64 = 100.0;
+
+/// The matched shape of a trajectory as `[lat, lon]` points along the road network,
+/// and the matcher's warnings if there were any
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedTrip {
+    pub shape: Vec<[f64; 2]>,
+    pub warnings: Option<String>,
+}
+
+/// Snaps the GPS points of a trajectory onto a road network
+pub trait MapMatcher {
+    fn map_match(&self, points: &[WayPoint]) -> impl Future<Output = Result<MatchedTrip>>;
+
+    /// Search radius (m) around each GPS point used by this matcher
+    fn search_radius_m(&self) -> f64;
+}
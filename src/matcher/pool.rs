@@ -0,0 +1,217 @@
+use anyhow::{Result, anyhow, bail};
+use http_body_util::{BodyExt, Full};
+use hyper::Request;
+use hyper::body::Bytes;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use url::Url;
+use valhalla_client::Valhalla;
+use valhalla_client::trace_route::Manifest;
+
+type HttpClient = Client<HttpConnector, Full<Bytes>>;
+
+/// Time after which an endpoint that failed a health check is checked again
+const HEALTH_RETRY: Duration = Duration::from_secs(30);
+
+struct Endpoint {
+    url: Url,
+    valhalla: Valhalla,
+    in_flight: AtomicUsize,
+    down_since: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn is_down(&self) -> bool {
+        self.down_since.lock().map(|d| d.is_some()).unwrap_or(true)
+    }
+
+    fn set_down(&self, since: Option<Instant>) {
+        if let Ok(mut down_since) = self.down_since.lock() {
+            *down_since = since;
+        }
+    }
+}
+
+/// Parses a comma-separated list of Valhalla URLs
+pub fn parse_urls(urls: &str) -> Result<Vec<Url>> {
+    let urls: Vec<Url> = urls
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| Url::parse(url).map_err(|e| anyhow!("Invalid Valhalla URL '{}': {}", url, e)))
+        .collect::<Result<_>>()?;
+    if urls.is_empty() {
+        return Err(anyhow!("No Valhalla URL given"));
+    }
+    Ok(urls)
+}
+
+/// Posts a `trace_route` request and returns the response body as received, so that
+/// it can be cached whole. `valhalla_client` only hands back the decoded trip.
+async fn post_trace_route(client: &HttpClient, url: &Url, manifest: &Manifest) -> Result<Vec<u8>> {
+    let request = Request::post(url.join("trace_route")?.as_str())
+        .header("content-type", "application/json")
+        .body(Full::new(Bytes::from(serde_json::to_vec(manifest)?)))?;
+    let response = client.request(request).await?;
+    let status = response.status();
+    let body = response.into_body().collect().await?.to_bytes();
+    if !status.is_success() {
+        bail!("HTTP {}: {}", status, String::from_utf8_lossy(&body));
+    }
+    Ok(body.to_vec())
+}
+
+/// Index of the up endpoint with the fewest requests in flight, scanning from `start`
+/// so that ties rotate round-robin
+fn least_loaded(up: &[bool], in_flight: &[usize], start: usize) -> Option<usize> {
+    let n = up.len();
+    (0..n)
+        .map(|i| (start + i) % n)
+        .filter(|&i| up[i])
+        .min_by_key(|&i| in_flight[i])
+}
+
+/// Valhalla instances sharing the map-matching requests. Each request goes to the
+/// least-loaded endpoint that is up. An endpoint whose request fails and which then
+/// fails the status call is dropped, and checked again after `HEALTH_RETRY`.
+pub struct ValhallaPool {
+    endpoints: Vec<Endpoint>,
+    next: AtomicUsize,
+    timeout: Duration,
+    client: HttpClient,
+}
+
+impl ValhallaPool {
+    async fn is_up(&self, valhalla: &Valhalla) -> bool {
+        let status = valhalla.status(valhalla_client::status::Manifest::default());
+        matches!(tokio::time::timeout(self.timeout, status).await, Ok(Ok(_)))
+    }
+
+    /// Health-checks every endpoint, failing when none is up. Requests and health
+    /// checks that take longer than `timeout` count as failures.
+    pub async fn connect(urls: &[Url], timeout: Duration, verbose: bool) -> Result<ValhallaPool> {
+        let mut pool = ValhallaPool {
+            endpoints: Vec::with_capacity(urls.len()),
+            next: AtomicUsize::new(0),
+            timeout,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        };
+        for url in urls {
+            if verbose {
+                println!("Checking Valhalla instance at {}", url);
+            }
+            let valhalla = Valhalla::new(url.clone());
+            let down_since = if pool.is_up(&valhalla).await {
+                None
+            } else {
+                eprintln!("Valhalla instance at {} is down", url);
+                Some(Instant::now())
+            };
+            pool.endpoints.push(Endpoint {
+                url: url.clone(),
+                valhalla,
+                in_flight: AtomicUsize::new(0),
+                down_since: Mutex::new(down_since),
+            });
+        }
+
+        if pool.endpoints.iter().all(Endpoint::is_down) {
+            return Err(anyhow!("No Valhalla instance is up"));
+        }
+        Ok(pool)
+    }
+
+    /// Checks the endpoints that have been down for longer than `HEALTH_RETRY` and
+    /// re-adds those that answer
+    async fn revive(&self) {
+        for endpoint in &self.endpoints {
+            let due = match endpoint.down_since.lock() {
+                Ok(mut down_since) => match *down_since {
+                    Some(since) if since.elapsed() >= HEALTH_RETRY => {
+                        // Restart the clock so that concurrent requests skip this check
+                        *down_since = Some(Instant::now());
+                        true
+                    }
+                    _ => false,
+                },
+                Err(_) => false,
+            };
+            if due && self.is_up(&endpoint.valhalla).await {
+                eprintln!("Valhalla instance at {} is back up", endpoint.url);
+                endpoint.set_down(None);
+            }
+        }
+    }
+
+    async fn pick(&self) -> Option<&Endpoint> {
+        self.revive().await;
+        let up: Vec<bool> = self.endpoints.iter().map(|e| !e.is_down()).collect();
+        let in_flight: Vec<usize> = self
+            .endpoints
+            .iter()
+            .map(|e| e.in_flight.load(Ordering::Relaxed))
+            .collect();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        least_loaded(&up, &in_flight, start % self.endpoints.len()).map(|i| &self.endpoints[i])
+    }
+
+    /// Sends the request to the least-loaded endpoint, moving on to the next one when
+    /// the endpoint turns out to be down, and returns the raw response body
+    pub async fn trace_route(&self, manifest: &Manifest) -> Result<Vec<u8>> {
+        loop {
+            let Some(endpoint) = self.pick().await else {
+                return Err(anyhow!("No Valhalla instance is up"));
+            };
+
+            endpoint.in_flight.fetch_add(1, Ordering::Relaxed);
+            let request = post_trace_route(&self.client, &endpoint.url, manifest);
+            let result = tokio::time::timeout(self.timeout, request).await;
+            endpoint.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+            let error = match result {
+                Ok(Ok(body)) => return Ok(body),
+                Ok(Err(e)) => format!("{:#}", e),
+                Err(_) => format!("timed out after {} s", self.timeout.as_secs()),
+            };
+            match error {
+                // A healthy instance rejected or timed out on this trace
+                error if self.is_up(&endpoint.valhalla).await => {
+                    return Err(anyhow!("Failed to map match: {}", error));
+                }
+                _ => {
+                    eprintln!("Valhalla instance at {} is down", endpoint.url);
+                    endpoint.set_down(Some(Instant::now()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_urls() {
+        let urls = parse_urls("http://localhost:8002/, http://localhost:8003/,").unwrap();
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[1].port(), Some(8003));
+        assert!(parse_urls(" , ").is_err());
+        assert!(parse_urls("http://localhost:8002/,not a url").is_err());
+    }
+
+    #[test]
+    fn test_least_loaded() {
+        let up = [true, false, true, true];
+        // Ties rotate with the starting point, down endpoints are skipped
+        assert_eq!(least_loaded(&up, &[0, 0, 0, 0], 0), Some(0));
+        assert_eq!(least_loaded(&up, &[0, 0, 0, 0], 1), Some(2));
+        assert_eq!(least_loaded(&up, &[0, 0, 0, 0], 3), Some(3));
+        assert_eq!(least_loaded(&up, &[2, 0, 1, 1], 0), Some(2));
+        assert_eq!(least_loaded(&[false, false], &[0, 0], 0), None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::cache::MatchCache;
+use crate::cli::MatchCommandArgs;
+use crate::matcher::pool::{ValhallaPool, parse_urls};
+use crate::matcher::{MapMatcher, MatchedTrip};
+use crate::models::config::{ValhallaConfig, ValhallaCosting};
+use crate::models::trajectory::WayPoint;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::time::Duration;
+use url::Url;
+use valhalla_client::costing::{AutoCostingOptions, Costing, TruckCostingOptions};
+use valhalla_client::route::{DirectionsType, ShapePoint, Trip};
+use valhalla_client::trace_route::{Manifest, ShapeMatchType, TraceOptions};
+
+/// Valhalla options in effect for a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValhallaSettings {
+    pub urls: Vec<Url>,
+    pub timeout: Duration,
+    pub costing: ValhallaCosting,
+    pub search_radius_m: f64,
+    pub gps_accuracy_m: f64,
+}
+
+impl ValhallaSettings {
+    /// Resolves every option from the `match` flags, then the `valhalla` config section,
+    /// which already holds the environment overrides
+    pub fn resolve(config: &ValhallaConfig, args: &MatchCommandArgs) -> Result<ValhallaSettings> {
+        let url = args.valhalla_url.as_deref().unwrap_or(&config.url);
+        Ok(ValhallaSettings {
+            urls: parse_urls(url)?,
+            timeout: Duration::from_secs(args.valhalla_timeout.unwrap_or(config.timeout_s)),
+            costing: args.costing.unwrap_or(config.costing),
+            search_radius_m: args.search_radius.unwrap_or(config.search_radius_m),
+            gps_accuracy_m: args.gps_accuracy.unwrap_or(config.gps_accuracy_m),
+        })
+    }
+
+    fn costing(&self) -> Costing {
+        match self.costing {
+            ValhallaCosting::Auto => Costing::Auto(AutoCostingOptions::default()),
+            ValhallaCosting::Truck => Costing::Truck(TruckCostingOptions::default()),
+        }
+    }
+}
+
+impl Default for ValhallaSettings {
+    fn default() -> Self {
+        ValhallaSettings::resolve(&ValhallaConfig::default(), &MatchCommandArgs::default())
+            .expect("the default Valhalla settings are valid")
+    }
+}
+
+pub fn build_manifest(
+    settings: &ValhallaSettings,
+    locations: impl Iterator<Item = ShapePoint>,
+) -> Manifest {
+    let trace_options = TraceOptions::builder()
+        .search_radius(settings.search_radius_m as f32)
+        .gps_accuracy(settings.gps_accuracy_m as f32);
+    Manifest::builder()
+        .shape_match(ShapeMatchType::MapSnap)
+        .shape(locations)
+        .use_timestamps(false)
+        .verbose(true)
+        .trace_options(trace_options)
+        .directions_type(DirectionsType::None)
+        .costing(settings.costing())
+}
+
+/// Decodes the trip of a `trace_route` response body
+pub fn decode_trip(body: &[u8]) -> Result<Trip> {
+    #[derive(Deserialize)]
+    struct Response {
+        trip: Trip,
+    }
+    let response: Response =
+        serde_json::from_slice(body).map_err(|e| anyhow!("Invalid trace_route response: {}", e))?;
+    Ok(response.trip)
+}
+
+fn matched_trip(trip: Trip) -> MatchedTrip {
+    MatchedTrip {
+        shape: trip
+            .legs
+            .iter()
+            .flat_map(|leg| leg.shape.iter())
+            .map(|pt| [pt.lat, pt.lon])
+            .collect(),
+        warnings: trip.warnings.map(|warnings| format!("{:?}", warnings)),
+    }
+}
+
+/// Matches through Valhalla's `trace_route`, answering from the cache when it holds the
+/// same request. Without Valhalla instances, cache misses are errors.
+pub struct ValhallaMatcher {
+    pool: Option<ValhallaPool>,
+    cache: Option<MatchCache>,
+    settings: ValhallaSettings,
+}
+
+impl ValhallaMatcher {
+    pub fn new(
+        pool: Option<ValhallaPool>,
+        cache: Option<MatchCache>,
+        settings: ValhallaSettings,
+    ) -> ValhallaMatcher {
+        ValhallaMatcher {
+            pool,
+            cache,
+            settings,
+        }
+    }
+}
+
+impl MapMatcher for ValhallaMatcher {
+    async fn map_match(&self, points: &[WayPoint]) -> Result<MatchedTrip> {
+        let manifest = build_manifest(&self.settings, points.iter().map(|p| p.into()));
+        let key = match self.cache {
+            Some(_) => Some(MatchCache::key(&manifest)?),
+            None => None,
+        };
+        if let (Some(cache), Some(key)) = (&self.cache, &key)
+            && let Some(trip) = cache.get(key)
+        {
+            return Ok(matched_trip(trip));
+        }
+
+        let Some(pool) = &self.pool else {
+            return Err(anyhow!("No cached map match for this request"));
+        };
+        let body = pool.trace_route(&manifest).await?;
+        let trip = decode_trip(&body)?;
+
+        if let (Some(cache), Some(key)) = (&self.cache, &key)
+            && let Err(e) = cache.put(key, &body)
+        {
+            eprintln!("Failed to cache map match: {}", e);
+        }
+        Ok(matched_trip(trip))
+    }
+
+    fn search_radius_m(&self) -> f64 {
+        self.settings.search_radius_m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_precedence() {
+        let config = ValhallaConfig {
+            url: "http://config:8002/,http://config:8003/".to_string(),
+            timeout_s: 30,
+            costing: ValhallaCosting::Truck,
+            ..ValhallaConfig::default()
+        };
+        let args = MatchCommandArgs {
+            search_radius: Some(50.0),
+            ..MatchCommandArgs::default()
+        };
+
+        // Config, then flags
+        let settings = ValhallaSettings::resolve(&config, &args).unwrap();
+        assert_eq!(settings.urls.len(), 2);
+        assert_eq!(settings.urls[0].host_str(), Some("config"));
+        assert_eq!(settings.timeout, Duration::from_secs(30));
+        assert_eq!(settings.costing, ValhallaCosting::Truck);
+        assert_eq!(settings.search_radius_m, 50.0);
+        assert_eq!(settings.gps_accuracy_m, 5.0);
+
+        let args = MatchCommandArgs {
+            valhalla_url: Some("http://flag:8002/".to_string()),
+            costing: Some(ValhallaCosting::Auto),
+            ..MatchCommandArgs::default()
+        };
+        let settings = ValhallaSettings::resolve(&config, &args).unwrap();
+        assert_eq!(settings.urls[0].host_str(), Some("flag"));
+        assert_eq!(settings.costing, ValhallaCosting::Auto);
+
+        let args = MatchCommandArgs {
+            valhalla_url: Some(" , ".to_string()),
+            ..MatchCommandArgs::default()
+        };
+        assert!(ValhallaSettings::resolve(&config, &args).is_err());
+    }
+}
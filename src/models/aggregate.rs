@@ -0,0 +1,69 @@
+use clap::ValueEnum;
+use rusqlite::types::Value;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum H3Metric {
+    Count,
+    Speed,
+    Energy,
+    Fuel,
+    Vehicles,
+}
+
+impl H3Metric {
+    pub fn all() -> Vec<H3Metric> {
+        vec![
+            H3Metric::Count,
+            H3Metric::Speed,
+            H3Metric::Energy,
+            H3Metric::Fuel,
+            H3Metric::Vehicles,
+        ]
+    }
+
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            H3Metric::Count => "point_count",
+            H3Metric::Speed => "mean_speed",
+            H3Metric::Energy => "mean_energy_consumption",
+            H3Metric::Fuel => "mean_fuel_rate",
+            H3Metric::Vehicles => "vehicle_count",
+        }
+    }
+}
+
+/// Signal statistics for one resolution 12 cell and vehicle
+pub struct H3SignalStats {
+    pub h3_12: u64,
+    pub vehicle_id: i64,
+    pub point_count: i64,
+    pub speed_sum: f64,
+    pub speed_count: i64,
+    pub energy_sum: f64,
+    pub energy_count: i64,
+    pub fuel_sum: f64,
+    pub fuel_count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct H3Aggregate {
+    pub h3: u64,
+    pub point_count: i64,
+    pub mean_speed: Option<f64>,
+    pub mean_energy_consumption: Option<f64>,
+    pub mean_fuel_rate: Option<f64>,
+    pub vehicle_count: i64,
+}
+
+impl H3Aggregate {
+    pub fn metric_value(&self, metric: H3Metric) -> Value {
+        let real = |v: Option<f64>| v.map(Value::Real).unwrap_or(Value::Null);
+        match metric {
+            H3Metric::Count => Value::Integer(self.point_count),
+            H3Metric::Speed => real(self.mean_speed),
+            H3Metric::Energy => real(self.mean_energy_consumption),
+            H3Metric::Fuel => real(self.mean_fuel_rate),
+            H3Metric::Vehicles => Value::Integer(self.vehicle_count),
+        }
+    }
+}
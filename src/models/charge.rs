@@ -0,0 +1,25 @@
+use bon::Builder;
+
+/// State of charge bounds of a PHEV or EV trajectory
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripSoc {
+    pub traj_id: i64,
+    pub vehicle_id: i64,
+    pub dt_ini: Option<String>,
+    pub dt_end: Option<String>,
+    pub soc_ini: Option<f64>,
+    pub soc_end: Option<f64>,
+}
+
+#[derive(Builder, Debug, Clone, PartialEq)]
+pub struct ChargeEvent {
+    pub vehicle_id: i64,
+    pub day: String,
+    pub traj_id_before: i64,
+    pub traj_id_after: i64,
+    pub dt_ini: String,
+    pub dt_end: String,
+    pub gap_s: f64,
+    pub soc_ini: f64,
+    pub soc_end: f64,
+}
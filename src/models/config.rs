@@ -1,32 +1,521 @@
+use crate::matcher::SEARCH_RADIUS_M;
+use crate::tools::{DEFAULT_BASE_DATE, DEFAULT_TIME_ZONE};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Config file of the project scope, read from and written to the current directory
+pub const PROJECT_FILE: &str = "evedb.toml";
+
+/// Table of the config files that holds the named profiles
+const PROFILES: &str = "profiles";
+
+/// Environment variables of earlier versions, read as aliases of the `EVEDB_*` ones
+const LEGACY_ENV: [(&str, &str); 5] = [
+    ("valhalla.url", "VALHALLA_URL"),
+    ("valhalla.timeout_s", "VALHALLA_TIMEOUT"),
+    ("valhalla.costing", "VALHALLA_COSTING"),
+    ("valhalla.search_radius_m", "VALHALLA_SEARCH_RADIUS"),
+    ("valhalla.gps_accuracy_m", "VALHALLA_GPS_ACCURACY"),
+];
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValhallaCosting {
+    Auto,
+    Truck,
+}
+
+/// Valhalla connection and map-matching options. `url` may list several instances
+/// separated by commas.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ValhallaConfig {
+    pub url: String,
+    pub timeout_s: u64,
+    pub costing: ValhallaCosting,
+    pub search_radius_m: f64,
+    pub gps_accuracy_m: f64,
+}
+
+impl Default for ValhallaConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8002/".to_string(),
+            timeout_s: 60,
+            costing: ValhallaCosting::Auto,
+            search_radius_m: SEARCH_RADIUS_M,
+            gps_accuracy_m: 5.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct Config {
     pub repo_path: String,
-
     pub db_path: String,
+    pub base_date: String,
+    pub time_zone: String,
+    pub valhalla: ValhallaConfig,
 }
 
-impl Config {
-    pub fn new(repo_path: String, db_path: String) -> Self {
-        Self { repo_path, db_path }
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            repo_path: "./data/eved/repo".to_string(),
+            db_path: "./data/eved/evedb.db".to_string(),
+            base_date: DEFAULT_BASE_DATE.to_string(),
+            time_zone: DEFAULT_TIME_ZONE.to_string(),
+            valhalla: ValhallaConfig::default(),
+        }
     }
+}
 
-    pub fn save(&self) {
-        let json = serde_json::to_string(&self).unwrap();
-        std::fs::write("./evedb.json", json).unwrap();
+/// Config file layers, from the lowest to the highest precedence
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigScope {
+    System,
+    User,
+    Project,
+}
+
+/// Effective value of a config key and the layer it was taken from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValue {
+    pub value: Value,
+    pub source: String,
+}
+
+/// Config files of a scope, from the most to the least important. Only the system
+/// scope may have several, one per directory of `XDG_CONFIG_DIRS`. The project scope
+/// is `project_file`, normally [`PROJECT_FILE`].
+pub fn config_paths(
+    scope: ConfigScope,
+    project_file: &Path,
+    env: &impl Fn(&str) -> Option<String>,
+) -> Vec<PathBuf> {
+    let var = |name: &str| env(name).filter(|v| !v.is_empty());
+    let in_dir = |dir: PathBuf| dir.join("evedb").join("config.toml");
+    match scope {
+        ConfigScope::System => var("XDG_CONFIG_DIRS")
+            .unwrap_or_else(|| "/etc/xdg".to_string())
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| in_dir(PathBuf::from(dir)))
+            .collect(),
+        ConfigScope::User => var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(in_dir)
+            .into_iter()
+            .collect(),
+        ConfigScope::Project => vec![project_file.to_path_buf()],
     }
+}
 
-    pub fn load() -> Self {
-        if Path::new("./evedb.json").exists() {
-            let json = std::fs::read_to_string("./evedb.json").unwrap();
-            serde_json::from_str(&json).unwrap()
+/// Environment variable that overrides a key, such as `EVEDB_VALHALLA_URL`
+pub fn env_var(key: &str) -> String {
+    format!("EVEDB_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// Default value of every key, by dotted key name
+fn defaults() -> BTreeMap<String, Value> {
+    let table = Table::try_from(Config::default()).expect("the default config is valid TOML");
+    let mut values = BTreeMap::new();
+    flatten("", table, &mut values);
+    values
+}
+
+fn flatten(prefix: &str, table: Table, values: &mut BTreeMap<String, Value>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
         } else {
-            Self::new(
-                "./data/eved/repo".to_string(),
-                "./data/eved/evedb.db".to_string(),
-            )
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Table(table) => flatten(&key, table, values),
+            value => {
+                values.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Checks that `value` has the type of the key's default, widening integers to floats
+fn coerce(key: &str, value: Value, default: &Value) -> Result<Value> {
+    match (value, default) {
+        (Value::Integer(i), Value::Float(_)) => Ok(Value::Float(i as f64)),
+        (value, default) if value.same_type(default) => Ok(value),
+        (value, default) => bail!(
+            "`{}` must be of type {}, not {}",
+            key,
+            default.type_str(),
+            value.type_str()
+        ),
+    }
+}
+
+/// Parses a value given on the command line or in an environment variable
+fn parse_value(key: &str, raw: &str, default: &Value) -> Result<Value> {
+    let value = match default {
+        Value::String(_) => Value::String(raw.to_string()),
+        Value::Integer(_) => Value::Integer(
+            raw.trim()
+                .parse()
+                .with_context(|| format!("`{}` must be an integer, not \"{}\"", key, raw))?,
+        ),
+        Value::Float(_) => Value::Float(
+            raw.trim()
+                .parse()
+                .with_context(|| format!("`{}` must be a number, not \"{}\"", key, raw))?,
+        ),
+        Value::Boolean(_) => Value::Boolean(
+            raw.trim()
+                .parse()
+                .with_context(|| format!("`{}` must be true or false, not \"{}\"", key, raw))?,
+        ),
+        _ => bail!("`{}` cannot be set from a string", key),
+    };
+    check_value(key, &value)?;
+    Ok(value)
+}
+
+/// Rejects values of the right type that the config still cannot hold, such as an
+/// unknown costing model
+fn check_value(key: &str, value: &Value) -> Result<()> {
+    let mut table = Table::new();
+    insert(&mut table, key, value.clone());
+    Config::deserialize(table)
+        .map(|_| ())
+        .map_err(|e| anyhow!("invalid value {} for `{}`: {}", value, key, e.message()))
+}
+
+/// Sets a dotted key in a table, creating the intermediate tables
+fn insert(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let entry = table
+                .entry(head)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+            if let Value::Table(inner) = entry {
+                insert(inner, rest, value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), value);
         }
     }
 }
+
+/// Reads a config file, or returns `None` when it does not exist
+fn read_table(path: &Path) -> Result<Option<Table>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    text.parse::<Table>()
+        .map(Some)
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Applies the keys of one layer over the effective values
+fn apply(
+    table: Table,
+    source: &str,
+    defaults: &BTreeMap<String, Value>,
+    values: &mut BTreeMap<String, ConfigValue>,
+) -> Result<()> {
+    let mut layer = BTreeMap::new();
+    flatten("", table, &mut layer);
+    for (key, value) in layer {
+        let default = defaults
+            .get(&key)
+            .ok_or_else(|| anyhow!("unknown key `{}` in {}", key, source))?;
+        let value = coerce(&key, value, default).with_context(|| format!("in {}", source))?;
+        check_value(&key, &value).with_context(|| format!("in {}", source))?;
+        values.insert(
+            key,
+            ConfigValue {
+                value,
+                source: source.to_string(),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Resolves every key from, by increasing precedence, the defaults, the system, user
+/// and project files, the selected profile of those files and the `EVEDB_*`
+/// environment variables. A `VALHALLA_*` variable stands in for its `EVEDB_*`
+/// counterpart when that one is not set.
+pub fn load_values(
+    project_file: &Path,
+    profile: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<BTreeMap<String, ConfigValue>> {
+    let defaults = defaults();
+    let mut values: BTreeMap<String, ConfigValue> = defaults
+        .iter()
+        .map(|(key, value)| {
+            let value = ConfigValue {
+                value: value.clone(),
+                source: "default".to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+
+    let mut profiles = Vec::new();
+    for scope in [ConfigScope::System, ConfigScope::User, ConfigScope::Project] {
+        for path in config_paths(scope, project_file, &env).into_iter().rev() {
+            let Some(mut table) = read_table(&path)? else {
+                continue;
+            };
+            let source = path.display().to_string();
+            match table.remove(PROFILES) {
+                Some(Value::Table(named)) => profiles.push((source.clone(), named)),
+                Some(_) => bail!("`{}` must be a table in {}", PROFILES, source),
+                None => {}
+            }
+            apply(table, &source, &defaults, &mut values)?;
+        }
+    }
+
+    if let Some(profile) = profile {
+        let mut found = false;
+        for (source, mut named) in profiles {
+            match named.remove(profile) {
+                Some(Value::Table(table)) => {
+                    found = true;
+                    let source = format!("{} [{}.{}]", source, PROFILES, profile);
+                    apply(table, &source, &defaults, &mut values)?;
+                }
+                Some(_) => bail!("profile `{}` must be a table in {}", profile, source),
+                None => {}
+            }
+        }
+        if !found {
+            bail!("profile `{}` is not defined in any config file", profile);
+        }
+    }
+
+    for (key, default) in &defaults {
+        let legacy = LEGACY_ENV
+            .iter()
+            .find(|(legacy_key, _)| legacy_key == key)
+            .map(|(_, var)| var.to_string());
+        let set = std::iter::once(env_var(key))
+            .chain(legacy)
+            .find_map(|var| env(&var).map(|raw| (var, raw)));
+        if let Some((var, raw)) = set {
+            let value = parse_value(key, &raw, default).with_context(|| format!("in {}", var))?;
+            values.insert(key.clone(), ConfigValue { value, source: var });
+        }
+    }
+    Ok(values)
+}
+
+/// Writes keys to a config file, or to a profile of that file
+fn write_values(path: &Path, profile: Option<&str>, values: Vec<(String, Value)>) -> Result<()> {
+    let mut table = read_table(path)?.unwrap_or_default();
+    for (key, value) in values {
+        let key = match profile {
+            Some(profile) => format!("{}.{}.{}", PROFILES, profile, key),
+            None => key,
+        };
+        insert(&mut table, &key, value);
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let text = toml::to_string_pretty(&table)?;
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Parses and writes one key to a config file, returning the value written
+pub fn set_value(path: &Path, profile: Option<&str>, key: &str, raw: &str) -> Result<Value> {
+    let default = defaults()
+        .remove(key)
+        .ok_or_else(|| anyhow!("unknown key `{}`", key))?;
+    let value = parse_value(key, raw, &default)?;
+    write_values(path, profile, vec![(key.to_string(), value.clone())])?;
+    Ok(value)
+}
+
+/// Writes the keys of `config` that differ from the effective `values`, returning
+/// their names
+pub fn save_changes(
+    path: &Path,
+    profile: Option<&str>,
+    config: &Config,
+    values: &BTreeMap<String, ConfigValue>,
+) -> Result<Vec<String>> {
+    let mut current = BTreeMap::new();
+    flatten("", Table::try_from(config)?, &mut current);
+    let changes: Vec<(String, Value)> = current
+        .into_iter()
+        .filter(|(key, value)| values.get(key).is_none_or(|v| &v.value != value))
+        .collect();
+    let keys = changes.iter().map(|(key, _)| key.clone()).collect();
+    if !changes.is_empty() {
+        write_values(path, profile, changes)?;
+    }
+    Ok(keys)
+}
+
+impl Config {
+    pub fn from_values(values: &BTreeMap<String, ConfigValue>) -> Result<Self> {
+        let mut table = Table::new();
+        for (key, config_value) in values {
+            insert(&mut table, key, config_value.value.clone());
+        }
+        Ok(Config::deserialize(table)?)
+    }
+
+    /// Loads the layered config, see [`load_values`]
+    pub fn load(
+        project_file: &Path,
+        profile: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        Self::from_values(&load_values(project_file, profile, env)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    /// Reads the system and user scopes from directories of `dir` instead of the real
+    /// ones, alongside the given variables
+    fn isolated(dir: &Path, vars: &[(&str, &str)]) -> HashMap<String, String> {
+        let mut env: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        for (name, sub) in [("XDG_CONFIG_DIRS", "system"), ("XDG_CONFIG_HOME", "user")] {
+            let path = dir.join(sub);
+            std::fs::create_dir_all(path.join("evedb")).unwrap();
+            env.insert(name.to_string(), path.to_str().unwrap().to_string());
+        }
+        env
+    }
+
+    #[test]
+    fn test_layered_config() {
+        let dir = tempdir().unwrap();
+        let system = dir.path().join("system");
+        let user = dir.path().join("user");
+        let project = dir.path().join(PROJECT_FILE);
+        let vars = isolated(
+            dir.path(),
+            &[
+                ("EVEDB_VALHALLA_COSTING", "truck"),
+                ("VALHALLA_COSTING", "auto"),
+                ("VALHALLA_URL", "http://legacy:8002/"),
+            ],
+        );
+        let env = |name: &str| vars.get(name).cloned();
+        std::fs::write(
+            system.join("evedb/config.toml"),
+            "db_path = \"/srv/evedb.db\"\n[valhalla]\ntimeout_s = 30\ngps_accuracy_m = 8\n",
+        )
+        .unwrap();
+        std::fs::write(
+            user.join("evedb/config.toml"),
+            "repo_path = \"/home/me/repo\"\n\
+             [profiles.cluster]\ndb_path = \"/scratch/evedb.db\"\nvalhalla.search_radius_m = 50\n",
+        )
+        .unwrap();
+        std::fs::write(&project, "[valhalla]\ngps_accuracy_m = 3.5\n").unwrap();
+
+        let config = Config::load(&project, None, env).unwrap();
+        assert_eq!(config.db_path, "/srv/evedb.db");
+        assert_eq!(config.repo_path, "/home/me/repo");
+        assert_eq!(config.valhalla.timeout_s, 30);
+        assert_eq!(config.valhalla.gps_accuracy_m, 3.5);
+        assert_eq!(config.valhalla.costing, ValhallaCosting::Truck);
+        assert_eq!(config.time_zone, DEFAULT_TIME_ZONE);
+
+        let values = load_values(&project, Some("cluster"), env).unwrap();
+        assert_eq!(values["db_path"].value.as_str(), Some("/scratch/evedb.db"));
+        assert!(values["db_path"].source.ends_with("[profiles.cluster]"));
+        assert_eq!(values["valhalla.search_radius_m"].value, Value::Float(50.0));
+        assert_eq!(values["valhalla.costing"].source, "EVEDB_VALHALLA_COSTING");
+        assert_eq!(
+            values["valhalla.url"].value.as_str(),
+            Some("http://legacy:8002/")
+        );
+        assert_eq!(values["valhalla.url"].source, "VALHALLA_URL");
+
+        let missing = load_values(&project, Some("laptop"), env).unwrap_err();
+        assert!(missing.to_string().contains("profile `laptop`"));
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join(PROJECT_FILE);
+        let vars = isolated(dir.path(), &[]);
+        let env = |name: &str| vars.get(name).cloned();
+        let path = config_paths(ConfigScope::User, &project, &env).remove(0);
+
+        for (text, expected) in [
+            ("db_path = ", "failed to parse"),
+            ("db_pth = \"x\"", "unknown key `db_pth`"),
+            (
+                "[valhalla]\ntimeout_s = \"slow\"",
+                "`valhalla.timeout_s` must be of type integer",
+            ),
+            ("[valhalla]\ncosting = \"bike\"", "invalid value \"bike\""),
+        ] {
+            std::fs::write(&path, text).unwrap();
+            let error = format!("{:#}", load_values(&project, None, env).unwrap_err());
+            assert!(error.contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_set_value() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join(PROJECT_FILE);
+        let vars = isolated(dir.path(), &[]);
+        let env = |name: &str| vars.get(name).cloned();
+
+        set_value(&path, None, "db_path", "/data/evedb.db").unwrap();
+        set_value(&path, Some("laptop"), "valhalla.timeout_s", "15").unwrap();
+        assert!(set_value(&path, None, "valhalla.timeout_s", "soon").is_err());
+        assert!(set_value(&path, None, "valhalla.costing", "bike").is_err());
+        assert!(set_value(&path, None, "colour", "red").is_err());
+
+        let values = load_values(&path, None, env).unwrap();
+        assert_eq!(values["db_path"].value.as_str(), Some("/data/evedb.db"));
+        let config = Config {
+            time_zone: "UTC".to_string(),
+            ..Config::from_values(&values).unwrap()
+        };
+        let changed = save_changes(&path, None, &config, &values).unwrap();
+        assert_eq!(changed, vec!["time_zone".to_string()]);
+
+        let table = read_table(&path).unwrap().unwrap();
+        assert_eq!(table["db_path"].as_str(), Some("/data/evedb.db"));
+        assert_eq!(table["time_zone"].as_str(), Some("UTC"));
+        assert_eq!(
+            table["profiles"]["laptop"]["valhalla"]["timeout_s"].as_integer(),
+            Some(15)
+        );
+    }
+}
@@ -14,3 +14,17 @@ pub struct TrajectoryEdge {
     pub trajectory_id: i64,
     pub edge_id: i64,
 }
+
+/// Edge of the observed road network with its traversal count and the mean speed
+/// (km/h) over the traversals with an observed travel time, their total length over
+/// their total time
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkEdge {
+    pub id: i64,
+    pub node_ini: i64,
+    pub node_end: i64,
+    pub length_m: f64,
+    pub bearing_deg: f64,
+    pub traversals: i64,
+    pub mean_speed_kmh: Option<f64>,
+}
@@ -0,0 +1,52 @@
+use bon::Builder;
+
+/// Kinds of flagged road features that signals pass through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Intersection,
+    BusStop,
+}
+
+impl EventKind {
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            EventKind::Intersection => "intersection_event",
+            EventKind::BusStop => "bus_stop_event",
+        }
+    }
+}
+
+pub struct EventSignal {
+    pub day_num: f64,
+    pub time_stamp: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub speed: Option<f64>,
+    pub intersection: bool,
+    pub bus_stop: bool,
+}
+
+impl EventSignal {
+    pub fn is_flagged(&self, kind: EventKind) -> bool {
+        match kind {
+            EventKind::Intersection => self.intersection,
+            EventKind::BusStop => self.bus_stop,
+        }
+    }
+}
+
+#[derive(Builder, Debug, Clone, PartialEq)]
+pub struct PassageEvent {
+    pub traj_id: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub h3_12: u64,
+    pub time_stamp_ini: i64,
+    pub time_stamp_end: i64,
+    pub dt_ini: String,
+    pub dt_end: String,
+    pub duration_s: f64,
+    pub approach_speed: Option<f64>,
+    pub min_speed: Option<f64>,
+    pub stopped: bool,
+}
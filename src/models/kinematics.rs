@@ -0,0 +1,12 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalKinematics {
+    pub signal_id: i64,
+    pub traj_id: i64,
+    pub dt_s: Option<f64>,
+    pub step_m: Option<f64>,
+    pub distance_m: f64,
+    pub speed: Option<f64>,
+    pub speed_interpolated: bool,
+    pub acceleration: Option<f64>,
+    pub jerk: Option<f64>,
+}
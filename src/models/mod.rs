@@ -1,7 +1,17 @@
+pub mod aggregate;
+pub mod charge;
 pub mod config;
-mod edge;
+pub mod edge;
+pub mod event;
+pub mod kinematics;
 pub mod node;
+pub mod od;
+pub mod osm;
+pub mod quality;
+pub mod route;
 mod segment;
 pub mod signal;
+pub mod stop;
 pub mod trajectory;
+pub mod turn;
 pub mod vehicle;
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+pub struct OdTrip {
+    pub vehicle_type: Option<String>,
+    pub dt_ini: String,
+    pub duration_s: f64,
+    pub length_m: f64,
+    pub h3_12_ini: u64,
+    pub h3_12_end: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OdCell {
+    pub h3_ini: u64,
+    pub h3_end: u64,
+    pub resolution: u8,
+    pub vehicle_type: Option<String>,
+    pub hour: u32,
+    pub weekday: u32,
+    pub trip_count: i64,
+    pub median_duration_s: f64,
+    pub median_length_m: f64,
+}
@@ -0,0 +1,125 @@
+/// Values of the `highway` tag for the ways that cars can drive on
+pub const DRIVABLE_HIGHWAYS: [&str; 15] = [
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "primary",
+    "primary_link",
+    "secondary",
+    "secondary_link",
+    "tertiary",
+    "tertiary_link",
+    "unclassified",
+    "residential",
+    "living_street",
+    "service",
+    "road",
+];
+
+/// Drivable OSM way, with its nodes in the direction of travel when it is one-way
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsmWay {
+    pub id: i64,
+    pub highway: String,
+    pub name: Option<String>,
+    pub oneway: bool,
+    pub max_speed_kmh: Option<f64>,
+    pub length_m: f64,
+    pub nodes: Vec<i64>,
+}
+
+/// Parses a `maxspeed` value such as `"50"` or `"35 mph"` into km/h
+fn parse_max_speed(value: &str) -> Option<f64> {
+    let value = value.trim();
+    match value.strip_suffix("mph") {
+        Some(mph) => mph.trim().parse::<f64>().ok().map(|s| s * 1.609344),
+        None => value.parse::<f64>().ok(),
+    }
+}
+
+impl OsmWay {
+    /// Builds the way from its tags, or returns `None` when it is not drivable
+    pub fn from_tags<'a>(
+        id: i64,
+        tags: impl Iterator<Item = (&'a str, &'a str)>,
+        mut nodes: Vec<i64>,
+    ) -> Option<OsmWay> {
+        let (mut highway, mut name, mut oneway, mut max_speed) = (None, None, None, None);
+        let mut closed = false;
+        for (key, value) in tags {
+            match key {
+                "highway" => highway = Some(value),
+                "name" => name = Some(value.to_string()),
+                "oneway" => oneway = Some(value),
+                "junction" => closed = value == "roundabout" || value == "circular",
+                "maxspeed" => max_speed = parse_max_speed(value),
+                "access" | "motor_vehicle" if value == "no" => return None,
+                "area" if value == "yes" => return None,
+                _ => {}
+            }
+        }
+
+        let highway = highway.filter(|h| DRIVABLE_HIGHWAYS.contains(h))?;
+        if nodes.len() < 2 {
+            return None;
+        }
+        let oneway = match oneway {
+            Some("yes" | "1" | "true") => true,
+            Some("-1" | "reverse") => {
+                nodes.reverse();
+                true
+            }
+            Some(_) => false,
+            None => closed || highway == "motorway",
+        };
+
+        Some(OsmWay {
+            id,
+            highway: highway.to_string(),
+            name,
+            oneway,
+            max_speed_kmh: max_speed,
+            length_m: 0.0,
+            nodes,
+        })
+    }
+}
+
+/// OSM length and the part of it traversed by matched edges for one road class
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoadClassCoverage {
+    pub highway: String,
+    pub osm_length_m: f64,
+    pub covered_length_m: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_way_from_tags() {
+        let way = OsmWay::from_tags(
+            7,
+            [("highway", "primary"), ("maxspeed", "35 mph"), ("oneway", "-1")].into_iter(),
+            vec![1, 2, 3],
+        )
+        .unwrap();
+        assert!(way.oneway);
+        assert_eq!(way.nodes, vec![3, 2, 1]);
+        assert!((way.max_speed_kmh.unwrap() - 56.33).abs() < 0.01);
+
+        let roundabout =
+            OsmWay::from_tags(8, [("highway", "tertiary"), ("junction", "roundabout")].into_iter(), vec![1, 2, 1])
+                .unwrap();
+        assert!(roundabout.oneway);
+
+        assert_eq!(OsmWay::from_tags(9, [("highway", "footway")].into_iter(), vec![1, 2]), None);
+        assert_eq!(
+            OsmWay::from_tags(10, [("highway", "service"), ("access", "no")].into_iter(), vec![1, 2]),
+            None
+        );
+        assert_eq!(OsmWay::from_tags(11, [("highway", "residential")].into_iter(), vec![1]), None);
+    }
+}
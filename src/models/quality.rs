@@ -0,0 +1,13 @@
+/// Agreement between a trajectory's GPS points and its map-matched shape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchQuality {
+    pub traj_id: i64,
+    pub point_count: i64,
+    pub mean_distance_m: f64,
+    pub max_distance_m: f64,
+    pub matched_length_m: f64,
+    pub length_ratio: Option<f64>,
+    pub beyond_radius_share: f64,
+    pub eved_mean_distance_m: Option<f64>,
+    pub eved_max_distance_m: Option<f64>,
+}
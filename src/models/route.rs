@@ -0,0 +1,13 @@
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteWeight {
+    Length,
+    Time,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteAlgorithm {
+    Dijkstra,
+    Astar,
+}
@@ -73,3 +73,118 @@ pub struct CsvSignal {
     #[serde(rename = "Focus Points")]
     pub focus_points: Option<String>,
 }
+
+const KMH_PER_MPH: f64 = 1.609_344;
+
+/// Speed limits parsed from the packed eVED speed limit text. A single value applies to
+/// both directions; a pair such as "[40, 56]" holds the forward and backward limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedLimit {
+    pub kmh: f64,
+    pub backward_kmh: Option<f64>,
+}
+
+impl SpeedLimit {
+    pub fn direction(&self) -> &'static str {
+        match self.backward_kmh {
+            Some(backward) if backward != self.kmh => "split",
+            _ => "both",
+        }
+    }
+}
+
+impl CsvSignal {
+    /// Parses the speed limit text, converting values marked "mph" to km/h. Returns `None`
+    /// when the text holds no number.
+    pub fn parse_speed_limit(&self) -> Option<SpeedLimit> {
+        let text = self.speed_limit.as_deref()?.to_lowercase();
+        let factor = if text.contains("mph") { KMH_PER_MPH } else { 1.0 };
+        let mut values = text
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter_map(|v| v.parse::<f64>().ok())
+            .map(|v| v * factor);
+
+        Some(SpeedLimit {
+            kmh: values.next()?,
+            backward_kmh: values.next(),
+        })
+    }
+
+    /// Splits the focus points text into distinct normalized point types, e.g.
+    /// "Traffic Signals, stop" into "traffic_signals" and "stop".
+    pub fn parse_focus_points(&self) -> Vec<String> {
+        let mut point_types: Vec<String> = Vec::new();
+        let Some(text) = self.focus_points.as_deref() else {
+            return point_types;
+        };
+
+        for point in text.split([',', '|', '/', '[', ']', '(', ')']) {
+            let point_type = point
+                .trim()
+                .to_lowercase()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_");
+            if !point_type.is_empty() && point_type != "nan" && !point_types.contains(&point_type)
+            {
+                point_types.push(point_type);
+            }
+        }
+        point_types
+    }
+}
+
+pub struct TrajectorySignal {
+    pub signal_id: i64,
+    pub day_num: f64,
+    pub time_stamp: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub speed: Option<f64>,
+}
+
+/// Energy-related readings of a trajectory signal
+#[derive(Debug, Clone, Default)]
+pub struct EnergySignal {
+    pub time_stamp: i64,
+    pub fuel_rate: Option<f64>,
+    pub hv_bat_current: Option<f64>,
+    pub hv_bat_volt: Option<f64>,
+    pub hv_bat_soc: Option<f64>,
+    pub oat: Option<f64>,
+    pub ac_power_kw: Option<f64>,
+    pub ac_power_w: Option<f64>,
+    pub heater_power_w: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csv_signal(speed_limit: Option<&str>) -> CsvSignal {
+        let csv = "DayNum,VehId,Trip,Timestamp(ms),Latitude[deg],Longitude[deg],Vehicle Speed[km/h],MAF[g/sec],Engine RPM[RPM],Absolute Load[%],OAT[DegC],Fuel Rate[L/hr],Air Conditioning Power[kW],Air Conditioning Power[Watts],Heater Power[Watts],HV Battery Current[A],HV Battery SOC[%],HV Battery Voltage[V],Short Term Fuel Trim Bank 1[%],Short Term Fuel Trim Bank 2[%],Long Term Fuel Trim Bank 1[%],Long Term Fuel Trim Bank 2[%],Elevation Raw[m],Elevation Smoothed[m],Gradient,Energy Consumption[,Matchted Latitude[deg],Matched Longitude[deg],Match Type,Class of Speed Limit,Speed Limit[km/h],Speed Limit Direction[km/h],Intersection,Bus Stops,Focus Points
+1,10,100,1000,42.1,-83.1,60.0,,,,,,,,,,,,,,,,,,,,42.1,-83.1,1,,,,,,
+";
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let mut signal: CsvSignal = reader.deserialize().next().unwrap().unwrap();
+        signal.speed_limit = speed_limit.map(String::from);
+        signal
+    }
+
+    #[test]
+    fn test_parse_speed_limit() {
+        assert_eq!(
+            csv_signal(Some("50")).parse_speed_limit(),
+            Some(SpeedLimit {
+                kmh: 50.0,
+                backward_kmh: None,
+            })
+        );
+        assert_eq!(csv_signal(Some("(56, 56)")).parse_speed_limit().unwrap().direction(), "both");
+
+        let limit = csv_signal(Some("25 mph")).parse_speed_limit().unwrap();
+        assert!((limit.kmh - 40.2336).abs() < 1e-9);
+        assert_eq!(csv_signal(Some("nan")).parse_speed_limit(), None);
+        assert_eq!(csv_signal(None).parse_speed_limit(), None);
+    }
+}
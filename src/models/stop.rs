@@ -0,0 +1,15 @@
+use bon::Builder;
+
+#[derive(Builder, Debug, Clone, PartialEq)]
+pub struct StopEvent {
+    pub traj_id: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub h3_12: u64,
+    pub time_stamp_ini: i64,
+    pub time_stamp_end: i64,
+    pub dt_ini: String,
+    pub dt_end: String,
+    pub duration_s: f64,
+    pub node_id: Option<i64>,
+}
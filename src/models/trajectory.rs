@@ -15,8 +15,42 @@ pub struct TrajectoryUpdate {
     pub duration_s: f64,
     pub h3_12_ini: u64,
     pub h3_12_end: u64,
+    pub energy: TrajectoryEnergy,
 }
 
+/// Per-trip fuel and battery totals. Fuel fields are set for trips with `fuel_rate`
+/// readings (ICE/HEV) and battery fields for trips with HV battery readings (PHEV/EV).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrajectoryEnergy {
+    pub fuel_l: Option<f64>,
+    pub fuel_l_per_100km: Option<f64>,
+    pub energy_kwh: Option<f64>,
+    pub energy_kwh_per_km: Option<f64>,
+    pub soc_ini: Option<f64>,
+    pub soc_end: Option<f64>,
+    pub mean_oat: Option<f64>,
+    pub ac_energy_kwh: Option<f64>,
+    pub heater_energy_kwh: Option<f64>,
+}
+
+/// Signal time stamp bounds of each sub-trajectory of a split trip
+pub struct TrajectorySplit {
+    pub trajectory_id: i64,
+    pub bounds: Vec<(i64, i64)>,
+}
+
+pub struct TrajectoryRegen {
+    pub trajectory_id: i64,
+    pub regen_kwh: Option<f64>,
+}
+
+pub struct TrajectoryStops {
+    pub trajectory_id: i64,
+    pub stop_count: i64,
+    pub idle_s: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct WayPoint {
     pub time: i64,
     pub latitude: f64,
@@ -0,0 +1,30 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnType {
+    Left,
+    Right,
+    Straight,
+    UTurn,
+}
+
+impl TurnType {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TurnType::Left => "left",
+            TurnType::Right => "right",
+            TurnType::Straight => "straight",
+            TurnType::UTurn => "u_turn",
+        }
+    }
+}
+
+/// Movement from one edge onto the next through their shared node, with the number of
+/// trajectories that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub from_edge: i64,
+    pub via_node: i64,
+    pub to_edge: i64,
+    pub angle_deg: f64,
+    pub turn_type: TurnType,
+    pub turn_count: i64,
+}
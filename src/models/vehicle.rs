@@ -1,9 +1,302 @@
+/// A vehicle category stored as a code in the `vehicle` table and backed by a lookup
+/// table. Values that are not recognized keep their text, trimmed and uppercased so that
+/// case variants share a code.
+pub trait VehicleCategory: Sized {
+    /// Name of the lookup table
+    const TABLE: &'static str;
+
+    /// Parses a value from the VED static data spreadsheets
+    fn parse(text: &str) -> Self;
+
+    /// The recognized categories
+    fn known() -> Vec<Self>;
+
+    fn code(&self) -> &str;
+
+    fn description(&self) -> Option<&'static str>;
+}
+
+/// Code of a value that no category recognizes
+fn unknown_code(text: &str) -> String {
+    text.trim().to_uppercase()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VehicleType {
+    Ice,
+    Hev,
+    Phev,
+    Ev,
+    Unknown(String),
+}
+
+impl VehicleCategory for VehicleType {
+    const TABLE: &'static str = "ref_vehicle_type";
+
+    fn parse(text: &str) -> Self {
+        match text.trim().to_uppercase().as_str() {
+            "ICE" => VehicleType::Ice,
+            "HEV" => VehicleType::Hev,
+            "PHEV" => VehicleType::Phev,
+            "EV" | "BEV" => VehicleType::Ev,
+            _ => VehicleType::Unknown(unknown_code(text)),
+        }
+    }
+
+    fn known() -> Vec<Self> {
+        vec![
+            VehicleType::Ice,
+            VehicleType::Hev,
+            VehicleType::Phev,
+            VehicleType::Ev,
+        ]
+    }
+
+    fn code(&self) -> &str {
+        match self {
+            VehicleType::Ice => "ICE",
+            VehicleType::Hev => "HEV",
+            VehicleType::Phev => "PHEV",
+            VehicleType::Ev => "EV",
+            VehicleType::Unknown(text) => text,
+        }
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            VehicleType::Ice => Some("Internal combustion engine"),
+            VehicleType::Hev => Some("Hybrid electric vehicle"),
+            VehicleType::Phev => Some("Plug-in hybrid electric vehicle"),
+            VehicleType::Ev => Some("Electric vehicle"),
+            VehicleType::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VehicleClass {
+    Car,
+    Suv,
+    Truck,
+    Unknown(String),
+}
+
+impl VehicleCategory for VehicleClass {
+    const TABLE: &'static str = "ref_vehicle_class";
+
+    fn parse(text: &str) -> Self {
+        let upper = text.trim().to_uppercase();
+        let has = |words: &[&str]| {
+            upper
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|token| words.contains(&token))
+        };
+        if has(&["SUV"]) {
+            VehicleClass::Suv
+        } else if has(&["TRUCK", "PICKUP"]) {
+            VehicleClass::Truck
+        } else if has(&["CAR", "SEDAN"]) {
+            VehicleClass::Car
+        } else {
+            VehicleClass::Unknown(unknown_code(text))
+        }
+    }
+
+    fn known() -> Vec<Self> {
+        vec![VehicleClass::Car, VehicleClass::Suv, VehicleClass::Truck]
+    }
+
+    fn code(&self) -> &str {
+        match self {
+            VehicleClass::Car => "CAR",
+            VehicleClass::Suv => "SUV",
+            VehicleClass::Truck => "TRUCK",
+            VehicleClass::Unknown(text) => text,
+        }
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            VehicleClass::Car => Some("Passenger car"),
+            VehicleClass::Suv => Some("Sport utility vehicle"),
+            VehicleClass::Truck => Some("Pickup or light truck"),
+            VehicleClass::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transmission {
+    Automatic,
+    Manual,
+    Cvt,
+    Unknown(String),
+}
+
+impl VehicleCategory for Transmission {
+    const TABLE: &'static str = "ref_transmission";
+
+    fn parse(text: &str) -> Self {
+        let upper = text.trim().to_uppercase();
+        if upper.contains("CVT") {
+            Transmission::Cvt
+        } else if upper.contains("AUTO") {
+            Transmission::Automatic
+        } else if upper.contains("MANUAL") {
+            Transmission::Manual
+        } else {
+            Transmission::Unknown(unknown_code(text))
+        }
+    }
+
+    fn known() -> Vec<Self> {
+        vec![Transmission::Automatic, Transmission::Manual, Transmission::Cvt]
+    }
+
+    fn code(&self) -> &str {
+        match self {
+            Transmission::Automatic => "AUTOMATIC",
+            Transmission::Manual => "MANUAL",
+            Transmission::Cvt => "CVT",
+            Transmission::Unknown(text) => text,
+        }
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            Transmission::Automatic => Some("Automatic transmission"),
+            Transmission::Manual => Some("Manual transmission"),
+            Transmission::Cvt => Some("Continuously variable transmission"),
+            Transmission::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveWheels {
+    Fwd,
+    Rwd,
+    Awd,
+    FourWd,
+    Unknown(String),
+}
+
+impl VehicleCategory for DriveWheels {
+    const TABLE: &'static str = "ref_drive_wheels";
+
+    fn parse(text: &str) -> Self {
+        match text.trim().to_uppercase().as_str() {
+            "FWD" => DriveWheels::Fwd,
+            "RWD" => DriveWheels::Rwd,
+            "AWD" => DriveWheels::Awd,
+            "4WD" | "4X4" => DriveWheels::FourWd,
+            _ => DriveWheels::Unknown(unknown_code(text)),
+        }
+    }
+
+    fn known() -> Vec<Self> {
+        vec![
+            DriveWheels::Fwd,
+            DriveWheels::Rwd,
+            DriveWheels::Awd,
+            DriveWheels::FourWd,
+        ]
+    }
+
+    fn code(&self) -> &str {
+        match self {
+            DriveWheels::Fwd => "FWD",
+            DriveWheels::Rwd => "RWD",
+            DriveWheels::Awd => "AWD",
+            DriveWheels::FourWd => "4WD",
+            DriveWheels::Unknown(text) => text,
+        }
+    }
+
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            DriveWheels::Fwd => Some("Front-wheel drive"),
+            DriveWheels::Rwd => Some("Rear-wheel drive"),
+            DriveWheels::Awd => Some("All-wheel drive"),
+            DriveWheels::FourWd => Some("Four-wheel drive"),
+            DriveWheels::Unknown(_) => None,
+        }
+    }
+}
+
+/// Engine details parsed from the VED engine description, e.g. "4-GAS 2.0L T/C"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EngineSpec {
+    pub displacement_l: Option<f64>,
+    pub cylinders: Option<i64>,
+    pub turbo: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightUnit {
+    Kilogram,
+    Pound,
+}
+
+impl WeightUnit {
+    pub fn code(&self) -> &'static str {
+        match self {
+            WeightUnit::Kilogram => "kg",
+            WeightUnit::Pound => "lb",
+        }
+    }
+
+    pub fn to_kg(&self, value: f64) -> f64 {
+        match self {
+            WeightUnit::Kilogram => value,
+            WeightUnit::Pound => value * 0.453_592_37,
+        }
+    }
+}
+
 pub struct Vehicle {
     pub vehicle_id: i64,
-    pub vehicle_type: Option<String>,
-    pub vehicle_class: Option<String>,
+    pub vehicle_type: Option<VehicleType>,
+    pub vehicle_class: Option<VehicleClass>,
     pub engine: Option<String>,
-    pub transmission: Option<String>,
-    pub drive_wheels: Option<String>,
+    pub engine_spec: Option<EngineSpec>,
+    pub transmission: Option<Transmission>,
+    pub drive_wheels: Option<DriveWheels>,
     pub weight: Option<i64>,
+    pub weight_kg: Option<f64>,
+    pub weight_unit: Option<WeightUnit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vehicle_categories() {
+        assert_eq!(VehicleType::parse(" phev "), VehicleType::Phev);
+        assert_eq!(
+            VehicleType::parse("FCEV"),
+            VehicleType::Unknown("FCEV".to_string())
+        );
+        assert_eq!(VehicleClass::parse("Pickup Truck"), VehicleClass::Truck);
+        assert_eq!(VehicleClass::parse("Sedan"), VehicleClass::Car);
+        assert_eq!(VehicleClass::parse("Passenger Car"), VehicleClass::Car);
+        assert_eq!(
+            VehicleClass::parse("Cargo Van"),
+            VehicleClass::Unknown("CARGO VAN".to_string())
+        );
+        assert_eq!(Transmission::parse("5-SP AUTOMATIC"), Transmission::Automatic);
+        assert_eq!(Transmission::parse("CVT"), Transmission::Cvt);
+        assert_eq!(DriveWheels::parse("4x4"), DriveWheels::FourWd);
+        assert_eq!(DriveWheels::parse("4x4").code(), "4WD");
+    }
+
+    #[test]
+    fn test_unknown_category_is_normalized() {
+        let wheels = DriveWheels::parse("6WD");
+        assert_eq!(wheels.code(), "6WD");
+        assert_eq!(wheels.description(), None);
+        assert!(!DriveWheels::known().contains(&wheels));
+        assert_eq!(DriveWheels::parse(" 6wd"), wheels);
+    }
 }
@@ -0,0 +1,303 @@
+use crate::models::edge::NetworkEdge;
+use crate::models::node::Node;
+use crate::models::route::{RouteAlgorithm, RouteWeight};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Speed (km/h) assumed for edges that no timed trajectory traverses
+pub const DEFAULT_SPEED_KMH: f64 = 40.0;
+
+struct Arc {
+    edge_id: i64,
+    node_end: i64,
+    length_m: f64,
+    time_s: f64,
+}
+
+/// Shortest path between two nodes, with its total length and travel time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub nodes: Vec<Node>,
+    pub edge_ids: Vec<i64>,
+    pub length_m: f64,
+    pub time_s: f64,
+}
+
+#[derive(PartialEq)]
+struct QueueEntry {
+    priority: f64,
+    node_id: i64,
+}
+
+impl Eq for QueueEntry {}
+
+impl Ord for QueueEntry {
+    // Reversed so that the max-heap pops the lowest priority first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .total_cmp(&self.priority)
+            .then_with(|| other.node_id.cmp(&self.node_id))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Directed graph of the observed road network, built from the `node` and `edge` tables
+pub struct RoadGraph {
+    nodes: HashMap<i64, Node>,
+    arcs: HashMap<i64, Vec<Arc>>,
+    max_speed_mps: f64,
+}
+
+impl RoadGraph {
+    pub fn new(nodes: Vec<Node>, edges: &[NetworkEdge]) -> RoadGraph {
+        let mut arcs: HashMap<i64, Vec<Arc>> = HashMap::new();
+        let mut max_speed_mps: f64 = 0.0;
+        for edge in edges {
+            let speed_mps = edge
+                .mean_speed_kmh
+                .filter(|&s| s > 0.0)
+                .unwrap_or(DEFAULT_SPEED_KMH)
+                / 3.6;
+            max_speed_mps = max_speed_mps.max(speed_mps);
+            arcs.entry(edge.node_ini).or_default().push(Arc {
+                edge_id: edge.id,
+                node_end: edge.node_end,
+                length_m: edge.length_m,
+                time_s: edge.length_m / speed_mps,
+            });
+        }
+        RoadGraph {
+            nodes: nodes.into_iter().map(|n| (n.id, n)).collect(),
+            arcs,
+            max_speed_mps,
+        }
+    }
+
+    pub fn node(&self, id: i64) -> Option<&Node> {
+        self.nodes.get(&id)
+    }
+
+    /// Lengths of the shortest paths from `from` to every node reachable within
+    /// `limit_m`, with the previous node on each path. `from` is its own predecessor.
+    pub fn paths_within(&self, from: i64, limit_m: f64) -> HashMap<i64, (f64, i64)> {
+        let mut paths: HashMap<i64, (f64, i64)> = HashMap::from([(from, (0.0, from))]);
+        let mut queue = BinaryHeap::from([QueueEntry {
+            priority: 0.0,
+            node_id: from,
+        }]);
+
+        while let Some(QueueEntry { priority, node_id }) = queue.pop() {
+            if priority > paths[&node_id].0 {
+                continue;
+            }
+            for arc in self.arcs.get(&node_id).into_iter().flatten() {
+                let length_m = priority + arc.length_m;
+                if length_m <= limit_m
+                    && paths.get(&arc.node_end).is_none_or(|&(l, _)| length_m < l)
+                {
+                    paths.insert(arc.node_end, (length_m, node_id));
+                    queue.push(QueueEntry {
+                        priority: length_m,
+                        node_id: arc.node_end,
+                    });
+                }
+            }
+        }
+        paths
+    }
+
+    fn cost(arc: &Arc, weight: RouteWeight) -> f64 {
+        match weight {
+            RouteWeight::Length => arc.length_m,
+            RouteWeight::Time => arc.time_s,
+        }
+    }
+
+    /// Lower bound of the remaining cost from the straight-line distance, which edge
+    /// lengths never undercut
+    fn heuristic(&self, node_id: i64, target: &Node, weight: RouteWeight) -> f64 {
+        let Some(node) = self.nodes.get(&node_id) else {
+            return 0.0;
+        };
+        let distance_m = node.distance_to(target);
+        match weight {
+            RouteWeight::Length => distance_m,
+            RouteWeight::Time if self.max_speed_mps > 0.0 => distance_m / self.max_speed_mps,
+            RouteWeight::Time => 0.0,
+        }
+    }
+
+    /// Finds the cheapest path from `from` to `to`, or `None` when either node is
+    /// unknown or `to` is unreachable
+    pub fn shortest_path(
+        &self,
+        from: i64,
+        to: i64,
+        weight: RouteWeight,
+        algorithm: RouteAlgorithm,
+    ) -> Option<Route> {
+        let target = self.nodes.get(&to)?;
+        self.nodes.get(&from)?;
+
+        let estimate = |node_id: i64| match algorithm {
+            RouteAlgorithm::Dijkstra => 0.0,
+            RouteAlgorithm::Astar => self.heuristic(node_id, target, weight),
+        };
+
+        let mut costs: HashMap<i64, f64> = HashMap::from([(from, 0.0)]);
+        let mut previous: HashMap<i64, (i64, &Arc)> = HashMap::new();
+        let mut queue = BinaryHeap::from([QueueEntry {
+            priority: estimate(from),
+            node_id: from,
+        }]);
+
+        while let Some(QueueEntry { priority, node_id }) = queue.pop() {
+            if node_id == to {
+                break;
+            }
+            let cost = costs[&node_id];
+            if priority > cost + estimate(node_id) {
+                continue;
+            }
+            for arc in self.arcs.get(&node_id).into_iter().flatten() {
+                let next_cost = cost + Self::cost(arc, weight);
+                if costs.get(&arc.node_end).is_none_or(|&c| next_cost < c) {
+                    costs.insert(arc.node_end, next_cost);
+                    previous.insert(arc.node_end, (node_id, arc));
+                    queue.push(QueueEntry {
+                        priority: next_cost + estimate(arc.node_end),
+                        node_id: arc.node_end,
+                    });
+                }
+            }
+        }
+
+        if !costs.contains_key(&to) {
+            return None;
+        }
+
+        let mut route_arcs: Vec<&Arc> = Vec::new();
+        let mut node_ids = vec![to];
+        let mut current = to;
+        while let Some(&(prev, arc)) = previous.get(&current) {
+            route_arcs.push(arc);
+            node_ids.push(prev);
+            current = prev;
+        }
+        route_arcs.reverse();
+        node_ids.reverse();
+
+        Some(Route {
+            nodes: node_ids
+                .iter()
+                .filter_map(|id| self.nodes.get(id).copied())
+                .collect(),
+            edge_ids: route_arcs.iter().map(|a| a.edge_id).collect(),
+            length_m: route_arcs.iter().map(|a| a.length_m).sum(),
+            time_s: route_arcs.iter().map(|a| a.time_s).sum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: i64, latitude: f64, longitude: f64) -> Node {
+        Node {
+            id,
+            latitude,
+            longitude,
+            altitude: 0.0,
+            h3_12: 0,
+        }
+    }
+
+    fn edge(id: i64, from: &Node, to: &Node, mean_speed_kmh: Option<f64>) -> NetworkEdge {
+        NetworkEdge {
+            id,
+            node_ini: from.id,
+            node_end: to.id,
+            length_m: from.distance_to(to),
+            bearing_deg: from.bearing_to(to),
+            traversals: 1,
+            mean_speed_kmh,
+        }
+    }
+
+    /// A square where the direct 1 -> 2 -> 4 side is short but slow and the
+    /// 1 -> 3 -> 4 detour through a far node is long but fast
+    fn square() -> RoadGraph {
+        let n1 = node(1, 42.000, -83.700);
+        let n2 = node(2, 42.001, -83.700);
+        let n3 = node(3, 42.000, -83.690);
+        let n4 = node(4, 42.001, -83.699);
+        let edges = vec![
+            edge(12, &n1, &n2, Some(5.0)),
+            edge(24, &n2, &n4, Some(5.0)),
+            edge(13, &n1, &n3, Some(120.0)),
+            edge(34, &n3, &n4, Some(120.0)),
+        ];
+        RoadGraph::new(vec![n1, n2, n3, n4], &edges)
+    }
+
+    #[test]
+    fn test_shortest_path_by_length() {
+        let graph = square();
+        for algorithm in [RouteAlgorithm::Dijkstra, RouteAlgorithm::Astar] {
+            let route = graph
+                .shortest_path(1, 4, RouteWeight::Length, algorithm)
+                .unwrap();
+            let ids: Vec<i64> = route.nodes.iter().map(|n| n.id).collect();
+            assert_eq!(ids, vec![1, 2, 4]);
+            assert_eq!(route.edge_ids, vec![12, 24]);
+        }
+    }
+
+    #[test]
+    fn test_shortest_path_by_time() {
+        let graph = square();
+        for algorithm in [RouteAlgorithm::Dijkstra, RouteAlgorithm::Astar] {
+            let route = graph
+                .shortest_path(1, 4, RouteWeight::Time, algorithm)
+                .unwrap();
+            assert_eq!(route.edge_ids, vec![13, 34]);
+            assert!((route.time_s - route.length_m / (120.0 / 3.6)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_paths_within() {
+        let graph = square();
+        let paths = graph.paths_within(1, 200.0);
+        assert_eq!(paths[&1], (0.0, 1));
+        assert_eq!(paths[&2].1, 1);
+        assert_eq!(paths[&4].1, 2);
+        assert!(!paths.contains_key(&3));
+        assert_eq!(graph.paths_within(4, 1000.0).len(), 1);
+    }
+
+    #[test]
+    fn test_shortest_path_unreachable() {
+        let graph = square();
+        assert_eq!(
+            graph.shortest_path(4, 1, RouteWeight::Length, RouteAlgorithm::Astar),
+            None
+        );
+        assert_eq!(
+            graph.shortest_path(1, 99, RouteWeight::Length, RouteAlgorithm::Astar),
+            None
+        );
+        let route = graph
+            .shortest_path(2, 2, RouteWeight::Length, RouteAlgorithm::Dijkstra)
+            .unwrap();
+        assert_eq!(route.nodes.len(), 1);
+        assert_eq!(route.length_m, 0.0);
+    }
+}
@@ -1,5 +1,69 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 use h3o::CellIndex;
 
+pub const DEFAULT_BASE_DATE: &str = "2017-11-01";
+pub const DEFAULT_TIME_ZONE: &str = "America/Detroit";
+
+/// Converts VED-style `day_num` and `time_stamp` pairs into absolute date times.
+///
+/// `day_num` is a one-based, fractional day count in local wall-clock time: day 1.0 is
+/// midnight of the base date and the fractional part is the time of day at which the trip
+/// started (1.5 is noon of the base date). The trip start is resolved in the configured time
+/// zone, so it keeps its wall-clock time across DST changes. An ambiguous start (the repeated
+/// hour when clocks go back) resolves to the earlier instant, and a start inside the skipped
+/// hour when clocks go forward is moved forward by the gap. `time_stamp` is the elapsed trip
+/// time in milliseconds and is added as absolute time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeBase {
+    pub base_date: NaiveDate,
+    pub time_zone: Tz,
+}
+
+impl Default for TimeBase {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE_DATE, DEFAULT_TIME_ZONE).unwrap()
+    }
+}
+
+impl TimeBase {
+    pub fn new(base_date: &str, time_zone: &str) -> Result<Self> {
+        let base_date = NaiveDate::parse_from_str(base_date, "%Y-%m-%d")
+            .map_err(|e| anyhow!("Invalid base date '{}': {}", base_date, e))?;
+        let time_zone: Tz = time_zone
+            .parse()
+            .map_err(|e| anyhow!("Invalid time zone '{}': {}", time_zone, e))?;
+        Ok(Self {
+            base_date,
+            time_zone,
+        })
+    }
+
+    fn localize(&self, local: NaiveDateTime) -> DateTime<Tz> {
+        match self.time_zone.from_local_datetime(&local) {
+            LocalResult::Single(dt) => dt,
+            LocalResult::Ambiguous(earliest, _) => earliest,
+            LocalResult::None => {
+                // Inside a DST gap: use the offset in force before the gap
+                let before = self.time_zone.from_utc_datetime(&(local - Duration::days(1)));
+                let offset = Duration::seconds(before.offset().fix().local_minus_utc() as i64);
+                self.time_zone.from_utc_datetime(&(local - offset))
+            }
+        }
+    }
+
+    pub fn trip_start(&self, day_num: f64) -> DateTime<Tz> {
+        let offset_ms = ((day_num - 1.0) * 86_400_000.0).round() as i64;
+        let local = self.base_date.and_hms_opt(0, 0, 0).unwrap() + Duration::milliseconds(offset_ms);
+        self.localize(local)
+    }
+
+    pub fn date_time(&self, day_num: f64, time_stamp: i64) -> DateTime<Tz> {
+        self.trip_start(day_num) + Duration::milliseconds(time_stamp)
+    }
+}
+
 pub fn lat_lng_to_h3_12(lat: f64, lng: f64) -> u64 {
     let coord = h3o::LatLng::new(lat, lng).unwrap();
     let cell = coord.to_cell(h3o::Resolution::Twelve);
@@ -16,6 +80,27 @@ pub fn get_ring(h3: u64, k: u32) -> Vec<u64> {
         .collect()
 }
 
+pub fn h3_to_parent(h3: u64, resolution: u8) -> Option<u64> {
+    let resolution = h3o::Resolution::try_from(resolution).ok()?;
+    CellIndex::try_from(h3)
+        .ok()?
+        .parent(resolution)
+        .map(u64::from)
+}
+
+pub fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,4 +148,75 @@ mod tests {
         assert_eq!(ring.len(), 7); // Center + 6 neighbors
         assert!(ring.contains(&h3_index));
     }
+
+    #[test]
+    fn test_h3_to_parent() {
+        let h3_index = lat_lng_to_h3_12(42.2808, -83.7430);
+        let parent = h3_to_parent(h3_index, 8).unwrap();
+        let cell = h3o::CellIndex::try_from(parent).unwrap();
+        assert_eq!(cell.resolution(), h3o::Resolution::Eight);
+
+        // A cell is its own parent at the same resolution, and has none at a finer one
+        assert_eq!(h3_to_parent(h3_index, 12), Some(h3_index));
+        assert_eq!(h3_to_parent(parent, 12), None);
+    }
+
+    fn utc(dt: DateTime<Tz>) -> String {
+        dt.with_timezone(&chrono::Utc).to_rfc3339()
+    }
+
+    #[test]
+    fn test_time_base_fractional_day_num() {
+        let time_base = TimeBase::default();
+
+        // Day 1.0 is midnight of the base date, local time (EDT, UTC-4)
+        assert_eq!(utc(time_base.trip_start(1.0)), "2017-11-01T04:00:00+00:00");
+        // The fractional part is the time of day
+        assert_eq!(utc(time_base.trip_start(1.5)), "2017-11-01T16:00:00+00:00");
+        assert_eq!(
+            time_base.date_time(2.25, 90_000).to_rfc3339(),
+            "2017-11-02T06:01:30-04:00"
+        );
+    }
+
+    #[test]
+    fn test_time_base_dst_fall_back() {
+        let time_base = TimeBase::default();
+
+        // Clocks went back on 2017-11-05 at 02:00 EDT, so noon is UTC-5 from that day
+        assert_eq!(utc(time_base.trip_start(4.5)), "2017-11-04T16:00:00+00:00");
+        assert_eq!(utc(time_base.trip_start(5.5)), "2017-11-05T17:00:00+00:00");
+
+        // 01:30 happens twice and resolves to the earlier (EDT) instant
+        assert_eq!(utc(time_base.trip_start(5.0 + 1.5 / 24.0)), "2017-11-05T05:30:00+00:00");
+
+        // A trip starting at midnight and lasting three hours crosses the change
+        let dt = time_base.date_time(5.0, 3 * 3_600_000);
+        assert_eq!(utc(dt), "2017-11-05T07:00:00+00:00");
+        assert_eq!(dt.to_rfc3339(), "2017-11-05T02:00:00-05:00");
+    }
+
+    #[test]
+    fn test_time_base_dst_spring_forward() {
+        let time_base = TimeBase::new("2018-03-11", "America/Detroit").unwrap();
+
+        // 02:30 does not exist on 2018-03-11 and moves forward by the one hour gap
+        assert_eq!(
+            time_base.trip_start(1.0 + 2.5 / 24.0).to_rfc3339(),
+            "2018-03-11T03:30:00-04:00"
+        );
+    }
+
+    #[test]
+    fn test_time_base_invalid() {
+        assert!(TimeBase::new("2017-13-01", DEFAULT_TIME_ZONE).is_err());
+        assert!(TimeBase::new(DEFAULT_BASE_DATE, "Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), Some(2.5));
+    }
 }
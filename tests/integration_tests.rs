@@ -1,7 +1,11 @@
-use evedb::cli::{BuildCommandArgs, Cli, Commands};
+use evedb::cache::MatchCache;
+use evedb::cli::{BuildCommandArgs, Cli, Commands, MatchCommandArgs, Matcher};
 use evedb::commands::build::build_database;
 use evedb::commands::builders::node::build_nodes;
 use evedb::db::evedb::EveDb;
+use evedb::matcher::valhalla::{ValhallaSettings, build_manifest};
+use evedb::models::config::ValhallaConfig;
+use evedb::tools::{DEFAULT_BASE_DATE, DEFAULT_TIME_ZONE};
 use rust_xlsxwriter::{Workbook, XlsxError};
 use serde_json::json;
 use serial_test::serial;
@@ -71,10 +75,17 @@ async fn test_full_build_command() {
     let cli = Cli {
         repo_path: repo_path.to_str().unwrap().to_string(),
         db_path: db_path.to_str().unwrap().to_string(),
+        base_date: DEFAULT_BASE_DATE.to_string(),
+        time_zone: DEFAULT_TIME_ZONE.to_string(),
         verbose: true,
+        profile: None,
+        valhalla: ValhallaConfig::default(),
         command: Some(Commands::Build(BuildCommandArgs {
             no_clone: true,
             no_clean: true,
+            split_gap: None,
+            split_jump: None,
+            local_time: false,
         })),
     };
 
@@ -108,31 +119,9 @@ async fn test_full_build_command() {
     assert_eq!(trajectory_count, 1);
 }
 
-#[tokio::test]
-#[serial]
-async fn test_match_command_with_mock_valhalla() {
-    let mock_server = MockServer::start().await;
-    unsafe {
-        std::env::set_var("VALHALLA_URL", mock_server.uri());
-    }
-
-    let tmp_dir = tempdir().unwrap();
-    let db_path = tmp_dir.path().join("evedb.db");
-
-    // Initialize DB with a trajectory
-    let db = EveDb::new(db_path.to_str().unwrap());
-    db.create_signal_table().unwrap();
-    db.create_trajectory_table().unwrap();
-    db.create_node_table().unwrap();
-
-    // Insert a signal and trajectory
-    let conn = db.connect().unwrap();
-    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 1000, 42.1, -83.1, 42.1, -83.1, 1)", []).unwrap();
-    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 2000, 42.2, -83.2, 42.2, -83.2, 1)", []).unwrap();
-    db.insert_trajectories().unwrap();
-
-    // Mock Valhalla response
-    let valhalla_response = json!({
+/// `trace_route` response with one leg along the polyline6-encoded `shape`
+fn valhalla_response(shape: &str) -> serde_json::Value {
+    json!({
         "trip": {
             "status": 0,
             "status_message": "OK",
@@ -157,7 +146,7 @@ async fn test_match_command_with_mock_valhalla() {
                             "travel_type": "car"
                         }
                     ],
-                    "shape": "_qqhoA~t_o}C_ibE~hbE",
+                    "shape": shape,
                     "summary": {
                         "length": 0.5,
                         "time": 60,
@@ -183,7 +172,31 @@ async fn test_match_command_with_mock_valhalla() {
                 "max_lon": -83.1
             }
         }
-    });
+    })
+}
+
+#[tokio::test]
+#[serial]
+async fn test_match_command_with_mock_valhalla() {
+    let mock_server = MockServer::start().await;
+
+    let tmp_dir = tempdir().unwrap();
+    let db_path = tmp_dir.path().join("evedb.db");
+
+    // Initialize DB with a trajectory
+    let db = EveDb::new(db_path.to_str().unwrap());
+    db.create_signal_table().unwrap();
+    db.create_trajectory_table().unwrap();
+    db.create_node_table().unwrap();
+
+    // Insert a signal and trajectory
+    let conn = db.connect().unwrap();
+    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 1000, 42.1, -83.1, 42.1, -83.1, 1)", []).unwrap();
+    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 2000, 42.2, -83.2, 42.2, -83.2, 1)", []).unwrap();
+    db.insert_trajectories().unwrap();
+
+    // Mock Valhalla response
+    let valhalla_response = valhalla_response("_qqhoA~t_o}C_ibE~hbE");
 
     Mock::given(method("POST"))
         .and(path("/trace_route"))
@@ -207,11 +220,18 @@ async fn test_match_command_with_mock_valhalla() {
     let cli = Cli {
         repo_path: "".to_string(),
         db_path: db_path.to_str().unwrap().to_string(),
+        base_date: DEFAULT_BASE_DATE.to_string(),
+        time_zone: DEFAULT_TIME_ZONE.to_string(),
         verbose: true,
-        command: Some(Commands::Match),
+        profile: None,
+        valhalla: ValhallaConfig {
+            url: mock_server.uri(),
+            ..ValhallaConfig::default()
+        },
+        command: Some(Commands::Match(MatchCommandArgs::default())),
     };
 
-    build_nodes(&cli).await;
+    build_nodes(&cli, &MatchCommandArgs::default()).await;
 
     // Verification
     let node_count: i64 = conn
@@ -220,10 +240,6 @@ async fn test_match_command_with_mock_valhalla() {
     // valhalla-client might return more or fewer nodes depending on how it parses the response,
     // but we expect at least the nodes from the shape.
     assert!(node_count > 0);
-
-    unsafe {
-        std::env::remove_var("VALHALLA_URL");
-    }
 }
 
 #[tokio::test]
@@ -239,18 +255,19 @@ async fn test_match_command_with_missing_valhalla() {
     let cli = Cli {
         repo_path: "".to_string(),
         db_path: db_path.to_str().unwrap().to_string(),
+        base_date: DEFAULT_BASE_DATE.to_string(),
+        time_zone: DEFAULT_TIME_ZONE.to_string(),
         verbose: true,
-        command: Some(Commands::Match),
+        profile: None,
+        // Use a port that is NOT the mock server and unlikely to be used
+        valhalla: ValhallaConfig {
+            url: "http://127.0.0.1:12345/".to_string(),
+            ..ValhallaConfig::default()
+        },
+        command: Some(Commands::Match(MatchCommandArgs::default())),
     };
 
-    // Use a port that is NOT the mock server and unlikely to be used
-    unsafe {
-        std::env::set_var("VALHALLA_URL", "http://127.0.0.1:12345/");
-    }
-    build_nodes(&cli).await;
-    unsafe {
-        std::env::remove_var("VALHALLA_URL");
-    }
+    build_nodes(&cli, &MatchCommandArgs::default()).await;
 
     let conn = db.connect().unwrap();
     let table_exists: bool = conn
@@ -267,3 +284,81 @@ async fn test_match_command_with_missing_valhalla() {
         "Node table should not have been created if Valhalla is missing"
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn test_match_command_offline_from_cache() {
+    let tmp_dir = tempdir().unwrap();
+    let db_path = tmp_dir.path().join("evedb.db");
+    let cache_dir = tmp_dir.path().join("cache");
+
+    let db = EveDb::new(db_path.to_str().unwrap());
+    db.create_signal_table().unwrap();
+    db.create_trajectory_table().unwrap();
+
+    let conn = db.connect().unwrap();
+    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 1000, 42.1, -83.1, 42.1, -83.1, 1)", []).unwrap();
+    conn.execute("INSERT INTO signal (day_num, vehicle_id, trip_id, time_stamp, latitude, longitude, match_latitude, match_longitude, match_type) VALUES (1, 101, 100, 2000, 42.2, -83.2, 42.2, -83.2, 1)", []).unwrap();
+    db.insert_trajectories().unwrap();
+
+    // Fixture standing in for the Valhalla response to this trajectory's request
+    let args = MatchCommandArgs {
+        no_filter: true,
+        cache_dir: Some(cache_dir.to_str().unwrap().to_string()),
+        offline: true,
+        ..MatchCommandArgs::default()
+    };
+    let way_points = db.get_way_points(1).unwrap();
+    let key = MatchCache::key(&build_manifest(
+        &ValhallaSettings::default(),
+        way_points.iter().map(|p| p.into()),
+    )).unwrap();
+    // Through (42.1, -83.1), (42.15, -83.15) and (42.2, -83.2)
+    let body = serde_json::to_vec(&valhalla_response("_qqhoA~t_o}C_t`B~s`B_t`B~s`B")).unwrap();
+    MatchCache::new(cache_dir.to_str().unwrap())
+        .unwrap()
+        .put(&key, &body)
+        .unwrap();
+
+    let cli = Cli {
+        repo_path: "".to_string(),
+        db_path: db_path.to_str().unwrap().to_string(),
+        base_date: DEFAULT_BASE_DATE.to_string(),
+        time_zone: DEFAULT_TIME_ZONE.to_string(),
+        verbose: true,
+        profile: None,
+        // No Valhalla is reachable here
+        valhalla: ValhallaConfig {
+            url: "http://127.0.0.1:12345/".to_string(),
+            ..ValhallaConfig::default()
+        },
+        command: Some(Commands::Match(args.clone())),
+    };
+    build_nodes(&cli, &args).await;
+
+    let node_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM node", [], |r| r.get::<_, i64>(0))
+        .unwrap();
+    assert_eq!(node_count, 3);
+    let edge_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM traj_edge", [], |r| r.get::<_, i64>(0))
+        .unwrap();
+    assert_eq!(edge_count, 2);
+    assert_eq!(db.get_search_radius(1).unwrap(), Some(100.0));
+
+    // The HMM matcher re-matches the trajectory on the network just built
+    let args = MatchCommandArgs {
+        no_filter: true,
+        matcher: Matcher::Hmm,
+        ..MatchCommandArgs::default()
+    };
+    build_nodes(&cli, &args).await;
+    let shape: Vec<(f64, f64)> = conn
+        .prepare("SELECT n.latitude, n.longitude FROM traj_node t JOIN node n ON n.node_id = t.node_id ORDER BY t.seq")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(shape, vec![(42.1, -83.1), (42.15, -83.15), (42.2, -83.2)]);
+}